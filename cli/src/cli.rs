@@ -14,6 +14,7 @@ pub fn build_cli() -> App<'static, 'static> {
       "You can also run `spectre SUBCOMMAND -h` to get more information about that subcommand.",
     )
     .subcommand(migration_subcommand())
+    .subcommand(db_subcommand())
     .subcommand(seed_subcommand())
     .subcommand(init_subcommand())
     .subcommand(completions_subcommand())
@@ -67,6 +68,34 @@ pub fn init_subcommand<'a, 'b>() -> App<'a, 'b> {
     ])
 }
 
+fn force_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("force").long("force").help(
+    "Remove migrations without a down script from the history instead of refusing to revert them",
+  )
+}
+
+fn format_arg<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("format")
+    .long("format")
+    .takes_value(true)
+    .possible_values(&["text", "json"])
+    .default_value("text")
+    .help("Print the result as text or as JSON")
+}
+
+fn dry_run_args<'a, 'b>() -> [Arg<'a, 'b>; 2] {
+  [
+    Arg::with_name("dry-run").long("dry-run").help(
+      "Print the SQL that would be executed, history bookkeeping included, without running it",
+    ),
+    Arg::with_name("output")
+      .long("output")
+      .takes_value(true)
+      .value_name("FILE")
+      .help("Write the SQL that would be executed to a file instead of running it"),
+  ]
+}
+
 fn migration_subcommand<'a, 'b>() -> App<'a, 'b> {
   SubCommand::with_name("migrate")
     .about(
@@ -76,38 +105,141 @@ fn migration_subcommand<'a, 'b>() -> App<'a, 'b> {
     .setting(AppSettings::VersionlessSubcommands)
     .arg(connection_arg())
     .subcommand(
-      SubCommand::with_name("save").about("Save the details of the latest changes in the schema in a migration file which would then be applied using the `up` command"),
+      SubCommand::with_name("save")
+        .about("Save the details of the latest changes in the schema in a migration file which would then be applied using the `up` command")
+        .arg(
+          Arg::with_name("name")
+            .help("Describe the changes, the migration is named after it")
+            .required(false),
+        ),
     )
     .subcommand(
-      SubCommand::with_name("up").about("Run all pending migration and create the tables"),
+      SubCommand::with_name("up")
+        .about("Run all pending migration and create the tables")
+        .args(&dry_run_args()),
     )
     .subcommand(
       SubCommand::with_name("reset")
-        .about("Rollback all application migration and empty the migration directory"),
+        .about("Rollback all applied migrations except the baselined ones")
+        .arg(force_arg())
+        .args(&dry_run_args()),
     )
     .subcommand(
       SubCommand::with_name("rollback")
-        .about("Reverts the latest migration operation(s)")
+        .about("Reverts the latest batch of migrations")
         .arg(
           Arg::with_name("step")
-            .help("Specify the number of steps to rollback")
+            .help("Rollback this number of migrations instead of the latest batch")
             .required(false),
-        ),
+        )
+        .arg(
+          Arg::with_name("batch")
+            .long("batch")
+            .takes_value(true)
+            .conflicts_with("step")
+            .help("Rollback every migration of the given batch, batch 0 holds the baselined ones"),
+        )
+        .args(&dry_run_args())
+        .arg(force_arg().help(
+          "Remove migrations without a down script from the history instead of refusing to \
+           revert them, and let `--batch 0` revert the baselined migrations",
+        )),
     )
     .subcommand(
       SubCommand::with_name("fresh")
-        .about("Reset all migrations and run again")
+        .about("Drop every table, view, sequence and type, then run all migrations again")
         .arg(
           Arg::with_name("seed")
-            .help("Should run the seeds after migrations")
-            .required(false),
-        ),
+            .long("seed")
+            .help("Should run the seeds after migrations"),
+        )
+        .arg(
+          Arg::with_name("force")
+            .long("force")
+            .help("Run even when the connection or environment is marked as production"),
+        )
+        .args(&dry_run_args()),
+    )
+    .subcommand(
+      SubCommand::with_name("list")
+        .about(
+          "Lists all available migrations, marking those that have been applied to the database.",
+        )
+        .arg(format_arg()),
     )
-    .subcommand(SubCommand::with_name("list").about(
-      "Lists all available migrations, marking those that have been applied to the database.",
+    .subcommand(
+      SubCommand::with_name("pending")
+        .about("Returns true if there are any pending migrations.")
+        .arg(format_arg()),
+    )
+    .subcommand(SubCommand::with_name("repair").about(
+      "Record the current checksum of applied migrations that were edited on purpose",
     ))
     .subcommand(
-      SubCommand::with_name("pending").about("Returns true if there are any pending migrations."),
+      SubCommand::with_name("baseline")
+        .about("Record the migrations up to the given one as applied without running them")
+        .arg(
+          Arg::with_name("name")
+            .help("The last migration the database already has")
+            .required(true),
+        ),
+    )
+    .subcommand(
+      SubCommand::with_name("squash")
+        .about("Replace the migrations up to the given one with a baseline creating the schema they leave")
+        .arg(
+          Arg::with_name("until")
+            .long("until")
+            .help("The last migration to squash")
+            .takes_value(true)
+            .required(true),
+        )
+        .arg(
+          Arg::with_name("scratch")
+            .long("scratch")
+            .help("An empty database of the same provider to replay the migrations in, an in-memory one on SQLite")
+            .takes_value(true),
+        ),
+    )
+    .subcommand(
+      SubCommand::with_name("lint")
+        .about("Check pending migrations for statements that lose data or lock tables")
+        .arg(
+          Arg::with_name("all")
+            .long("all")
+            .help("Check every migration, not only the pending ones"),
+        ),
+    )
+    .setting(AppSettings::SubcommandRequiredElseHelp)
+}
+
+fn db_subcommand<'a, 'b>() -> App<'a, 'b> {
+  SubCommand::with_name("db")
+    .about("A group of commands working on the database schema directly.")
+    .setting(AppSettings::VersionlessSubcommands)
+    .arg(connection_arg())
+    .subcommand(
+      SubCommand::with_name("pull")
+        .about("Write the schema file describing the tables of the database")
+        .arg(
+          Arg::with_name("force")
+            .long("force")
+            .help("Overwrite the schema file when it exists"),
+        )
+        .arg(
+          Arg::with_name("print")
+            .long("print")
+            .help("Print the schema instead of writing the schema file"),
+        ),
+    )
+    .subcommand(
+      SubCommand::with_name("push")
+        .about("Apply the schema file to the database without writing a migration")
+        .arg(
+          Arg::with_name("accept-data-loss")
+            .long("accept-data-loss")
+            .help("Push changes that may lose data without asking"),
+        ),
     )
     .setting(AppSettings::SubcommandRequiredElseHelp)
 }
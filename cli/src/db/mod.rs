@@ -0,0 +1,142 @@
+use crate::directory::find_project_root;
+use crate::util::{connect, connection_name, connection_option, handle_error};
+use clap::ArgMatches;
+use config::ConfigManager;
+use connection::Session;
+use migration::Dialect;
+use schema::{diff, own_transaction, statements_against};
+use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+
+pub fn db_command(matches: &ArgMatches) {
+  let (name, sub) = matches.subcommand();
+  let run = match name {
+    "pull" => pull_command,
+    "push" => push_command,
+    _ => unreachable!("clap requires a subcommand"),
+  };
+  run(matches, sub.unwrap()).unwrap_or_else(handle_error)
+}
+
+fn pull_command(matches: &ArgMatches, sub: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let root = find_project_root()?;
+  let config = ConfigManager::from(&root)?.config();
+  let option = connection_option(&config, connection_name(matches, Some(sub)))?;
+  let connection = connect(&option)?;
+  let database = connection.introspect()?;
+  let pulled = schema::pull(&database, Dialect::from(connection.provider()));
+
+  for warning in pulled.warnings.iter() {
+    eprintln!("warning: {}", warning);
+  }
+  // keep what was left out in the file, next to what replaces it
+  let mut source = String::new();
+  if !pulled.warnings.is_empty() {
+    source.push_str(&format!(
+      "// Pulled from the `{}` connection, leaving out:\n",
+      connection.name()
+    ));
+    for warning in pulled.warnings.iter() {
+      source.push_str(&format!("// - {}\n", warning));
+    }
+    source.push('\n');
+  }
+  source.push_str(&pulled.schema.to_string());
+
+  if sub.is_present("print") {
+    print!("{}", source);
+    return Ok(());
+  }
+  let path = root.join(config.schema_path());
+  if path.exists() && !sub.is_present("force") {
+    return Err(
+      format!(
+        "`{}` already exists, pass `--force` to overwrite it",
+        config.schema_path()
+      )
+      .into(),
+    );
+  }
+  fs::write(&path, source)?;
+  println!(
+    "Wrote {} models and {} enums to {}",
+    pulled.schema.models.len(),
+    pulled.schema.enums.len(),
+    config.schema_path()
+  );
+  Ok(())
+}
+
+fn push_command(matches: &ArgMatches, sub: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let root = find_project_root()?;
+  let config = ConfigManager::from(&root)?.config();
+  let option = connection_option(&config, connection_name(matches, Some(sub)))?;
+  let connection = connect(&option)?;
+  let dialect = Dialect::from(connection.provider());
+
+  let target = schema::read(&root.join(config.schema_path()))?;
+  let database = connection.introspect()?;
+  let current = schema::pull(&database, dialect).schema;
+  let changes = diff(&current, &target, dialect);
+  if changes.is_empty() {
+    println!("The database is already in sync with the schema.");
+    return Ok(());
+  }
+
+  let destructive: Vec<_> = changes.iter().filter(|c| c.is_destructive()).collect();
+  if !destructive.is_empty() && !sub.is_present("accept-data-loss") {
+    println!("These changes may lose data:");
+    for change in destructive {
+      println!("  - {}", change);
+    }
+    if !confirm("Push them anyway?")? {
+      return Err(
+        "Nothing was pushed, pass `--accept-data-loss` to push changes that may lose data".into(),
+      );
+    }
+  }
+
+  let mut session = connection.session()?;
+  // SQLite table copies open and commit their own transaction
+  let own_transaction = own_transaction(&changes, dialect);
+  let transaction = dialect.transactional_ddl() && !own_transaction;
+  if transaction {
+    session.batch_execute("BEGIN")?;
+  }
+  let result = statements_against(&changes, &target, &database, dialect)
+    .iter()
+    .try_for_each(|statement| session.batch_execute(statement));
+  match result {
+    Ok(_) if transaction => session.batch_execute("COMMIT")?,
+    Err(error) if transaction || own_transaction => {
+      let _ = session.batch_execute("ROLLBACK");
+      if own_transaction {
+        let _ = session.batch_execute("PRAGMA foreign_keys = ON");
+      }
+      return Err(error.into());
+    }
+    result => result?,
+  }
+
+  for change in changes.iter() {
+    println!("  - {}", change);
+  }
+  println!(
+    "Pushed the schema to the `{}` connection",
+    connection.name()
+  );
+  Ok(())
+}
+
+/// Ask a yes or no question, answering no when nobody is there to answer it
+fn confirm(question: &str) -> io::Result<bool> {
+  if !io::stdin().is_terminal() {
+    return Ok(false);
+  }
+  print!("{} [y/N] ", question);
+  io::stdout().flush()?;
+  let mut answer = String::new();
+  io::stdin().read_line(&mut answer)?;
+  Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
@@ -3,7 +3,7 @@ use crate::util::handle_error;
 use clap::ArgMatches;
 use config::ConfigManager;
 use connection::Provider;
-use std::{error::Error, fs, path::PathBuf, str::FromStr};
+use std::{error::Error, fs, path::Path, str::FromStr};
 
 pub fn init_command(matches: &ArgMatches) {
   let base_path = match find_project_root() {
@@ -15,7 +15,7 @@ pub fn init_command(matches: &ArgMatches) {
   let mut config = ConfigManager::new();
 
   if let Some(name) = name {
-    config.set_config_name(&name);
+    config.set_config_name(name);
   }
 
   config.create(&base_path).unwrap_or_else(handle_error);
@@ -48,7 +48,7 @@ pub struct DockerCompose {
 }
 
 pub fn create_docker_compose_file(
-  base: &PathBuf,
+  base: &Path,
   containers: Vec<&str>,
 ) -> Result<(), Box<dyn Error>> {
   use std::io::Write;
@@ -113,7 +113,7 @@ mod tests {
   use super::{create_docker_compose_file, init_command};
   use crate::cli::init_subcommand;
   use clap::App;
-  use std::{env, fs, panic, path::PathBuf};
+  use std::{env, fs, path::PathBuf};
   use tempfile::tempdir;
 
   #[test]
@@ -122,11 +122,11 @@ mod tests {
     let base_path = PathBuf::from(&cur_dir).join("tests/default_init");
     fs::create_dir(&base_path).unwrap();
     env::set_current_dir(&base_path).unwrap();
-    fs::File::create(&base_path.join("Cargo.toml")).unwrap();
+    fs::File::create(base_path.join("Cargo.toml")).unwrap();
     let matches = App::new("init-test")
       .subcommand(init_subcommand())
       .get_matches_from(vec!["", "init"]);
-    init_command(&matches.subcommand_matches("init").unwrap());
+    init_command(matches.subcommand_matches("init").unwrap());
     assert!(&base_path.join("spectre.yaml").exists());
     fs::remove_dir_all(&base_path).unwrap();
     env::set_current_dir(&cur_dir).unwrap();
@@ -214,7 +214,7 @@ mod tests {
     let dir = tempdir().unwrap();
     let temp_path = dir.path();
     let dbs = vec!["postgres", "mysql", "sqlite"];
-    assert!(create_docker_compose_file(&temp_path.into(), dbs).is_ok());
+    assert!(create_docker_compose_file(temp_path, dbs).is_ok());
     assert!(temp_path.join("docker-compose.yml").exists());
     dir.close().unwrap();
   }
@@ -227,7 +227,7 @@ mod tests {
 
     fs::File::create(&docker_file_path).unwrap();
     let dbs = vec!["postgres", "mysql", "sqlite"];
-    assert!(create_docker_compose_file(&temp_path.into(), dbs).is_err());
+    assert!(create_docker_compose_file(temp_path, dbs).is_err());
     dir.close().unwrap();
   }
 }
@@ -4,22 +4,35 @@ extern crate config;
 extern crate connection;
 extern crate directory;
 extern crate dotenv;
+extern crate migration;
+extern crate schema;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 
 mod cli;
+mod db;
 mod init;
 mod migrate;
 mod seed;
 mod util;
 
+use self::db::db_command;
 use self::init::init_command;
 use self::migrate::migrate_command;
 use self::seed::seed_command;
 use clap::{crate_name, ArgMatches, Shell};
+use migration::Migration;
 use std::io::stdout;
 
 pub fn main() {
+  main_with(Vec::new())
+}
+
+/// Run the command line with migrations written in Rust, for a binary of the
+/// project built to register them, e.g.
+/// `cli::main_with(vec![Migration::from_code(BackfillNames).unwrap()])`
+pub fn main_with(migrations: Vec<Migration>) {
   use self::dotenv::dotenv;
   dotenv().ok();
 
@@ -27,7 +40,8 @@ pub fn main() {
 
   match matches.subcommand() {
     ("init", Some(matches)) => init_command(matches),
-    ("migrate", Some(matches)) => migrate_command(matches),
+    ("migrate", Some(matches)) => migrate_command(matches, migrations),
+    ("db", Some(matches)) => db_command(matches),
     ("seed", Some(matches)) => seed_command(matches),
     ("completions", Some(matches)) => completions_command(matches),
     _ => unreachable!("Unsupported command"),
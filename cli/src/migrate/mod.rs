@@ -1,5 +1,463 @@
+mod save;
+mod squash;
+mod status;
+
+use self::save::save;
+use self::squash::squash;
+use self::status::{table, MigrationStatus, State};
+use crate::directory::find_project_root;
+use crate::seed::run_seeds;
+use crate::util::{connect, connection_name, connection_option, handle_error};
 use clap::ArgMatches;
+use config::ConfigManager;
+use connection::{Connection, ConnectionOption, Datasource};
+use migration::{
+  discover, lint, script, Dialect, DryRun, Migration, MigrationRun, Migrator, RollbackTarget,
+  Severity, BASELINE_BATCH, LINT_IGNORE,
+};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+/// The connection and migrations a migrate subcommand works with
+struct MigrationContext {
+  connection: Connection,
+  migrator: Migrator,
+  migration_dir: PathBuf,
+  seed_dir: PathBuf,
+  // the connection or the whole environment is marked as production
+  production: bool,
+}
+
+impl MigrationContext {
+  fn load(
+    matches: &ArgMatches,
+    subcommand: Option<&ArgMatches>,
+    code: Vec<Migration>,
+  ) -> Result<Self, Box<dyn Error>> {
+    let root = find_project_root()?;
+    let config = ConfigManager::from(&root)?.config();
+    let option = connection_option(&config, connection_name(matches, subcommand))?;
+    let connection = connect(&option)?;
+
+    let migration_dir = root.join(config.migration_dir());
+    let migrations = discover(&migration_dir)?;
+    let mut migrator = Migrator::new(Dialect::from(connection.provider()), migrations);
+    for migration in code {
+      migrator = migrator.migration(migration);
+    }
+    if let Some(timeout) = config.migration_lock_timeout() {
+      migrator = migrator.lock_timeout(timeout);
+    }
+    Ok(MigrationContext {
+      connection,
+      migrator,
+      migration_dir,
+      seed_dir: root.join(config.seed_dir()),
+      production: config.is_production() || option.production == Some(true),
+    })
+  }
+}
+
+pub fn migrate_command(matches: &ArgMatches, code: Vec<Migration>) {
+  let (name, sub) = matches.subcommand();
+  // saving only reads the schema file, there is no database to connect to
+  if name == "save" {
+    return save_command(matches, sub).unwrap_or_else(handle_error);
+  }
+  let run = match name {
+    "up" => up_command,
+    "rollback" => rollback_command,
+    "reset" => reset_command,
+    "list" => list_command,
+    "pending" => pending_command,
+    "repair" => repair_command,
+    "lint" => lint_command,
+    "squash" => squash_command,
+    "baseline" => baseline_command,
+    "fresh" => fresh_command,
+    _ => unreachable!("clap requires a subcommand"),
+  };
+  let context = MigrationContext::load(matches, sub, code).unwrap_or_else(handle_error);
+  run(context, sub.unwrap()).unwrap_or_else(handle_error)
+}
+
+fn save_command(matches: &ArgMatches, sub: Option<&ArgMatches>) -> Result<(), Box<dyn Error>> {
+  let root = find_project_root()?;
+  let config = ConfigManager::from(&root)?.config();
+  let option = connection_option(&config, connection_name(matches, sub))?;
+
+  let description = sub.and_then(|m| m.value_of("name")).unwrap_or("schema");
+  let saved = save(
+    &root.join(config.schema_path()),
+    &root.join(config.migration_dir()),
+    Dialect::from(&option.datasource.provider),
+    description,
+  )?;
+  match saved {
+    Some(saved) => {
+      println!("Saved migration {}", saved.name);
+      if saved.changes.iter().any(|change| change.is_destructive()) {
+        println!("The migration drops or converts columns, existing data may be lost.");
+      }
+    }
+    None => println!("No schema changes to save."),
+  }
+  Ok(())
+}
+
+fn up_command(context: MigrationContext, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let mut session = context.connection.session()?;
+  if is_dry_run(matches) {
+    let (_, statements) = context
+      .migrator
+      .dry_run(&mut session, |migrator, session| migrator.up(session))
+      .map_err(with_hint)?;
+    return print_script(matches, context.migrator.dialect(), &statements);
+  }
+
+  let runs = context.migrator.up(&mut session).map_err(with_hint)?;
+
+  if runs.is_empty() {
+    println!("Nothing to migrate.");
+  }
+  print_applied(&runs);
+  Ok(())
+}
+
+/// Report applied migrations under their batch, and the squashed baselines
+/// only recorded in place of migrations the database already ran
+fn print_applied(runs: &[MigrationRun]) {
+  if let Some(run) = runs.iter().find(|run| run.batch != BASELINE_BATCH) {
+    println!("Migrating batch {}", run.batch);
+  }
+  for run in runs.iter() {
+    if run.batch == BASELINE_BATCH {
+      println!("Recorded {} as baseline", run.name);
+    } else {
+      println!("Applied {} ({}ms)", run.name, run.duration.as_millis());
+    }
+  }
+}
+
+fn rollback_command(context: MigrationContext, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let target = if let Some(step) = matches.value_of("step") {
+    RollbackTarget::Steps(parse_number(step, "step")?)
+  } else if let Some(batch) = matches.value_of("batch") {
+    RollbackTarget::Batch(parse_number(batch, "batch")?)
+  } else {
+    RollbackTarget::LastBatch
+  };
+  let force = matches.is_present("force");
+  let mut session = context.connection.session()?;
+  if is_dry_run(matches) {
+    let (_, statements) = context
+      .migrator
+      .dry_run(&mut session, |migrator, session| {
+        migrator.rollback(session, target, force)
+      })
+      .map_err(with_hint)?;
+    return print_script(matches, context.migrator.dialect(), &statements);
+  }
+
+  let runs = context
+    .migrator
+    .rollback(&mut session, target, force)
+    .map_err(with_hint)?;
+  if runs.is_empty() && target == RollbackTarget::LastBatch {
+    let applied = context.migrator.applied(&mut session)?;
+    if applied.last().is_some_and(|last| last.is_repeatable()) {
+      println!("The last batch only re-ran repeatable migrations, which are never rolled back.");
+      return Ok(());
+    }
+  }
+  print_reverted(&runs);
+  Ok(())
+}
+
+fn reset_command(context: MigrationContext, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let force = matches.is_present("force");
+  let mut session = context.connection.session()?;
+  if is_dry_run(matches) {
+    let (_, statements) = context
+      .migrator
+      .dry_run(&mut session, |migrator, session| {
+        migrator.reset(session, force)
+      })
+      .map_err(with_hint)?;
+    return print_script(matches, context.migrator.dialect(), &statements);
+  }
+
+  let runs = context
+    .migrator
+    .reset(&mut session, force)
+    .map_err(with_hint)?;
+  print_reverted(&runs);
+  Ok(())
+}
+
+fn fresh_command(context: MigrationContext, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let dry_run = is_dry_run(matches);
+  if context.production && !matches.is_present("force") && !dry_run {
+    return Err(
+      format!(
+        "Refusing to drop every table of the production connection `{}`, pass `--force` to run it anyway",
+        context.connection.name()
+      )
+      .into(),
+    );
+  }
+
+  let mut session = context.connection.session()?;
+  if dry_run {
+    let (_, mut statements) = context
+      .migrator
+      .dry_run(&mut session, |migrator, session| migrator.fresh(session))
+      .map_err(with_hint)?;
+    if matches.is_present("seed") {
+      let mut recorder = DryRun::new(&mut session);
+      run_seeds(&mut recorder, context.migrator.dialect(), &context.seed_dir)?;
+      statements.extend(recorder.into_statements());
+    }
+    return print_script(matches, context.migrator.dialect(), &statements);
+  }
+
+  let (dropped, runs) = context.migrator.fresh(&mut session).map_err(with_hint)?;
+  for object in dropped.iter() {
+    println!("Dropped {}", object);
+  }
+  print_applied(&runs);
+
+  if matches.is_present("seed") {
+    for seed in run_seeds(&mut session, context.migrator.dialect(), &context.seed_dir)? {
+      println!("Seeded {}", seed);
+    }
+  }
+  Ok(())
+}
+
+fn list_command(context: MigrationContext, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let mut session = context.connection.session()?;
+  let applied = context.migrator.applied(&mut session)?;
+  let statuses = MigrationStatus::collect(&context.migrator, &applied);
+
+  if matches.value_of("format") == Some("json") {
+    println!("{}", serde_json::to_string_pretty(&statuses)?);
+  } else if statuses.is_empty() {
+    println!("No migrations found.");
+  } else {
+    println!("{}", table(&statuses));
+  }
+  // the table shows which files changed, still fail so scripts notice
+  context.migrator.verify(&applied).map_err(with_hint)?;
+  Ok(())
+}
+
+/// Print the pending migrations and exit with 0 when there are any, 1 otherwise
+fn pending_command(context: MigrationContext, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let mut session = context.connection.session()?;
+  let applied = context.migrator.applied(&mut session)?;
+  context.migrator.verify(&applied).map_err(with_hint)?;
+  let pending: Vec<MigrationStatus> = MigrationStatus::collect(&context.migrator, &applied)
+    .into_iter()
+    .filter(|status| status.state == State::Pending)
+    .collect();
+
+  if matches.value_of("format") == Some("json") {
+    println!("{}", serde_json::to_string_pretty(&pending)?);
+  } else {
+    if pending.is_empty() {
+      println!("Nothing to migrate.");
+    }
+    for status in pending.iter() {
+      println!("{}", status.name);
+    }
+  }
+
+  if pending.is_empty() {
+    drop(session);
+    ::std::process::exit(1);
+  }
+  Ok(())
+}
+
+fn repair_command(context: MigrationContext, _matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let mut session = context.connection.session()?;
+  let repaired = context.migrator.repair(&mut session)?;
+
+  if repaired.is_empty() {
+    println!("Nothing to repair.");
+  }
+  for migration in repaired.iter() {
+    println!("Recorded the new checksum of {}", migration.name);
+  }
+  Ok(())
+}
+
+fn baseline_command(context: MigrationContext, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let name = matches.value_of("name").unwrap();
+  let mut session = context.connection.session()?;
+  let marked = context.migrator.baseline(&mut session, name)?;
+
+  if marked.is_empty() {
+    println!(
+      "Nothing to baseline, every migration up to {} is applied.",
+      name
+    );
+  }
+  for migration in marked.iter() {
+    println!("Recorded {} as applied", migration.name);
+  }
+  Ok(())
+}
+
+fn squash_command(context: MigrationContext, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let until = matches.value_of("until").unwrap();
+  let scratch = scratch_connection(&context.connection, matches.value_of("scratch"))?;
+  let squashed = squash(&scratch, &context.migrator, &context.migration_dir, until)?;
+  println!(
+    "Squashed {} migrations into {}",
+    squashed.replaced.len(),
+    squashed.name
+  );
+  println!("Databases that applied them record the baseline as applied on the next `migrate up`.");
+  Ok(())
+}
+
+/// The connection `migrate squash` replays the migrations in, never the one
+/// being migrated
+fn scratch_connection(
+  connection: &Connection,
+  name: Option<&str>,
+) -> Result<Connection, Box<dyn Error>> {
+  let dialect = Dialect::from(connection.provider());
+  let option = match name {
+    Some(name) if name == connection.name() => {
+      return Err("The scratch connection must be another database than the one migrated".into())
+    }
+    Some(name) => {
+      let root = find_project_root()?;
+      connection_option(&ConfigManager::from(&root)?.config(), name)?
+    }
+    None if dialect == Dialect::SQLite => ConnectionOption {
+      datasource: Datasource {
+        provider: connection.provider().clone(),
+        url: "sqlite://:memory:".to_string(),
+      },
+      ..ConnectionOption::default()
+    },
+    None => {
+      return Err(
+        "Squashing replays the migrations in an empty database, pick one with `--scratch <connection>`"
+          .into(),
+      )
+    }
+  };
+  if Dialect::from(&option.datasource.provider) != dialect {
+    return Err("The scratch connection must use the same provider as the one migrated".into());
+  }
+  connect(&option)
+}
+
+fn lint_command(context: MigrationContext, matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let migrations: Vec<&Migration> = if matches.is_present("all") {
+    context.migrator.migrations().iter().collect()
+  } else {
+    let mut session = context.connection.session()?;
+    context.migrator.pending(&mut session).map_err(with_hint)?
+  };
+
+  let mut findings = Vec::new();
+  for migration in migrations.iter() {
+    findings.extend(lint(migration, context.migrator.dialect())?);
+  }
+  if findings.is_empty() {
+    println!(
+      "No problems found in {}.",
+      count(migrations.len(), "migration")
+    );
+    return Ok(());
+  }
+
+  for finding in findings.iter() {
+    println!("{}", finding);
+  }
+  let errors = findings
+    .iter()
+    .filter(|finding| finding.rule.severity() == Severity::Error)
+    .count();
+  println!(
+    "\nFound {} and {}. Silence a rule for one statement with `{} <rule>` above it.",
+    count(errors, "error"),
+    count(findings.len() - errors, "warning"),
+    LINT_IGNORE
+  );
+  if errors > 0 {
+    ::std::process::exit(1);
+  }
+  Ok(())
+}
+
+fn count(n: usize, noun: &str) -> String {
+  if n == 1 {
+    format!("1 {}", noun)
+  } else {
+    format!("{} {}s", n, noun)
+  }
+}
+
+/// Whether to print the SQL of the command instead of running it, `--output`
+/// implies it
+fn is_dry_run(matches: &ArgMatches) -> bool {
+  matches.is_present("dry-run") || matches.is_present("output")
+}
+
+fn print_script(
+  matches: &ArgMatches,
+  dialect: Dialect,
+  statements: &[String],
+) -> Result<(), Box<dyn Error>> {
+  let script = script(statements, dialect);
+  match matches.value_of("output") {
+    Some(path) => {
+      fs::write(path, &script).map_err(|e| format!("{}: {}", path, e))?;
+      println!("Wrote {} statements to {}", statements.len(), path);
+    }
+    None => print!("{}", script),
+  }
+  Ok(())
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+  value
+    .parse::<T>()
+    .map_err(|_| format!("Invalid {} `{}`, expected a number", name, value))
+}
+
+fn print_reverted(runs: &[MigrationRun]) {
+  if runs.is_empty() {
+    println!("Nothing to rollback.");
+  }
+  for run in runs.iter() {
+    println!("Rolled back {} ({}ms)", run.name, run.duration.as_millis());
+  }
+}
 
-pub fn migrate_command(_matches: &ArgMatches) {
-  unimplemented!()
+fn with_hint(error: migration::Error) -> Box<dyn Error> {
+  match error {
+    migration::Error::MissingDownScript(_) => format!(
+      "{}, pass `--force` to remove it from the history without reverting it",
+      error
+    )
+    .into(),
+    migration::Error::BaselineRollback => {
+      format!("{}, pass `--force` to revert them anyway", error).into()
+    }
+    migration::Error::ChecksumMismatch { .. } => format!(
+      "{}, run `spectre migrate repair` to record the new checksum if the edit was intended",
+      error
+    )
+    .into(),
+    error => error.into(),
+  }
 }
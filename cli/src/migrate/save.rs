@@ -0,0 +1,160 @@
+use migration::{script, Dialect, NO_TRANSACTION};
+use schema::{diff, own_transaction, statements, Change, Schema};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The copy of the schema file kept with each saved migration, the state the
+/// next `migrate save` diffs against
+pub const SNAPSHOT: &str = "schema.spectre";
+
+/// What `migrate save` wrote
+pub struct Saved {
+  pub name: String,
+  pub changes: Vec<Change>,
+}
+
+/// Diff the schema file against the snapshot of the latest saved migration
+/// and write the changes as a new migration directory, unless nothing changed
+pub fn save(
+  schema_path: &Path,
+  migration_dir: &Path,
+  dialect: Dialect,
+  description: &str,
+) -> Result<Option<Saved>, Box<dyn Error>> {
+  let source = fs::read_to_string(schema_path)
+    .map_err(|e| schema::Error::IoError(schema_path.to_path_buf(), e))?;
+  let next = schema::parse(&source)?;
+  let previous = match latest_snapshot(migration_dir)? {
+    Some(path) => schema::read(&path)?,
+    None => Schema::default(),
+  };
+
+  let changes = diff(&previous, &next, dialect);
+  if changes.is_empty() {
+    return Ok(None);
+  }
+  let up = migration_script(&changes, &next, dialect);
+  let down = migration_script(&diff(&next, &previous, dialect), &previous, dialect);
+
+  let name = format!("{}_{}", next_timestamp(migration_dir)?, slug(description));
+  let dir = migration_dir.join(&name);
+  fs::create_dir_all(&dir)?;
+  fs::write(dir.join("up.sql"), up)?;
+  fs::write(dir.join("down.sql"), down)?;
+  fs::write(dir.join(SNAPSHOT), source)?;
+  Ok(Some(Saved { name, changes }))
+}
+
+/// The script of a migration making the changes, run outside of a transaction
+/// when its statements commit their own
+pub(crate) fn migration_script(changes: &[Change], schema: &Schema, dialect: Dialect) -> String {
+  let sql = script(&statements(changes, schema, dialect), dialect);
+  if own_transaction(changes, dialect) {
+    format!("{}\n{}", NO_TRANSACTION, sql)
+  } else {
+    sql
+  }
+}
+
+/// The snapshot of the most recent migration saved from the schema file
+fn latest_snapshot(migration_dir: &Path) -> Result<Option<PathBuf>, Box<dyn Error>> {
+  if !migration_dir.is_dir() {
+    return Ok(None);
+  }
+  let mut snapshots = Vec::new();
+  for entry in fs::read_dir(migration_dir)? {
+    let path = entry?.path().join(SNAPSHOT);
+    if path.is_file() {
+      snapshots.push(path);
+    }
+  }
+  snapshots.sort();
+  Ok(snapshots.pop())
+}
+
+/// A timestamp sorting after every existing migration, even when the last one
+/// was saved within the same second
+fn next_timestamp(migration_dir: &Path) -> Result<String, Box<dyn Error>> {
+  let now = timestamp(SystemTime::now());
+  let mut latest = 0;
+  if migration_dir.is_dir() {
+    for entry in fs::read_dir(migration_dir)? {
+      let name = entry?.file_name().to_string_lossy().into_owned();
+      let digits: String = name.chars().take_while(char::is_ascii_digit).collect();
+      latest = latest.max(digits.parse::<u64>().unwrap_or(0));
+    }
+  }
+  match now.parse::<u64>() {
+    Ok(now) if now <= latest => Ok((latest + 1).to_string()),
+    _ => Ok(now),
+  }
+}
+
+/// Keep the description usable as a directory name: ASCII letters and digits
+/// joined by single underscores, `migration` when nothing is left
+fn slug(description: &str) -> String {
+  let slug = description
+    .split(|c: char| !c.is_ascii_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .map(str::to_ascii_lowercase)
+    .collect::<Vec<_>>()
+    .join("_");
+  if slug.is_empty() {
+    "migration".to_string()
+  } else {
+    slug
+  }
+}
+
+/// The `YYYYMMDDHHMMSS` UTC timestamp migration names start with
+pub(crate) fn timestamp(time: SystemTime) -> String {
+  let seconds = time
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs() as i64;
+  let (days, rest) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+  // the civil date of a day count, from Howard Hinnant's `civil_from_days`
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z - era * 146_097;
+  let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+  format!(
+    "{:04}{:02}{:02}{:02}{:02}{:02}",
+    year,
+    month,
+    day,
+    rest / 3600,
+    rest % 3600 / 60,
+    rest % 60
+  )
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::time::Duration;
+
+  #[test]
+  fn test_timestamp() {
+    let time = UNIX_EPOCH + Duration::from_secs(1_792_411_445);
+    assert_eq!(timestamp(time), "20261019120405");
+    assert_eq!(timestamp(UNIX_EPOCH), "19700101000000");
+  }
+
+  #[test]
+  fn test_slug() {
+    assert_eq!(slug("Add users-table"), "add_users_table");
+    assert_eq!(slug("  add -- café  "), "add_caf");
+    assert_eq!(slug(""), "migration");
+    assert_eq!(slug("!?"), "migration");
+  }
+}
@@ -0,0 +1,291 @@
+use super::save::{migration_script, SNAPSHOT};
+use connection::{Column, Connection, DatabaseSchema, Provider, Session};
+use migration::{Dialect, Migration, Migrator, Script, HISTORY_TABLE, LOCK_TABLE, REPLACES};
+use schema::{diff, Pulled, Schema};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The statements that change rows rather than the schema, a baseline built
+/// from the schema alone would not repeat them
+const DATA_STATEMENTS: &[&str] = &["INSERT", "UPDATE", "DELETE", "REPLACE", "MERGE", "COPY"];
+
+/// What `migrate squash` wrote
+pub struct Squashed {
+  pub name: String,
+  pub replaced: Vec<String>,
+}
+
+/// Replace the migrations up to and including `until` with a baseline
+/// migration creating the schema they leave behind. Repeatable migrations
+/// come after every versioned one and are kept as they are.
+///
+/// The migrations are replayed in `scratch`, an empty database of the same
+/// provider, and so is the baseline to check it creates the same schema.
+/// Nothing is written when the baseline would lose anything.
+pub fn squash(
+  scratch: &Connection,
+  migrator: &Migrator,
+  migration_dir: &Path,
+  until: &str,
+) -> Result<Squashed, Box<dyn Error>> {
+  let migrations = migrator.migrations();
+  let end = migrations
+    .iter()
+    .position(|migration| migration.name == until && !migration.is_repeatable())
+    .ok_or_else(|| migration::Error::UnknownMigration(until.to_string()))?;
+  let squashed = &migrations[..=end];
+  if squashed.len() < 2 {
+    return Err("Squashing needs at least two migrations".into());
+  }
+  if let Some(migration) = squashed.iter().find(|m| m.code.is_some()) {
+    return Err(
+      format!(
+        "`{}` is written in Rust and cannot be squashed",
+        migration.name
+      )
+      .into(),
+    );
+  }
+  let timestamp: String = until.chars().take_while(char::is_ascii_digit).collect();
+  let name = format!("{}_baseline", timestamp);
+  if squashed.iter().any(|migration| migration.name == name) {
+    return Err(
+      format!(
+        "`{}` already is a baseline, squash until a later migration",
+        name
+      )
+      .into(),
+    );
+  }
+
+  let dialect = migrator.dialect();
+  let mut lost = data_changes(squashed, dialect)?;
+  let mut session = scratch.session()?;
+  ensure_empty(&mut session, scratch.provider(), scratch.name())?;
+  let result = rebuild(&mut session, scratch.provider(), dialect, squashed);
+  // only the empty history table is left behind, which the next squash ignores
+  let cleaned = Migrator::new(dialect, Vec::new()).fresh(&mut session);
+  let (pulled, approximated) = result?;
+  cleaned?;
+  lost.extend(pulled.warnings.iter().cloned());
+  lost.extend(approximated);
+  if !lost.is_empty() {
+    let mut message = String::from("The baseline would not match the squashed migrations:\n");
+    for reason in lost.iter() {
+      message.push_str(&format!("  - {}\n", reason));
+    }
+    message.push_str("Squash until an earlier migration, nothing was changed.");
+    return Err(message.into());
+  }
+
+  let empty = Schema::default();
+  let up = migration_script(
+    &diff(&empty, &pulled.schema, dialect),
+    &pulled.schema,
+    dialect,
+  );
+  let down = migration_script(&diff(&pulled.schema, &empty, dialect), &empty, dialect);
+  let mut source = String::new();
+  for migration in squashed.iter() {
+    source.push_str(&format!("{} {}\n", REPLACES, migration.name));
+  }
+  source.push_str(&up);
+
+  let dir = migration_dir.join(&name);
+  fs::create_dir_all(&dir)?;
+  fs::write(dir.join("up.sql"), source)?;
+  fs::write(dir.join("down.sql"), down)?;
+  // `migrate save` diffs against the latest snapshot, keep it around
+  if let Some(snapshot) = squashed
+    .iter()
+    .rev()
+    .map(|migration| migration.path.join(SNAPSHOT))
+    .find(|path| path.is_file())
+  {
+    fs::copy(snapshot, dir.join(SNAPSHOT))?;
+  }
+
+  for migration in squashed.iter() {
+    if migration.path.is_dir() {
+      fs::remove_dir_all(&migration.path)?;
+    } else {
+      fs::remove_file(&migration.path)?;
+    }
+  }
+  Ok(Squashed {
+    name,
+    replaced: squashed.iter().map(|m| m.name.clone()).collect(),
+  })
+}
+
+/// Refuse a scratch database holding anything besides an empty history,
+/// replaying starts by dropping everything in it
+fn ensure_empty(
+  session: &mut dyn Session,
+  provider: &Provider,
+  name: &str,
+) -> Result<(), Box<dyn Error>> {
+  let database = connection::introspect(session, provider)?;
+  let has_tables = database
+    .tables
+    .iter()
+    .any(|table| table.name != HISTORY_TABLE && table.name != LOCK_TABLE);
+  let applied = database
+    .tables
+    .iter()
+    .any(|table| table.name == HISTORY_TABLE)
+    && !session
+      .query(&format!("SELECT name FROM {} LIMIT 1", HISTORY_TABLE))
+      .unwrap_or_default()
+      .is_empty();
+  if has_tables
+    || applied
+    || !database.views.is_empty()
+    || !database.enums.is_empty()
+    || !database.sequences.is_empty()
+    || !database.triggers.is_empty()
+  {
+    return Err(
+      format!(
+        "The scratch connection `{}` is not empty, squashing replays the migrations in an empty database",
+        name
+      )
+      .into(),
+    );
+  }
+  Ok(())
+}
+
+/// Replay the squashed migrations and read back the schema they leave, then
+/// replay the baseline built from it and list the columns it creates differently
+fn rebuild(
+  session: &mut dyn Session,
+  provider: &Provider,
+  dialect: Dialect,
+  squashed: &[Migration],
+) -> Result<(Pulled, Vec<String>), Box<dyn Error>> {
+  let replayed = replay(session, provider, dialect, squashed.to_vec())?;
+  let pulled = schema::pull(&replayed, dialect);
+  let up = migration_script(
+    &diff(&Schema::default(), &pulled.schema, dialect),
+    &pulled.schema,
+    dialect,
+  );
+  let baseline = Migration {
+    name: "baseline".to_string(),
+    path: PathBuf::new(),
+    up: Script::new(&up),
+    down: None,
+    checksum: String::new(),
+    code: None,
+    replaces: Vec::new(),
+  };
+  let rebuilt = replay(session, provider, dialect, vec![baseline])?;
+  let approximated = approximations(&replayed, &rebuilt);
+  Ok((pulled, approximated))
+}
+
+fn replay(
+  session: &mut dyn Session,
+  provider: &Provider,
+  dialect: Dialect,
+  migrations: Vec<Migration>,
+) -> Result<DatabaseSchema, Box<dyn Error>> {
+  Migrator::new(dialect, migrations).fresh(session)?;
+  Ok(connection::introspect(session, provider)?)
+}
+
+/// The columns of the replayed migrations the baseline creates with another
+/// type, nullability or default, and the indexes and foreign keys it names
+/// differently. Tables and columns `db pull` left out are
+/// already among its warnings.
+fn approximations(replayed: &DatabaseSchema, rebuilt: &DatabaseSchema) -> Vec<String> {
+  let mut approximated = Vec::new();
+  for table in replayed.tables.iter() {
+    let rebuilt = match rebuilt.tables.iter().find(|t| t.name == table.name) {
+      Some(rebuilt) => rebuilt,
+      None => continue,
+    };
+    for column in table.columns.iter() {
+      let after = match rebuilt.columns.iter().find(|c| c.name == column.name) {
+        Some(after) => after,
+        None => continue,
+      };
+      let (before, after) = (describe(column), describe(after));
+      if before != after {
+        approximated.push(format!(
+          "the column `{}.{}` would change from `{}` to `{}`",
+          table.name, column.name, before, after
+        ));
+      }
+    }
+    // later migrations may refer to them by name
+    for index in table.indexes.iter() {
+      if !rebuilt.indexes.iter().any(|i| i.name == index.name) {
+        approximated.push(format!(
+          "the index `{}` of `{}` would get another name",
+          index.name, table.name
+        ));
+      }
+    }
+    for key in table.foreign_keys.iter() {
+      if !rebuilt.foreign_keys.iter().any(|k| k.name == key.name) {
+        approximated.push(format!(
+          "the foreign key `{}` of `{}` would get another name",
+          key.name.as_deref().unwrap_or_default(),
+          table.name
+        ));
+      }
+    }
+  }
+  approximated
+}
+
+fn describe(column: &Column) -> String {
+  let mut description = column.data_type.clone();
+  if !column.nullable {
+    description.push_str(" NOT NULL");
+  }
+  if let Some(default) = &column.default {
+    description.push_str(&format!(" DEFAULT {}", default));
+  }
+  if column.auto_increment {
+    description.push_str(" AUTO_INCREMENT");
+  }
+  description
+}
+
+/// The squashed migrations with statements changing rows
+fn data_changes(squashed: &[Migration], dialect: Dialect) -> Result<Vec<String>, Box<dyn Error>> {
+  let mut changes = Vec::new();
+  for migration in squashed.iter() {
+    let statements = migration
+      .up
+      .statements(dialect)
+      .map_err(|reason| migration::Error::InvalidMigration(migration.path.clone(), reason))?;
+    if let Some(keyword) = statements
+      .iter()
+      .map(|statement| first_keyword(statement))
+      .find(|keyword| DATA_STATEMENTS.contains(&keyword.as_str()))
+    {
+      changes.push(format!(
+        "`{}` changes rows with `{}`, the baseline only creates the schema",
+        migration.name, keyword
+      ));
+    }
+  }
+  Ok(changes)
+}
+
+/// The first word of a statement after its comment lines, upper-cased
+fn first_keyword(statement: &str) -> String {
+  statement
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.starts_with("--"))
+    .flat_map(|line| line.split(|c: char| !c.is_ascii_alphabetic()))
+    .find(|word| !word.is_empty())
+    .unwrap_or_default()
+    .to_ascii_uppercase()
+}
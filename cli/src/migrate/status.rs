@@ -0,0 +1,136 @@
+use migration::{AppliedMigration, Migrator, BASELINE_BATCH};
+use std::fmt;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+  Applied,
+  Pending,
+  /// Recorded in the history table but no longer in the migrations directory
+  Missing,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Checksum {
+  Ok,
+  Changed,
+}
+
+impl fmt::Display for State {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      State::Applied => f.write_str("applied"),
+      State::Pending => f.write_str("pending"),
+      State::Missing => f.write_str("missing"),
+    }
+  }
+}
+
+impl fmt::Display for Checksum {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Checksum::Ok => f.write_str("ok"),
+      Checksum::Changed => f.write_str("changed"),
+    }
+  }
+}
+
+/// A row of `migrate list`
+#[derive(Serialize, Debug)]
+pub struct MigrationStatus {
+  pub name: String,
+  pub state: State,
+  pub batch: Option<i64>,
+  pub applied_at: Option<String>,
+  pub checksum: Option<Checksum>,
+}
+
+impl MigrationStatus {
+  /// Every migration on disk in order, followed by the applied ones missing
+  /// from disk. A repeatable migration changed since its last run is pending.
+  pub fn collect(migrator: &Migrator, applied: &[AppliedMigration]) -> Vec<MigrationStatus> {
+    let mut statuses: Vec<MigrationStatus> = migrator
+      .migrations()
+      .iter()
+      .map(
+        |migration| match applied.iter().find(|a| a.name == migration.name) {
+          // a changed repeatable migration runs again on the next `up`
+          Some(applied) if migration.is_repeatable() && applied.checksum != migration.checksum => {
+            MigrationStatus {
+              checksum: Some(Checksum::Changed),
+              ..MigrationStatus::applied(applied, State::Pending)
+            }
+          }
+          Some(applied) => MigrationStatus {
+            checksum: Some(if applied.checksum == migration.checksum {
+              Checksum::Ok
+            } else {
+              Checksum::Changed
+            }),
+            ..MigrationStatus::applied(applied, State::Applied)
+          },
+          None => MigrationStatus {
+            name: migration.name.clone(),
+            state: State::Pending,
+            batch: None,
+            applied_at: None,
+            checksum: None,
+          },
+        },
+      )
+      .collect();
+
+    for applied in applied.iter() {
+      if !migrator.migrations().iter().any(|m| m.name == applied.name) {
+        statuses.push(MigrationStatus::applied(applied, State::Missing));
+      }
+    }
+    statuses
+  }
+
+  fn applied(applied: &AppliedMigration, state: State) -> Self {
+    MigrationStatus {
+      name: applied.name.clone(),
+      state,
+      batch: Some(applied.batch),
+      applied_at: Some(applied.applied_at.clone()),
+      checksum: None,
+    }
+  }
+}
+
+/// Render the statuses as an aligned table with a header row
+pub fn table(statuses: &[MigrationStatus]) -> String {
+  let header = ["Migration", "Status", "Batch", "Applied at", "Checksum"];
+  let mut rows = vec![header.iter().map(|h| h.to_string()).collect::<Vec<_>>()];
+  for status in statuses.iter() {
+    rows.push(vec![
+      status.name.clone(),
+      status.state.to_string(),
+      match status.batch {
+        Some(BASELINE_BATCH) => "baseline".to_string(),
+        Some(batch) => batch.to_string(),
+        None => String::new(),
+      },
+      status.applied_at.clone().unwrap_or_default(),
+      status.checksum.map(|c| c.to_string()).unwrap_or_default(),
+    ]);
+  }
+
+  let widths: Vec<usize> = (0..header.len())
+    .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+    .collect();
+  rows
+    .iter()
+    .map(|row| {
+      let cells: Vec<String> = row
+        .iter()
+        .zip(widths.iter())
+        .map(|(cell, width)| format!("{:width$}", cell, width = width))
+        .collect();
+      cells.join("  ").trim_end().to_string()
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
@@ -1,5 +1,43 @@
 use clap::ArgMatches;
+use connection::Session;
+use migration::{Dialect, Script};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
 
 pub fn seed_command(_matches: &ArgMatches) {
   unimplemented!()
 }
+
+/// Run every `.sql` file of the seeds directory in name order, returning the
+/// names of the files run
+pub fn run_seeds(
+  session: &mut dyn Session,
+  dialect: Dialect,
+  dir: &Path,
+) -> Result<Vec<String>, Box<dyn Error>> {
+  let mut paths = Vec::new();
+  for entry in fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))? {
+    let path = entry?.path();
+    if path.is_file() && path.extension().is_some_and(|ext| ext == "sql") {
+      paths.push(path);
+    }
+  }
+  paths.sort();
+
+  let mut seeded = Vec::new();
+  for path in paths.iter() {
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    let sql = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let statements = Script::new(&sql)
+      .statements(dialect)
+      .map_err(|reason| format!("Invalid seed `{}`: {}", path.display(), reason))?;
+    for statement in statements.iter() {
+      session
+        .batch_execute(statement)
+        .map_err(|e| format!("Seed `{}` failed: {}", name, e))?;
+    }
+    seeded.push(name.into_owned());
+  }
+  Ok(seeded)
+}
@@ -1,8 +1,34 @@
+use clap::ArgMatches;
+use config::Config;
+use connection::{Connection, ConnectionOption, ConnectionOptionManager};
+use std::error::Error;
 use std::fmt::Display;
 
 pub fn handle_error<E: Display, T>(error: E) -> T {
-  // TODO: Gracefully handle this error
-  panic!("{}", error);
-  // eprintln!("{}", error);
-  // ::std::process::exit(1);
+  eprintln!("{}", error);
+  ::std::process::exit(1);
+}
+
+/// The connection picked with `--connection`, given before or after the subcommand
+pub fn connection_name<'a>(matches: &'a ArgMatches, subcommand: Option<&'a ArgMatches>) -> &'a str {
+  subcommand
+    .and_then(|m| m.value_of("connection"))
+    .or_else(|| matches.value_of("connection"))
+    .unwrap_or("default")
+}
+
+/// The options of a connection of the config
+pub fn connection_option(config: &Config, name: &str) -> Result<ConnectionOption, Box<dyn Error>> {
+  let options = ConnectionOptionManager::new(&config.connections);
+  let option = options
+    .get(name)
+    .ok_or_else(|| connection::Error::UnknownConnection(name.to_string()))?;
+  Ok(option.clone())
+}
+
+/// Connect to a connection of the config
+pub fn connect(option: &ConnectionOption) -> Result<Connection, Box<dyn Error>> {
+  let mut connection = Connection::new(option);
+  connection.connect()?;
+  Ok(connection)
 }
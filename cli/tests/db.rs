@@ -0,0 +1,135 @@
+use temp_test::build_project;
+
+const CONFIG: &str = r#"
+version: "1.0"
+connections:
+  - name: "default"
+    datasource:
+      url: "sqlite://default.db"
+      provider: "sqlite"
+cli:
+  migration_dir: "migrations"
+"#;
+
+#[test]
+fn db_pull() {
+  let p = build_project("db_pull")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261019120000_legacy.sql",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL UNIQUE); \
+       CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL REFERENCES users (id)); \
+       CREATE VIEW emails AS SELECT email FROM users;",
+    )
+    .package_name("spectre")
+    .build();
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+
+  let result = p.command("db").arg("pull").arg("--print").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("// - the view `emails` was left out"));
+  assert!(result
+    .stdout()
+    .contains("user    users @relation(fields: [user_id], references: [id], onDelete: NoAction)"));
+  assert!(result.stdout().contains("posts posts[]"));
+  assert!(!result.stdout().contains("spectre_migrations"));
+  assert!(result
+    .stderr()
+    .contains("warning: the view `emails` was left out"));
+  assert!(!p.has_file("schema.spectre"));
+
+  let result = p.command("db").arg("pull").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("Wrote 2 models and 0 enums to schema.spectre"));
+
+  let result = p.command("db").arg("pull").run();
+  assert!(result.is_err());
+  assert!(result.stderr().contains("pass `--force` to overwrite it"));
+  let result = p.command("db").arg("pull").arg("--force").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+
+  // the pulled schema is a valid starting point for migrations
+  let result = p.command("migrate").arg("save").arg("baseline").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+}
+
+#[test]
+fn db_push() {
+  let p = build_project("db_push")
+    .file("spectre.yaml", CONFIG)
+    .file(
+      "schema.spectre",
+      "enum Role {\n  USER\n  ADMIN\n}\n\nmodel users {\n  id    Int    @id @default(autoincrement())\n  email String @unique\n  role  Role   @default(USER)\n}\n",
+    )
+    .package_name("spectre")
+    .build();
+  let result = p.command("db").arg("push").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("- create the table `users`"));
+  assert!(result
+    .stdout()
+    .contains("Pushed the schema to the `default` connection"));
+  assert!(!p.has_file("migrations"));
+
+  // pushing the unchanged schema again is a no-op, enum columns included
+  let result = p.command("db").arg("push").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("The database is already in sync with the schema."));
+
+  std::fs::write(
+    p.dir.path().join("schema.spectre"),
+    "enum Role {\n  USER\n  ADMIN\n}\n\nmodel users {\n  id   Int  @id @default(autoincrement())\n  role Role @default(USER)\n}\n",
+  )
+  .unwrap();
+  // nobody is there to confirm dropping the column
+  let result = p.command("db").arg("push").run();
+  assert!(result.is_err());
+  assert!(result.stdout().contains("These changes may lose data:"));
+  assert!(result
+    .stderr()
+    .contains("pass `--accept-data-loss` to push changes that may lose data"));
+
+  let result = p.command("db").arg("push").arg("--accept-data-loss").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  let result = p.command("db").arg("push").run();
+  assert!(result
+    .stdout()
+    .contains("The database is already in sync with the schema."));
+}
+
+#[test]
+fn db_push_drops_by_database_names() {
+  let p = build_project("db_push_drops_by_database_names")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261019120000_legacy.sql",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL); \
+       CREATE INDEX legacy_users_by_email ON users (email);",
+    )
+    .file(
+      "schema.spectre",
+      "model users {\n  id    Int    @id @default(autoincrement())\n  email String\n}\n",
+    )
+    .package_name("spectre")
+    .build();
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+
+  // the index is not named `users_email_idx` as the schema would name it
+  let result = p.command("db").arg("push").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("- drop the index"));
+  let result = p.command("db").arg("push").run();
+  assert!(result
+    .stdout()
+    .contains("The database is already in sync with the schema."));
+}
@@ -0,0 +1,795 @@
+use temp_test::build_project;
+
+const CONFIG: &str = r#"
+version: "1.0"
+connections:
+  - name: "default"
+    datasource:
+      url: "sqlite://default.db"
+      provider: "sqlite"
+  - name: "reporting"
+    datasource:
+      url: "sqlite://reporting.db"
+      provider: "sqlite"
+cli:
+  migration_dir: "migrations"
+"#;
+
+/// The cells of the `migrate list` row of a migration
+fn row<'a>(table: &'a str, name: &str) -> Vec<&'a str> {
+  table
+    .lines()
+    .find(|line| line.starts_with(name))
+    .unwrap_or_else(|| panic!("no row for {} in\n{}", name, table))
+    .split_whitespace()
+    .collect()
+}
+
+#[test]
+fn migrate_up() {
+  let p = build_project("migrate_up")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("Applied 20261018120000_create_users"));
+  assert!(p.has_file("default.db"));
+
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("Nothing to migrate."));
+}
+
+#[test]
+fn migrate_up_on_connection() {
+  let p = build_project("migrate_up_on_connection")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY);",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p
+    .command("migrate")
+    .arg("--connection=reporting")
+    .arg("up")
+    .run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(p.has_file("reporting.db"));
+  assert!(!p.has_file("default.db"));
+
+  let result = p
+    .command("migrate")
+    .arg("up")
+    .arg("-c")
+    .arg("missing")
+    .run();
+  assert!(result.is_err());
+  assert!(result.stderr().contains("No connection named `missing`"));
+}
+
+#[test]
+fn migrate_up_with_failing_migration() {
+  let p = build_project("migrate_up_failing")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_broken.sql",
+      "CREATE TABLE users (id INTEGER); INSERT INTO missing VALUES (1);",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_err());
+  assert!(result
+    .stderr()
+    .contains("Migration `20261018120000_broken` failed"));
+}
+
+#[test]
+fn migrate_rollback_and_reset() {
+  let p = build_project("migrate_rollback")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "-- +up\nCREATE TABLE users (id INTEGER);\n-- +down\nDROP TABLE users;\n",
+    )
+    .file(
+      "migrations/20261018130000_create_posts.sql",
+      "-- +up\nCREATE TABLE posts (id INTEGER);\n-- +down\nDROP TABLE posts;\n",
+    )
+    .package_name("spectre")
+    .build();
+
+  assert!(p.command("migrate").arg("up").run().is_success());
+
+  let result = p.command("migrate").arg("rollback").arg("1").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("Rolled back 20261018130000_create_posts"));
+  assert!(!result.stdout().contains("create_users"));
+
+  let result = p.command("migrate").arg("reset").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("Rolled back 20261018120000_create_users"));
+
+  let result = p.command("migrate").arg("rollback").arg("2").run();
+  assert!(result.stdout().contains("Nothing to rollback."));
+}
+
+#[test]
+fn migrate_rollback_without_down_script() {
+  let p = build_project("migrate_rollback_force")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .package_name("spectre")
+    .build();
+
+  assert!(p.command("migrate").arg("up").run().is_success());
+
+  let result = p.command("migrate").arg("rollback").run();
+  assert!(result.is_err());
+  assert!(result.stderr().contains("has no down script"));
+  assert!(result.stderr().contains("--force"));
+
+  let result = p.command("migrate").arg("rollback").arg("--force").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+}
+
+#[test]
+fn migrate_rollback_by_batch() {
+  let p = build_project("migrate_rollback_batch")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "-- +up\nCREATE TABLE users (id INTEGER);\n-- +down\nDROP TABLE users;\n",
+    )
+    .file(
+      "migrations/20261018130000_create_posts.sql",
+      "-- +up\nCREATE TABLE posts (id INTEGER);\n-- +down\nDROP TABLE posts;\n",
+    )
+    .file(
+      "migrations/20261018140000_create_tags.sql",
+      "-- +up\nCREATE TABLE tags (id INTEGER);\n-- +down\nDROP TABLE tags;\n",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.stdout().contains("Migrating batch 1"));
+  assert!(p
+    .command("migrate")
+    .arg("rollback")
+    .arg("2")
+    .run()
+    .is_success());
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.stdout().contains("Migrating batch 2"));
+
+  let result = p.command("migrate").arg("list").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert_eq!(
+    row(result.stdout(), "20261018120000_create_users")[..3],
+    ["20261018120000_create_users", "applied", "1"]
+  );
+  assert_eq!(
+    row(result.stdout(), "20261018140000_create_tags")[..3],
+    ["20261018140000_create_tags", "applied", "2"]
+  );
+
+  let result = p.command("migrate").arg("rollback").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("create_posts"));
+  assert!(result.stdout().contains("create_tags"));
+  assert!(!result.stdout().contains("create_users"));
+
+  let result = p.command("migrate").arg("rollback").arg("--batch=1").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("Rolled back 20261018120000_create_users"));
+
+  let result = p.command("migrate").arg("list").run();
+  assert_eq!(
+    row(result.stdout(), "20261018120000_create_users"),
+    ["20261018120000_create_users", "pending"]
+  );
+}
+
+#[test]
+fn migrate_with_changed_migration() {
+  let p = build_project("migrate_checksum")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .package_name("spectre")
+    .build();
+
+  assert!(p.command("migrate").arg("up").run().is_success());
+  std::fs::write(
+    p.dir
+      .path()
+      .join("migrations/20261018120000_create_users.sql"),
+    "CREATE TABLE users (id INTEGER, name TEXT);",
+  )
+  .unwrap();
+
+  for command in ["up", "list", "pending"].iter() {
+    let result = p.command("migrate").arg(*command).run();
+    assert!(result.is_err(), "`{}` succeeded {:?}", command, result);
+    assert!(result
+      .stderr()
+      .contains("Migration `20261018120000_create_users` was changed after it was applied"));
+    assert!(result.stderr().contains("20261018120000_create_users.sql"));
+  }
+
+  let result = p.command("migrate").arg("repair").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("Recorded the new checksum of 20261018120000_create_users"));
+
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("Nothing to migrate."));
+}
+
+#[test]
+fn migrate_list_and_pending() {
+  let p = build_project("migrate_list")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .file(
+      "migrations/20261018130000_create_posts.sql",
+      "CREATE TABLE posts (id INTEGER);",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p.command("migrate").arg("pending").run();
+  assert_eq!(result.code(), 0, "{:?}", result);
+  assert!(result.stdout().contains("20261018130000_create_posts"));
+
+  assert!(p.command("migrate").arg("up").run().is_success());
+  std::fs::remove_file(
+    p.dir
+      .path()
+      .join("migrations/20261018130000_create_posts.sql"),
+  )
+  .unwrap();
+
+  let result = p.command("migrate").arg("list").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  let header = result.stdout().lines().next().unwrap();
+  assert_eq!(
+    header
+      .split("  ")
+      .map(str::trim)
+      .filter(|c| !c.is_empty())
+      .collect::<Vec<_>>(),
+    ["Migration", "Status", "Batch", "Applied at", "Checksum"]
+  );
+  let users = row(result.stdout(), "20261018120000_create_users");
+  assert_eq!(
+    (users[1], users[2], users[users.len() - 1]),
+    ("applied", "1", "ok")
+  );
+  let posts = row(result.stdout(), "20261018130000_create_posts");
+  assert_eq!(posts[1], "missing");
+
+  let result = p.command("migrate").arg("list").arg("--format=json").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  let json: serde_json::Value = serde_json::from_str(result.stdout()).unwrap();
+  assert_eq!(json[0]["name"], "20261018120000_create_users");
+  assert_eq!(json[0]["state"], "applied");
+  assert_eq!(json[0]["batch"], 1);
+  assert_eq!(json[0]["checksum"], "ok");
+  assert_eq!(json[1]["state"], "missing");
+
+  let result = p.command("migrate").arg("pending").run();
+  assert_eq!(result.code(), 1, "{:?}", result);
+  assert!(result.stdout().contains("Nothing to migrate."));
+
+  let result = p
+    .command("migrate")
+    .arg("pending")
+    .arg("--format")
+    .arg("json")
+    .run();
+  assert_eq!(result.code(), 1, "{:?}", result);
+  assert_eq!(result.stdout().trim(), "[]");
+}
+
+#[test]
+fn migrate_fresh() {
+  let p = build_project("migrate_fresh")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+    )
+    .folder("seeds")
+    .file(
+      "seeds/01_users.sql",
+      "INSERT INTO users (name) VALUES ('ada');",
+    )
+    .package_name("spectre")
+    .build();
+
+  assert!(p.command("migrate").arg("up").run().is_success());
+  // leave `users` behind without any history pointing at it
+  assert!(p
+    .command("migrate")
+    .arg("rollback")
+    .arg("--force")
+    .run()
+    .is_success());
+
+  let result = p.command("migrate").arg("fresh").arg("--seed").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("Dropped table users"));
+  assert!(result.stdout().contains("Dropped table spectre_migrations"));
+  assert!(result
+    .stdout()
+    .contains("Applied 20261018120000_create_users"));
+  assert!(result.stdout().contains("Seeded 01_users"));
+
+  let result = p.command("migrate").arg("list").run();
+  assert_eq!(
+    row(result.stdout(), "20261018120000_create_users")[1..3],
+    ["applied", "1"]
+  );
+}
+
+#[test]
+fn migrate_fresh_refuses_production() {
+  let config = CONFIG.replace(
+    "version: \"1.0\"",
+    "version: \"1.0\"\nenvironment: production",
+  );
+  let p = build_project("migrate_fresh_production")
+    .file("spectre.yaml", &config)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p.command("migrate").arg("fresh").run();
+  assert!(result.is_err());
+  assert!(result
+    .stderr()
+    .contains("Refusing to drop every table of the production connection `default`"));
+
+  let result = p.command("migrate").arg("fresh").arg("--force").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+}
+
+#[test]
+fn migrate_dry_run() {
+  let p = build_project("migrate_dry_run")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "-- +up\nCREATE TABLE users (id INTEGER);\n-- +down\nDROP TABLE users;\n",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p.command("migrate").arg("up").arg("--dry-run").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  let lines: Vec<&str> = result.stdout().lines().collect();
+  assert!(lines[0].starts_with("CREATE TABLE IF NOT EXISTS spectre_migrations"));
+  assert_eq!(lines[1..3], ["BEGIN;", "CREATE TABLE users (id INTEGER);"]);
+  assert!(lines[3].starts_with("INSERT INTO spectre_migrations (name, checksum, batch"));
+  assert_eq!(lines[4], "COMMIT;");
+
+  let result = p.command("migrate").arg("pending").run();
+  assert_eq!(result.code(), 0, "{:?}", result);
+
+  let result = p
+    .command("migrate")
+    .arg("up")
+    .arg("--output")
+    .arg("plan.sql")
+    .run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().starts_with("Wrote "));
+  assert!(p
+    .file_contents("plan.sql")
+    .contains("CREATE TABLE users (id INTEGER);\n"));
+
+  assert!(p.command("migrate").arg("up").run().is_success());
+  let result = p.command("migrate").arg("rollback").arg("--dry-run").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert_eq!(
+    result.stdout(),
+    "BEGIN;\nDROP TABLE users;\nDELETE FROM spectre_migrations WHERE name = '20261018120000_create_users';\nCOMMIT;\n"
+  );
+  assert!(p
+    .command("migrate")
+    .arg("list")
+    .run()
+    .stdout()
+    .contains("applied"));
+}
+
+#[test]
+fn migrate_save() {
+  let p = build_project("migrate_save")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "schema.spectre",
+      "model User {\n  id Int @id @default(autoincrement())\n  email String @unique\n}\n",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p.command("migrate").arg("save").arg("create users").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("Saved migration "));
+  assert!(result.stdout().trim_end().ends_with("_create_users"));
+
+  let result = p.command("migrate").arg("save").run();
+  assert!(result.stdout().contains("No schema changes to save."));
+
+  std::fs::write(
+    p.dir.path().join("schema.spectre"),
+    "model User {\n  id Int @id @default(autoincrement())\n  email String @unique\n  age Int?\n}\n",
+  )
+  .unwrap();
+  let result = p.command("migrate").arg("save").arg("add_age").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("_create_users"));
+  assert!(result.stdout().contains("_add_age"));
+
+  // the generated down scripts undo both migrations
+  let result = p.command("migrate").arg("reset").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+}
+
+#[test]
+fn migrate_lint() {
+  let p = build_project("migrate_lint")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);\nCREATE INDEX users_name_idx ON users (name);",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p.command("migrate").arg("lint").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("No problems found in 1 migration."));
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+
+  std::fs::write(
+    p.dir.path().join("migrations/20261019120000_drop_name.sql"),
+    "ALTER TABLE users DROP COLUMN name;",
+  )
+  .unwrap();
+  let result = p.command("migrate").arg("lint").run();
+  assert!(result.is_err());
+  assert!(result.stdout().contains(
+    "error[drop-column] 20261019120000_drop_name (statement 1): drops the column `name` of `users`"
+  ));
+  assert!(result.stdout().contains("Found 1 error and 0 warnings."));
+
+  std::fs::write(
+    p.dir.path().join("migrations/20261019120000_drop_name.sql"),
+    "-- spectre:lint-ignore drop-column\nALTER TABLE users DROP COLUMN name;",
+  )
+  .unwrap();
+  let result = p.command("migrate").arg("lint").arg("--all").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("No problems found in 2 migrations."));
+}
+
+#[test]
+fn migrate_squash() {
+  let p = build_project("migrate_squash")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY);",
+    )
+    .file(
+      "migrations/20261018130000_add_name.sql",
+      "ALTER TABLE users ADD COLUMN name TEXT;",
+    )
+    .file(
+      "migrations/20261018140000_create_posts.sql",
+      "CREATE TABLE posts (id INTEGER PRIMARY KEY);",
+    )
+    .package_name("spectre")
+    .build();
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+
+  let result = p
+    .command("migrate")
+    .arg("squash")
+    .arg("--until")
+    .arg("20261018130000_add_name")
+    .run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("Squashed 2 migrations into 20261018130000_baseline"));
+  assert!(!p.has_file("migrations/20261018120000_create_users.sql"));
+  assert!(!p.has_file("migrations/20261018130000_add_name.sql"));
+  let up = std::fs::read_to_string(
+    p.dir
+      .path()
+      .join("migrations/20261018130000_baseline/up.sql"),
+  )
+  .unwrap();
+  assert!(up.starts_with(
+    "-- spectre:replaces 20261018120000_create_users\n-- spectre:replaces 20261018130000_add_name\n"
+  ));
+  assert!(up.contains("CREATE TABLE \"users\""));
+
+  // the database that ran the squashed migrations only records the baseline
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("20261018130000_baseline"));
+  let result = p.command("migrate").arg("list").run();
+  assert!(!result.stdout().contains("missing"));
+  assert!(!result.stdout().contains("pending"));
+
+  // a new one runs it
+  let result = p
+    .command("migrate")
+    .arg("up")
+    .arg("--connection")
+    .arg("reporting")
+    .run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("20261018140000_create_posts"));
+}
+
+#[test]
+fn migrate_squash_keeps_baseline_out_of_rollback() {
+  let p = build_project("migrate_squash_keeps_baseline_out_of_rollback")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .package_name("spectre")
+    .build();
+  // each migration is applied in a batch of its own
+  for (name, sql) in [
+    (
+      "20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY);",
+    ),
+    (
+      "20261018130000_add_name.sql",
+      "ALTER TABLE users ADD COLUMN name TEXT;",
+    ),
+    (
+      "20261018140000_create_posts.sql",
+      "-- +up\nCREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES users (id));\n-- +down\nDROP TABLE posts;\n",
+    ),
+  ] {
+    std::fs::write(p.dir.path().join("migrations").join(name), sql).unwrap();
+    let result = p.command("migrate").arg("up").run();
+    assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  }
+
+  let result = p
+    .command("migrate")
+    .arg("squash")
+    .arg("--until")
+    .arg("20261018130000_add_name")
+    .run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("Recorded 20261018130000_baseline as baseline"));
+  assert!(!result.stdout().contains("Migrating batch"));
+
+  // the last batch is still the one that created posts
+  let result = p.command("migrate").arg("rollback").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("Rolled back 20261018140000_create_posts"));
+  assert!(!result.stdout().contains("20261018130000_baseline"));
+  let result = p.command("migrate").arg("rollback").run();
+  assert!(result.stdout().contains("Nothing to rollback."));
+
+  let result = p.command("migrate").arg("list").run();
+  let cells = row(result.stdout(), "20261018130000_baseline");
+  assert_eq!(&cells[1..3], &["applied", "baseline"]);
+}
+
+#[test]
+fn migrate_squash_refuses_to_lose_anything() {
+  let p = build_project("migrate_squash_refuses_to_lose_anything")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY);",
+    )
+    .file(
+      "migrations/20261018130000_add_admin.sql",
+      "INSERT INTO users (id) VALUES (1);\nCREATE VIEW admins AS SELECT id FROM users;",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p
+    .command("migrate")
+    .arg("squash")
+    .arg("--until")
+    .arg("20261018130000_add_admin")
+    .run();
+  assert!(result.is_err());
+  assert!(result
+    .stderr()
+    .contains("`20261018130000_add_admin` changes rows with `INSERT`"));
+  assert!(result.stderr().contains("the view `admins` was left out"));
+  assert!(p.has_file("migrations/20261018120000_create_users.sql"));
+  assert!(p.has_file("migrations/20261018130000_add_admin.sql"));
+  assert!(!p.has_file("migrations/20261018130000_baseline/up.sql"));
+
+  // the database being migrated is never the scratch one
+  let result = p
+    .command("migrate")
+    .arg("squash")
+    .arg("--until")
+    .arg("20261018130000_add_admin")
+    .arg("--scratch")
+    .arg("default")
+    .run();
+  assert!(result.is_err());
+  assert!(result
+    .stderr()
+    .contains("The scratch connection must be another database"));
+}
+
+#[test]
+fn migrate_baseline() {
+  let p = build_project("migrate_baseline")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY);",
+    )
+    .file(
+      "migrations/20261018130000_create_posts.sql",
+      "CREATE TABLE posts (id INTEGER PRIMARY KEY);",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p
+    .command("migrate")
+    .arg("baseline")
+    .arg("20261018120000_create_users")
+    .run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("Recorded 20261018120000_create_users as applied"));
+
+  let result = p.command("migrate").arg("list").run();
+  let cells = row(result.stdout(), "20261018120000_create_users");
+  assert_eq!(&cells[1..3], &["applied", "baseline"]);
+
+  // the users table was never created, only the posts migration runs
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("Migrating batch 1"));
+  assert!(!result.stdout().contains("20261018120000_create_users"));
+
+  let result = p.command("migrate").arg("baseline").arg("nope").run();
+  assert!(result.is_err());
+  assert!(result
+    .stderr()
+    .contains("There is no migration named `nope`"));
+}
+
+#[test]
+fn migrate_repeatable() {
+  let p = build_project("migrate_repeatable")
+    .file("spectre.yaml", CONFIG)
+    .folder("migrations")
+    .file(
+      "migrations/20261018120000_create_users.sql",
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+    )
+    .file(
+      "migrations/R__user_names.sql",
+      "DROP VIEW IF EXISTS user_names; CREATE VIEW user_names AS SELECT name FROM users;",
+    )
+    .package_name("spectre")
+    .build();
+
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("R__user_names"));
+
+  let result = p.command("migrate").arg("list").run();
+  let cells = row(result.stdout(), "R__user_names");
+  assert_eq!(&cells[1..3], &["applied", "1"]);
+
+  std::fs::write(
+    p.dir.path().join("migrations/R__user_names.sql"),
+    "DROP VIEW IF EXISTS user_names; CREATE VIEW user_names AS SELECT id, name FROM users;",
+  )
+  .unwrap();
+  let result = p.command("migrate").arg("list").run();
+  let cells = row(result.stdout(), "R__user_names");
+  assert_eq!(cells[1], "pending");
+  assert_eq!(cells.last(), Some(&"changed"));
+
+  let result = p.command("migrate").arg("up").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result.stdout().contains("Migrating batch 2"));
+  assert!(!result.stdout().contains("20261018120000_create_users"));
+
+  let result = p.command("migrate").arg("list").run();
+  let cells = row(result.stdout(), "R__user_names");
+  assert_eq!(&cells[1..3], &["applied", "2"]);
+
+  // rolling back that batch leaves the users table alone
+  let result = p.command("migrate").arg("rollback").run();
+  assert!(result.is_success(), "Result was unsuccessful {:?}", result);
+  assert!(result
+    .stdout()
+    .contains("The last batch only re-ran repeatable migrations"));
+  let result = p.command("migrate").arg("list").run();
+  let cells = row(result.stdout(), "20261018120000_create_users");
+  assert_eq!(cells[1], "applied");
+}
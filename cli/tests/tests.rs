@@ -1,3 +1,6 @@
+extern crate serde_json;
 extern crate temp_test;
 
+mod db;
 mod init;
+mod migrate;
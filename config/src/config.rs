@@ -1,17 +1,63 @@
 use crate::{Logger, Logging};
 use connection::ConnectionOption;
+use std::time::Duration;
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct CliOption {
   // Directory where migrations should be created by default.
   #[serde(default)]
   migration_dir: Option<String>,
+  // Seconds to wait for another process to finish migrating the same database.
+  #[serde(default)]
+  migration_lock_timeout: Option<u64>,
+  // Directory holding the `.sql` seed files, run in name order.
+  #[serde(default)]
+  seed_dir: Option<String>,
+  // The declarative schema `migrate save` generates migrations from.
+  #[serde(default)]
+  schema: Option<String>,
+}
+
+impl CliOption {
+  /// The migrations directory, relative to the project root
+  pub fn migration_dir(&self) -> &str {
+    self
+      .migration_dir
+      .as_deref()
+      .unwrap_or("/migrations")
+      .trim_start_matches('/')
+  }
+
+  pub fn migration_lock_timeout(&self) -> Option<Duration> {
+    self.migration_lock_timeout.map(Duration::from_secs)
+  }
+
+  /// The seeds directory, relative to the project root
+  pub fn seed_dir(&self) -> &str {
+    self
+      .seed_dir
+      .as_deref()
+      .unwrap_or("/seeds")
+      .trim_start_matches('/')
+  }
+
+  /// The schema file, relative to the project root
+  pub fn schema(&self) -> &str {
+    self
+      .schema
+      .as_deref()
+      .unwrap_or("/schema.spectre")
+      .trim_start_matches('/')
+  }
 }
 
 impl Default for CliOption {
   fn default() -> Self {
     CliOption {
       migration_dir: Some("/migrations".to_string()),
+      migration_lock_timeout: None,
+      seed_dir: None,
+      schema: None,
     }
   }
 }
@@ -23,6 +69,9 @@ pub struct Config {
   pub logging: Option<Logging>,
   #[serde(default)]
   pub version: String,
+  // e.g. `production`, usually set from an environment variable
+  #[serde(default)]
+  pub environment: Option<String>,
   #[serde(default)]
   pub connections: Vec<ConnectionOption>,
   #[serde(default)]
@@ -33,11 +82,11 @@ pub struct Config {
 
 impl Default for Config {
   fn default() -> Self {
-    let mut connections = Vec::new();
-    connections.push(ConnectionOption::new());
+    let connections = vec![ConnectionOption::new()];
     Config {
       // TODO: This should not be hardcoded
       version: String::from("1.0"),
+      environment: None,
       cli: Some(CliOption::default()),
       logging: Some(Logging::Error),
       logger: Some(Logger::default()),
@@ -67,10 +116,61 @@ impl Config {
   pub fn cli(mut self, migration_dir: &str) -> Self {
     self.cli = Some(CliOption {
       migration_dir: Some(migration_dir.into()),
+      migration_lock_timeout: None,
+      seed_dir: None,
+      schema: None,
     });
     self
   }
 
+  /// The migrations directory, relative to the project root
+  pub fn migration_dir(&self) -> &str {
+    match self.cli {
+      Some(ref cli) => cli.migration_dir(),
+      None => "migrations",
+    }
+  }
+
+  /// The seeds directory, relative to the project root
+  pub fn seed_dir(&self) -> &str {
+    match self.cli {
+      Some(ref cli) => cli.seed_dir(),
+      None => "seeds",
+    }
+  }
+
+  /// The schema file, relative to the project root
+  pub fn schema_path(&self) -> &str {
+    match self.cli {
+      Some(ref cli) => cli.schema(),
+      None => "schema.spectre",
+    }
+  }
+
+  /// Whether the configured environment is `production`
+  pub fn is_production(&self) -> bool {
+    self
+      .environment
+      .as_deref()
+      .is_some_and(|environment| environment.eq_ignore_ascii_case("production"))
+  }
+
+  /// Whether applied migrations are logged, when `logging` is `All` or lists `Migration`
+  pub fn logs_migrations(&self) -> bool {
+    self
+      .logging
+      .as_ref()
+      .is_some_and(Logging::includes_migration)
+  }
+
+  /// How long migrations wait for the lock held by another process, when configured
+  pub fn migration_lock_timeout(&self) -> Option<Duration> {
+    self
+      .cli
+      .as_ref()
+      .and_then(CliOption::migration_lock_timeout)
+  }
+
   #[inline(always)]
   pub fn logger(mut self, logger: Logger) -> Self {
     self.logger = Some(logger);
@@ -6,6 +6,7 @@ use std::{fmt, io};
 use self::Error::*;
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
   FileNotFound(PathBuf),
   IoError(io::Error),
@@ -92,10 +93,7 @@ impl fmt::Display for Error {
 
 impl PartialEq for Error {
   fn eq(&self, other: &Self) -> bool {
-    match (self, other) {
-      (&FileNotFound(_), &FileNotFound(_)) => true,
-      _ => false,
-    }
+    matches!((self, other), (&FileNotFound(_), &FileNotFound(_)))
   }
 }
 
@@ -183,6 +181,7 @@ mod test {
   #[test]
   fn test_config_file_error() {
     #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
     struct Basic {
       v: bool,
       w: bool,
@@ -10,6 +10,16 @@ pub enum Logging {
   Many(Vec<Logging>),
 }
 
+impl Logging {
+  pub fn includes_migration(&self) -> bool {
+    match self {
+      Logging::All | Logging::Migration => true,
+      Logging::Many(levels) => levels.iter().any(Logging::includes_migration),
+      _ => false,
+    }
+  }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub enum LogMode {
   Advance,
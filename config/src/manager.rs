@@ -64,8 +64,7 @@ impl ConfigManager {
         .read_to_string(&mut self.content)
         .map_err(Error::IoError)?;
       self.content = parser::transform_from_env(&mut self.content)?;
-      println!("{}", &self.content);
-      let config = parser::into(&self.content.as_bytes())?;
+      let config = parser::into(self.content.as_bytes())?;
       self.config = config;
       Ok(())
     } else {
@@ -73,7 +72,7 @@ impl ConfigManager {
     }
   }
 
-  pub fn create(&mut self, root: &PathBuf) -> Result<()> {
+  pub fn create(&mut self, root: &Path) -> Result<()> {
     use std::io::Write;
 
     let config = Config::default();
@@ -93,7 +92,7 @@ impl ConfigManager {
   }
 }
 
-pub fn is_in_path(path: &PathBuf, ext: &str) -> Option<PathBuf> {
+pub fn is_in_path(path: &Path, ext: &str) -> Option<PathBuf> {
   let file_path = path.with_extension(ext);
 
   if file_path.exists() && file_path.is_file() {
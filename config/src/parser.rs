@@ -8,7 +8,7 @@ macro_rules! regex {
 
 /// parse the config file and replace any env variables with their respect values
 pub fn into(bytes: &[u8]) -> Result<Config> {
-  let config_option = serde_yaml::from_slice::<Config>(&bytes)?;
+  let config_option = serde_yaml::from_slice::<Config>(bytes)?;
   Ok(config_option)
 }
 
@@ -22,7 +22,7 @@ pub fn transform_from_env(template: &mut String) -> Result<String> {
   let mut last = 0;
   let re = regex!(r"\B\$\{([A-Z0-9_]*?)\}");
 
-  for cap in re.captures_iter(&template) {
+  for cap in re.captures_iter(template) {
     let range = cap.get(0).unwrap();
     content.push_str(&template[last..range.start()]);
     let key = cap.get(1).unwrap().as_str();
@@ -233,6 +233,7 @@ mod tests {
   fn parse_from() {
     let option = Config {
       version: String::from("1.0"),
+      environment: None,
       connections: vec![
         ConnectionOption::new(),
         ConnectionOption::new(),
@@ -276,4 +277,17 @@ mod tests {
     let result = into(option.as_bytes());
     assert!(result.is_err());
   }
+
+  #[test]
+  fn parse_migration_logging() {
+    let option = indoc!(
+      r#"
+        version: "1.0"
+        logging:
+          Many: [Error, Migration]
+      "#
+    );
+    assert!(into(option.as_bytes()).unwrap().logs_migrations());
+    assert!(!Config::new().logs_migrations());
+  }
 }
@@ -1,9 +1,15 @@
+use crate::metrics::MetricsSnapshot;
 use crate::{create_driver, Driver};
-use crate::{ConnectionOption, Result};
+use crate::{introspect, ConnectionOption, DatabaseSchema, Error, PooledSession, Provider, Result};
+use std::time::{Duration, Instant};
+
+/// How long cancelled statements get to return before the pool is closed anyway
+const CANCEL_GRACE: Duration = Duration::from_secs(1);
 
 #[derive(Debug)]
 pub struct Connection {
   name: String,
+  provider: Provider,
   is_connected: bool,
   driver: Box<dyn Driver + 'static>,
 }
@@ -13,16 +19,30 @@ impl Connection {
     let name = option.clone().name.unwrap_or_else(|| "default".to_string());
     Connection {
       name,
+      provider: option.datasource.provider.clone(),
       is_connected: false,
-      driver: create_driver(&option.datasource.provider),
+      driver: create_driver(option),
     }
   }
 
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn provider(&self) -> &Provider {
+    &self.provider
+  }
+
   pub fn connect(&mut self) -> Result<()> {
     if self.is_connected() {
-      panic!("Connection already exist")
+      return Err(Error::BadConnection(format!(
+        "The connection `{}` is already connected",
+        self.name
+      )));
     }
 
+    self.driver.connect()?;
+    self.driver.in_flight().open();
     self.is_connected = true;
     Ok(())
   }
@@ -33,10 +53,14 @@ impl Connection {
 
   pub fn disconnect(&mut self) -> Result<()> {
     if !self.is_connected() {
-      panic!("The connection is currently not connected")
+      return Err(Error::BadConnection(format!(
+        "The connection `{}` is not connected",
+        self.name
+      )));
     }
 
-    self.is_connected = true;
+    self.driver.disconnect()?;
+    self.is_connected = false;
     Ok(())
   }
 
@@ -44,8 +68,60 @@ impl Connection {
     unimplemented!()
   }
 
+  /// Stop handing out sessions, statements already running are left alone
+  pub fn drain(&self) {
+    self.driver.in_flight().drain();
+  }
+
+  /// Close the connection once its running statements finish.
+  ///
+  /// No new sessions are handed out, statements still running at the deadline
+  /// are cancelled. Returns false when statements had to be cancelled.
+  pub fn shutdown(&mut self, deadline: Instant) -> Result<bool> {
+    let in_flight = self.driver.in_flight();
+    in_flight.drain();
+
+    let finished = in_flight.wait(deadline);
+    if !finished {
+      for error in in_flight.cancel() {
+        self.driver.metrics().record_error(&error);
+      }
+      in_flight.wait(Instant::now() + CANCEL_GRACE);
+    }
+    in_flight.close();
+
+    if self.is_connected {
+      self.driver.disconnect()?;
+      self.is_connected = false;
+    }
+    Ok(finished)
+  }
+
   pub fn is_connected(&self) -> bool {
-    // TODO: check if the connection is valid
-    self.is_connected
+    self.is_connected && self.driver.is_connected()
+  }
+
+  /// Check a session out of the connection pool
+  pub fn session(&self) -> Result<PooledSession> {
+    self.driver.session()
+  }
+
+  /// Read back the tables, views and types of the database
+  pub fn introspect(&self) -> Result<DatabaseSchema> {
+    let mut session = self.session()?;
+    introspect(&mut session, &self.provider)
+  }
+
+  /// The number of statements running on the sessions of the connection
+  pub fn running(&self) -> usize {
+    self.driver.in_flight().len()
+  }
+
+  /// Take a copy of the pool and query metrics of the connection
+  pub fn metrics(&self) -> MetricsSnapshot {
+    self
+      .driver
+      .metrics()
+      .snapshot(&self.name, self.driver.pool_state())
   }
 }
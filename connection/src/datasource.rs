@@ -1,12 +1,13 @@
-use crate::Result;
+use crate::{ConnectionUrl, Error, Result};
 use std::fmt;
 use std::str::FromStr;
 
 use self::Provider::*;
 
-#[derive(Deserialize, Debug, Hash, Clone, Serialize)]
+#[derive(Deserialize, Debug, Hash, Clone, Serialize, Default)]
 pub enum Provider {
   #[cfg(feature = "postgres")]
+  #[default]
   #[serde(rename(serialize = "postgres", deserialize = "postgres"))]
   Postgres,
   #[cfg(feature = "mysql")]
@@ -46,39 +47,24 @@ impl fmt::Display for Provider {
   }
 }
 
-impl Default for Provider {
-  fn default() -> Self {
-    Postgres
-  }
-}
-
 impl Provider {
-  pub fn verify_url(self, url: &str) -> Result<()> {
-    match self {
+  /// Check the url uses a scheme this provider understands
+  pub fn verify_url(&self, url: &str) -> Result<()> {
+    let schemes: &[&str] = match *self {
       #[cfg(feature = "postgres")]
-      Postgres => {
-        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
-          Ok(())
-        } else {
-          panic!("Invalid database url `{}` provided for `postgres`", url);
-        }
-      }
+      Postgres => &["postgres://", "postgresql://"],
       #[cfg(feature = "mysql")]
-      MySQL => {
-        if url.starts_with("mysql://") {
-          Ok(())
-        } else {
-          panic!("Invalid database url `{}` provided for `mysql`", url);
-        }
-      }
+      MySQL => &["mysql://"],
       #[cfg(feature = "sqlite")]
-      SQLite => {
-        if url.starts_with("sqlite://") {
-          Ok(())
-        } else {
-          panic!("Invalid database url `{}` provided for `sqlite`", url);
-        }
-      }
+      SQLite => &["sqlite://"],
+    };
+    if schemes.iter().any(|scheme| url.starts_with(scheme)) {
+      Ok(())
+    } else {
+      Err(Error::invalid_url(
+        url,
+        format!("expected a `{}` url for `{}`", schemes.join("` or `"), self),
+      ))
     }
   }
 
@@ -96,6 +82,15 @@ pub struct Datasource {
   pub url: String,
 }
 
+impl Datasource {
+  /// Split the url into its hosts, credentials and parameters.
+  ///
+  /// Only the network providers use this form, `sqlite` urls are a file path.
+  pub fn connection_url(&self) -> Result<ConnectionUrl> {
+    ConnectionUrl::parse(&self.url)
+  }
+}
+
 impl Default for Datasource {
   fn default() -> Self {
     Datasource {
@@ -1,32 +1,96 @@
+#[cfg(feature = "mysql")]
 mod mysql;
+#[cfg(feature = "postgres")]
 mod postgres;
+#[cfg(feature = "sqlite")]
 mod sqlite;
 
+#[cfg(feature = "mysql")]
 use self::mysql::MySQLDriver;
+#[cfg(feature = "postgres")]
 use self::postgres::PotsgresDriver;
+#[cfg(feature = "sqlite")]
 use self::sqlite::SQLiteDriver;
 use crate::datasource::Provider;
+use crate::metrics::{Metrics, PoolState};
+use crate::{ConnectionOption, Hooks, InFlight, PooledSession, Result, Session};
+use std::sync::Arc;
 
-use crate::Result;
+pub trait Driver: std::fmt::Debug + Send + Sync {
+  /// Open the session pool, running `post_connect` on every session it creates
+  fn connect(&mut self) -> Result<()>;
 
-pub trait Driver: std::fmt::Debug {
-  // const whatervre: whatevr
+  /// Close the session pool, idle sessions are closed straight away and
+  /// checked out ones when they are returned
+  fn disconnect(&mut self) -> Result<()>;
 
-  fn connect(&self) -> Result<()>;
+  /// Initialise a newly opened session before the pool hands it out
+  fn post_connect(&self, session: &mut dyn Session) -> Result<()>;
 
-  fn disconnect(&self) -> Result<()>;
+  /// Check a session out of the pool
+  fn session(&self) -> Result<PooledSession>;
 
-  fn post_connect(&self) -> Result<()>;
+  fn is_connected(&self) -> bool;
+
+  /// The number of open and idle sessions in the pool
+  fn pool_state(&self) -> PoolState;
+
+  /// The counters and histograms recorded by the pool and its sessions
+  fn metrics(&self) -> &Metrics;
+
+  /// The statements running on the sessions checked out of the pool
+  fn in_flight(&self) -> &InFlight;
 
   fn establish(database_url: &str) -> Result<Self>
   where
     Self: Sized;
 }
 
-pub fn create_driver(source: &Provider) -> Box<dyn Driver + 'static> {
-  match source {
-    Provider::Postgres => Box::new(PotsgresDriver::new()),
-    Provider::MySQL => Box::new(MySQLDriver::new()),
-    Provider::SQLite => Box::new(SQLiteDriver::new()),
+/// The settings shared by every driver, taken from a `ConnectionOption`
+#[derive(Clone, Debug, Default)]
+pub struct DriverOptions {
+  pub url: String,
+  pub on_connect: Vec<String>,
+  pub hooks: Arc<Hooks>,
+  pub metrics: Arc<Metrics>,
+  pub in_flight: Arc<InFlight>,
+}
+
+impl DriverOptions {
+  pub fn new(option: &ConnectionOption) -> Self {
+    DriverOptions {
+      url: option.datasource.url.clone(),
+      on_connect: option.on_connect.clone().unwrap_or_default(),
+      hooks: Arc::new(option.hooks.clone()),
+      metrics: Arc::new(Metrics::new()),
+      in_flight: Arc::new(InFlight::default()),
+    }
+  }
+
+  pub fn from_url(url: &str) -> Self {
+    DriverOptions {
+      url: url.to_string(),
+      ..Default::default()
+    }
+  }
+
+  /// Run the configured `on_connect` statements followed by the `after_connect` hooks
+  pub fn post_connect(&self, session: &mut dyn Session) -> Result<()> {
+    for statement in self.on_connect.iter() {
+      session.batch_execute(statement)?;
+    }
+    self.hooks.run_after_connect(session)
+  }
+}
+
+pub fn create_driver(option: &ConnectionOption) -> Box<dyn Driver + 'static> {
+  let options = DriverOptions::new(option);
+  match option.datasource.provider {
+    #[cfg(feature = "postgres")]
+    Provider::Postgres => Box::new(PotsgresDriver::new(options)),
+    #[cfg(feature = "mysql")]
+    Provider::MySQL => Box::new(MySQLDriver::new(options)),
+    #[cfg(feature = "sqlite")]
+    Provider::SQLite => Box::new(SQLiteDriver::new(options)),
   }
 }
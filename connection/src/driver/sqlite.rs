@@ -1,31 +1,319 @@
-use crate::Driver;
-use crate::Result;
+use crate::driver::DriverOptions;
+use crate::metrics::{Metrics, PoolState};
+use crate::pool::{build_pool, pool_state, PooledSession};
+use crate::{Canceller, Driver, Error, InFlight, Result, Row, Session, Value};
+use r2d2::{ManageConnection, Pool};
+use rusqlite::types::ValueRef;
+use std::sync::Arc;
 
+/// Opens SQLite sessions for the pool
 #[derive(Clone, Debug)]
-pub struct SQLiteDriver {}
+pub struct SQLiteManager {
+  path: String,
+}
+
+impl ManageConnection for SQLiteManager {
+  type Connection = rusqlite::Connection;
+  type Error = Error;
+
+  fn connect(&self) -> Result<rusqlite::Connection> {
+    Ok(rusqlite::Connection::open(&self.path)?)
+  }
+
+  fn is_valid(&self, conn: &mut rusqlite::Connection) -> Result<()> {
+    Ok(conn.execute_batch("SELECT 1")?)
+  }
+
+  fn has_broken(&self, _conn: &mut rusqlite::Connection) -> bool {
+    false
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct SQLiteDriver {
+  options: DriverOptions,
+  pool: Option<Pool<SQLiteManager>>,
+}
 
 impl SQLiteDriver {
-  pub fn new() -> Self {
-    unimplemented!()
+  pub fn new(options: DriverOptions) -> Self {
+    SQLiteDriver {
+      options,
+      pool: None,
+    }
+  }
+
+  /// The database file referenced by a `sqlite://` url
+  fn path(&self) -> Result<String> {
+    match self.options.url.strip_prefix("sqlite://") {
+      Some(path) if !path.is_empty() => Ok(path.to_string()),
+      _ => Err(Error::invalid_url(
+        &self.options.url,
+        "expected `sqlite://` followed by a file path",
+      )),
+    }
   }
 }
 
 impl Driver for SQLiteDriver {
-  fn connect(&self) -> Result<()> {
+  fn connect(&mut self) -> Result<()> {
+    let manager = SQLiteManager { path: self.path()? };
+    self.pool = Some(build_pool(manager, self.clone(), &self.options.metrics)?);
     Ok(())
   }
-  fn disconnect(&self) -> Result<()> {
+
+  fn disconnect(&mut self) -> Result<()> {
+    self.pool = None;
     Ok(())
   }
 
-  fn post_connect(&self) -> Result<()> {
-    Ok(())
+  fn post_connect(&self, session: &mut dyn Session) -> Result<()> {
+    self.options.post_connect(session)
+  }
+
+  fn session(&self) -> Result<PooledSession> {
+    match self.pool {
+      Some(ref pool) => PooledSession::acquire(pool, &self.options),
+      None => Err(Error::BadConnection(
+        "The sqlite driver is not connected".to_string(),
+      )),
+    }
+  }
+
+  fn is_connected(&self) -> bool {
+    self.pool.is_some()
+  }
+
+  fn pool_state(&self) -> PoolState {
+    pool_state(&self.pool)
+  }
+
+  fn metrics(&self) -> &Metrics {
+    &self.options.metrics
+  }
+
+  fn in_flight(&self) -> &InFlight {
+    &self.options.in_flight
   }
 
   fn establish(database_url: &str) -> Result<Self>
   where
     Self: Sized,
   {
-    unimplemented!()
+    let mut driver = SQLiteDriver::new(DriverOptions::from_url(database_url));
+    driver.connect()?;
+    Ok(driver)
+  }
+}
+
+impl Session for rusqlite::Connection {
+  fn batch_execute(&mut self, sql: &str) -> Result<()> {
+    Ok(self.execute_batch(sql)?)
+  }
+
+  fn execute(&mut self, sql: &str) -> Result<u64> {
+    Ok(rusqlite::Connection::execute(self, sql, [])? as u64)
+  }
+
+  fn query(&mut self, sql: &str) -> Result<Vec<Row>> {
+    let mut statement = self.prepare(sql)?;
+    let columns: Vec<String> = statement
+      .column_names()
+      .into_iter()
+      .map(String::from)
+      .collect();
+    let mut rows = statement.query([])?;
+    let mut result = Vec::new();
+
+    while let Some(row) = rows.next()? {
+      let mut values = Vec::with_capacity(columns.len());
+      for i in 0..columns.len() {
+        values.push(match row.get_ref(i)? {
+          ValueRef::Null => Value::Null,
+          ValueRef::Integer(i) => Value::Integer(i),
+          ValueRef::Real(f) => Value::Real(f),
+          ValueRef::Text(t) => Value::Text(String::from_utf8_lossy(t).into_owned()),
+          ValueRef::Blob(b) => Value::Blob(b.to_vec()),
+        });
+      }
+      result.push(Row::new(columns.clone(), values));
+    }
+
+    Ok(result)
+  }
+
+  fn canceller(&self) -> Option<Canceller> {
+    let handle = self.get_interrupt_handle();
+    Some(Arc::new(move || {
+      handle.interrupt();
+      Ok(())
+    }))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  extern crate tempfile;
+
+  use super::*;
+  use crate::ConnectionOption;
+  use crate::Datasource;
+  use crate::Provider;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::time::{Duration, Instant};
+
+  fn option(dir: &tempfile::TempDir) -> ConnectionOption {
+    ConnectionOption {
+      datasource: Datasource {
+        provider: Provider::SQLite,
+        url: format!("sqlite://{}", dir.path().join("test.db").display()),
+      },
+      ..ConnectionOption::default()
+    }
+  }
+
+  #[test]
+  fn test_invalid_url() {
+    let mut driver = SQLiteDriver::new(DriverOptions::from_url("sqlite://"));
+    assert!(driver.connect().is_err());
+    assert!(!driver.is_connected());
+  }
+
+  #[test]
+  fn test_session_before_connect() {
+    let driver = SQLiteDriver::new(DriverOptions::from_url("sqlite://test.db"));
+    assert!(driver.session().is_err());
+  }
+
+  #[test]
+  fn test_query() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite://{}", dir.path().join("test.db").display());
+    let driver = SQLiteDriver::establish(&url).unwrap();
+    let mut session = driver.session().unwrap();
+    session
+      .batch_execute(
+        "CREATE TABLE users (id INTEGER, name TEXT); INSERT INTO users VALUES (1, 'spectre');",
+      )
+      .unwrap();
+    let rows = session.query("SELECT id, name FROM users").unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get_i64("id"), Some(1));
+    assert_eq!(rows[0].get_string("name"), Some("spectre".to_string()));
+  }
+
+  #[test]
+  fn test_on_connect_statements() {
+    let dir = tempfile::tempdir().unwrap();
+    let option = option(&dir).on_connect("PRAGMA foreign_keys = ON");
+    let mut driver = SQLiteDriver::new(DriverOptions::new(&option));
+    driver.connect().unwrap();
+    let rows = driver
+      .session()
+      .unwrap()
+      .query("PRAGMA foreign_keys")
+      .unwrap();
+    assert_eq!(rows[0].get_i64("foreign_keys"), Some(1));
+  }
+
+  #[test]
+  fn test_lifecycle_hooks() {
+    let dir = tempfile::tempdir().unwrap();
+    let connected = Arc::new(AtomicUsize::new(0));
+    let acquired = Arc::new(AtomicUsize::new(0));
+    let released = Arc::new(AtomicUsize::new(0));
+    let (c, a, r) = (connected.clone(), acquired.clone(), released.clone());
+    let option = option(&dir)
+      .after_connect(move |_| {
+        c.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+      })
+      .after_acquire(move |_| {
+        a.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+      })
+      .before_release(move |_| {
+        r.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+      });
+    let mut driver = SQLiteDriver::new(DriverOptions::new(&option));
+    driver.connect().unwrap();
+
+    let pool_size = driver.pool.as_ref().unwrap().state().connections as usize;
+    assert_eq!(connected.load(Ordering::SeqCst), pool_size);
+
+    driver.session().unwrap().release().unwrap();
+    drop(driver.session().unwrap());
+    assert_eq!(acquired.load(Ordering::SeqCst), 2);
+    assert_eq!(released.load(Ordering::SeqCst), 2);
+  }
+
+  #[test]
+  fn test_metrics() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut connection = crate::Connection::new(&option(&dir));
+    connection.connect().unwrap();
+    let mut session = connection.session().unwrap();
+    session.execute("CREATE TABLE users (id INTEGER)").unwrap();
+    session.query("SELECT * FROM users").unwrap();
+    assert!(session.query("SELECT * FROM missing").is_err());
+
+    let metrics = connection.metrics();
+    assert_eq!(metrics.connection, "default");
+    assert_eq!(metrics.active_sessions, 1);
+    assert_eq!(metrics.idle_sessions, metrics.max_sessions - 1);
+    assert_eq!(metrics.acquire_wait.count, 1);
+    assert_eq!(metrics.queries, 3);
+    assert_eq!(metrics.query_latency.count, 3);
+    assert_eq!(metrics.errors.get("driver"), Some(&1));
+    assert_eq!(metrics.reconnects, 0);
+
+    drop(session);
+    assert_eq!(connection.metrics().active_sessions, 0);
+  }
+
+  #[test]
+  fn test_shutdown_waits_for_running_statements() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut connection = crate::Connection::new(&option(&dir));
+    connection.connect().unwrap();
+    let mut session = connection.session().unwrap();
+
+    assert!(connection
+      .shutdown(Instant::now() + Duration::from_secs(1))
+      .unwrap());
+    assert!(!connection.is_connected());
+    assert!(connection.session().is_err());
+    assert!(session.query("SELECT 1").is_err());
+  }
+
+  #[test]
+  fn test_shutdown_cancels_statements_past_deadline() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut connection = crate::Connection::new(&option(&dir));
+    connection.connect().unwrap();
+    let mut session = connection.session().unwrap();
+    let running = std::thread::spawn(move || {
+      session.query(
+        "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c) SELECT count(*) FROM c",
+      )
+    });
+    while connection.running() == 0 {
+      std::thread::sleep(Duration::from_millis(1));
+    }
+
+    assert!(!connection
+      .shutdown(Instant::now() + Duration::from_millis(50))
+      .unwrap());
+    assert!(running.join().unwrap().is_err());
+  }
+
+  #[test]
+  fn test_failing_after_acquire_hook() {
+    let dir = tempfile::tempdir().unwrap();
+    let option = option(&dir).after_acquire(|_| Err(Error::BadConnection("rejected".to_string())));
+    let mut driver = SQLiteDriver::new(DriverOptions::new(&option));
+    driver.connect().unwrap();
+    assert!(driver.session().is_err());
   }
 }
@@ -3,14 +3,105 @@ use std::ffi::NulError;
 use std::{error, fmt};
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
   /// The connection URL contained a `NUL` byte.
   InvalidString(NulError),
   BadConnection(String),
-  /// The connection URL could not be parsed.
-  InvalidConnectionUrl(String),
-  #[doc(hidden)]
-  __Nonexhaustive,
+  /// The connection URL could not be parsed or does not suit the provider.
+  InvalidConnectionUrl {
+    url: String,
+    reason: String,
+    source: Option<Box<dyn error::Error + Send + Sync>>,
+  },
+  /// A connection with the same name is already registered.
+  DuplicateConnection(String),
+  /// No connection with the given name is registered or configured.
+  UnknownConnection(String),
+  /// The database driver reported an error while talking to the server.
+  DriverError(Box<dyn error::Error + Send + Sync>),
+  /// A session could not be checked out of the connection pool.
+  PoolError(r2d2::Error),
+  /// The connection is shutting down and no longer runs statements.
+  ShuttingDown,
+}
+
+impl Error {
+  pub(crate) fn invalid_url(url: &str, reason: impl Into<String>) -> Self {
+    Error::InvalidConnectionUrl {
+      url: url.to_string(),
+      reason: reason.into(),
+      source: None,
+    }
+  }
+
+  /// Attach the error reported by the driver while reading the url
+  pub(crate) fn caused_by(self, cause: impl Into<Box<dyn error::Error + Send + Sync>>) -> Self {
+    match self {
+      Error::InvalidConnectionUrl { url, reason, .. } => Error::InvalidConnectionUrl {
+        url,
+        reason,
+        source: Some(cause.into()),
+      },
+      other => other,
+    }
+  }
+
+  /// Whether the database refused a lock held by another session, the Postgres
+  /// `lock_not_available` error or a busy SQLite database
+  pub fn is_lock_unavailable(&self) -> bool {
+    #[cfg(feature = "postgres")]
+    if let Some(e) = self.driver_error::<postgres::Error>() {
+      return e.code() == Some(&postgres::error::SqlState::LOCK_NOT_AVAILABLE);
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(e) = self.driver_error::<rusqlite::Error>() {
+      return matches!(
+        e.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy) | Some(rusqlite::ErrorCode::DatabaseLocked)
+      );
+    }
+    false
+  }
+
+  /// Whether the statement broke a primary key, unique or other constraint
+  pub fn is_constraint_violation(&self) -> bool {
+    #[cfg(feature = "postgres")]
+    if let Some(e) = self.driver_error::<postgres::Error>() {
+      return e.code().is_some_and(|code| code.code().starts_with("23"));
+    }
+    #[cfg(feature = "mysql")]
+    if let Some(mysql::Error::MySqlError(e)) = self.driver_error::<mysql::Error>() {
+      return e.state.starts_with("23");
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(e) = self.driver_error::<rusqlite::Error>() {
+      return e.sqlite_error_code() == Some(rusqlite::ErrorCode::ConstraintViolation);
+    }
+    false
+  }
+
+  #[cfg(any(feature = "postgres", feature = "mysql", feature = "sqlite"))]
+  fn driver_error<E: error::Error + 'static>(&self) -> Option<&E> {
+    match self {
+      Error::DriverError(e) => e.downcast_ref::<E>(),
+      _ => None,
+    }
+  }
+
+  /// A short name for the kind of error, used to group errors in metrics
+  pub fn kind(&self) -> &'static str {
+    match *self {
+      Error::InvalidString(_) => "invalid_string",
+      Error::BadConnection(_) => "bad_connection",
+      Error::InvalidConnectionUrl { .. } => "invalid_connection_url",
+      Error::DuplicateConnection(_) => "duplicate_connection",
+      Error::UnknownConnection(_) => "unknown_connection",
+      Error::DriverError(_) => "driver",
+      Error::PoolError(_) => "pool",
+      Error::ShuttingDown => "shutting_down",
+    }
+  }
 }
 
 impl From<NulError> for Error {
@@ -19,13 +110,50 @@ impl From<NulError> for Error {
   }
 }
 
+impl From<r2d2::Error> for Error {
+  fn from(e: r2d2::Error) -> Self {
+    Error::PoolError(e)
+  }
+}
+
+#[cfg(feature = "postgres")]
+impl From<postgres::Error> for Error {
+  fn from(e: postgres::Error) -> Self {
+    Error::DriverError(Box::new(e))
+  }
+}
+
+#[cfg(feature = "mysql")]
+impl From<mysql::Error> for Error {
+  fn from(e: mysql::Error) -> Self {
+    Error::DriverError(Box::new(e))
+  }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+  fn from(e: rusqlite::Error) -> Self {
+    Error::DriverError(Box::new(e))
+  }
+}
+
 impl fmt::Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match *self {
       Error::InvalidString(ref nul_err) => nul_err.fmt(f),
       Error::BadConnection(ref s) => write!(f, "{}", s),
-      Error::InvalidConnectionUrl(ref s) => write!(f, "{}", s),
-      _ => unreachable!(),
+      Error::InvalidConnectionUrl {
+        ref url,
+        ref reason,
+        ..
+      } => write!(f, "Invalid database url `{}`: {}", url, reason),
+      Error::DuplicateConnection(ref name) => {
+        write!(f, "A connection named `{}` is already registered", name)
+      }
+      Error::UnknownConnection(ref name) => write!(f, "No connection named `{}` exists", name),
+      Error::DriverError(ref err) => err.fmt(f),
+      Error::PoolError(ref err) => err.fmt(f),
+      Error::ShuttingDown => f.write_str("The connection is shutting down"),
     }
   }
 }
@@ -34,6 +162,12 @@ impl error::Error for Error {
   fn cause(&self) -> Option<&dyn error::Error> {
     match *self {
       Error::InvalidString(ref e) => Some(e),
+      Error::DriverError(ref e) => Some(e.as_ref()),
+      Error::InvalidConnectionUrl {
+        source: Some(ref e),
+        ..
+      } => Some(e.as_ref()),
+      Error::PoolError(ref e) => Some(e),
       _ => None,
     }
   }
@@ -41,6 +175,11 @@ impl error::Error for Error {
   fn source(&self) -> Option<&(dyn error::Error + 'static)> {
     match self {
       Error::InvalidString(err) => Some(err),
+      Error::DriverError(err) => Some(err.as_ref()),
+      Error::InvalidConnectionUrl {
+        source: Some(err), ..
+      } => Some(err.as_ref()),
+      Error::PoolError(err) => Some(err),
       _ => None,
     }
   }
@@ -59,7 +198,27 @@ mod test {
   }
   #[test]
   fn test_invalid_connection_url() {
-    let err = Error::InvalidConnectionUrl(String::from("Invalid connection URL provided"));
+    let err = Error::invalid_url("localhost", "missing scheme");
+    assert_eq!(
+      format!("{}", err),
+      "Invalid database url `localhost`: missing scheme"
+    );
     assert!(err.source().is_none());
+
+    let io_err: std::io::Error = std::io::ErrorKind::InvalidInput.into();
+    let err = Error::invalid_url("mysql://localhost", "bad option").caused_by(io_err);
+    assert!(err.source().is_some());
+  }
+
+  #[test]
+  fn test_driver_error_source() {
+    let io_err: std::io::Error = std::io::ErrorKind::ConnectionRefused.into();
+    let err = Error::DriverError(Box::new(io_err));
+    assert_eq!(format!("{}", err), "connection refused");
+    assert!(err
+      .source()
+      .unwrap()
+      .downcast_ref::<std::io::Error>()
+      .is_some());
   }
 }
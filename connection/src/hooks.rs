@@ -0,0 +1,79 @@
+use crate::{Result, Session};
+use std::fmt;
+use std::sync::Arc;
+
+/// A callback run against a pooled session
+pub type Hook = Arc<dyn Fn(&mut dyn Session) -> Result<()> + Send + Sync>;
+
+/// Callbacks registered programmatically on a `ConnectionOption`.
+///
+/// - `after_connect` runs once for every new session opened by the pool,
+///   after the `on_connect` statements of the option.
+/// - `after_acquire` runs every time a session is checked out of the pool.
+/// - `before_release` runs every time a session is given back to the pool.
+#[derive(Clone, Default)]
+pub struct Hooks {
+  after_connect: Vec<Hook>,
+  after_acquire: Vec<Hook>,
+  before_release: Vec<Hook>,
+}
+
+impl Hooks {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn after_connect<F>(&mut self, hook: F)
+  where
+    F: Fn(&mut dyn Session) -> Result<()> + Send + Sync + 'static,
+  {
+    self.after_connect.push(Arc::new(hook));
+  }
+
+  pub fn after_acquire<F>(&mut self, hook: F)
+  where
+    F: Fn(&mut dyn Session) -> Result<()> + Send + Sync + 'static,
+  {
+    self.after_acquire.push(Arc::new(hook));
+  }
+
+  pub fn before_release<F>(&mut self, hook: F)
+  where
+    F: Fn(&mut dyn Session) -> Result<()> + Send + Sync + 'static,
+  {
+    self.before_release.push(Arc::new(hook));
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.after_connect.is_empty() && self.after_acquire.is_empty() && self.before_release.is_empty()
+  }
+
+  pub(crate) fn run_after_connect(&self, session: &mut dyn Session) -> Result<()> {
+    run(&self.after_connect, session)
+  }
+
+  pub(crate) fn run_after_acquire(&self, session: &mut dyn Session) -> Result<()> {
+    run(&self.after_acquire, session)
+  }
+
+  pub(crate) fn run_before_release(&self, session: &mut dyn Session) -> Result<()> {
+    run(&self.before_release, session)
+  }
+}
+
+fn run(hooks: &[Hook], session: &mut dyn Session) -> Result<()> {
+  for hook in hooks.iter() {
+    hook(session)?;
+  }
+  Ok(())
+}
+
+impl fmt::Debug for Hooks {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Hooks")
+      .field("after_connect", &self.after_connect.len())
+      .field("after_acquire", &self.after_acquire.len())
+      .field("before_release", &self.before_release.len())
+      .finish()
+  }
+}
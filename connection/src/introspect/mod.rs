@@ -0,0 +1,330 @@
+#[cfg(feature = "mysql")]
+mod mysql;
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(any(feature = "postgres", feature = "mysql"))]
+use crate::Row;
+use crate::{Provider, Result, Session};
+
+/// What a database holds, as read back from its catalog.
+///
+/// Types are kept as the database reports them, e.g. `character varying(255)`
+/// on Postgres or `varchar(255)` on MySQL, and defaults as their SQL expression.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DatabaseSchema {
+  pub tables: Vec<Table>,
+  pub views: Vec<View>,
+  /// Postgres enum types, MySQL spells the values out in the column type
+  pub enums: Vec<EnumType>,
+  pub sequences: Vec<Sequence>,
+  pub triggers: Vec<Trigger>,
+}
+
+impl DatabaseSchema {
+  pub fn table(&self, name: &str) -> Option<&Table> {
+    self.tables.iter().find(|table| table.name == name)
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+  pub name: String,
+  pub columns: Vec<Column>,
+  pub primary_key: Option<PrimaryKey>,
+  pub foreign_keys: Vec<ForeignKey>,
+  /// Indexes not backing a primary key or unique constraint
+  pub indexes: Vec<Index>,
+  pub unique_constraints: Vec<UniqueConstraint>,
+  pub checks: Vec<CheckConstraint>,
+}
+
+impl Table {
+  pub fn column(&self, name: &str) -> Option<&Column> {
+    self.columns.iter().find(|column| column.name == name)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+  pub name: String,
+  pub data_type: String,
+  pub nullable: bool,
+  pub default: Option<String>,
+  /// Filled in by the database, from a sequence, identity or `AUTO_INCREMENT`
+  pub auto_increment: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrimaryKey {
+  /// SQLite does not name its constraints
+  pub name: Option<String>,
+  pub columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+  pub name: Option<String>,
+  pub columns: Vec<String>,
+  pub referenced_table: String,
+  pub referenced_columns: Vec<String>,
+  /// The referential actions in SQL, e.g. `CASCADE` or `NO ACTION`
+  pub on_delete: String,
+  pub on_update: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index {
+  pub name: String,
+  pub columns: Vec<String>,
+  /// Whether the index also covers expressions, which `columns` leaves out
+  pub expressions: bool,
+  pub unique: bool,
+  /// The `WHERE` condition of a partial index
+  pub predicate: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniqueConstraint {
+  pub name: Option<String>,
+  pub columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckConstraint {
+  pub name: Option<String>,
+  pub expression: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct View {
+  pub name: String,
+  pub definition: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumType {
+  pub name: String,
+  pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sequence {
+  pub name: String,
+  pub start: i64,
+  pub increment: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trigger {
+  pub name: String,
+  pub table: String,
+}
+
+/// Read the tables, views and types of the current schema, leaving out the
+/// catalog of the database itself
+pub fn introspect(session: &mut dyn Session, provider: &Provider) -> Result<DatabaseSchema> {
+  match *provider {
+    #[cfg(feature = "postgres")]
+    Provider::Postgres => postgres::introspect(session),
+    #[cfg(feature = "mysql")]
+    Provider::MySQL => mysql::introspect(session),
+    #[cfg(feature = "sqlite")]
+    Provider::SQLite => sqlite::introspect(session),
+  }
+}
+
+/// The catalog queries of a provider, each naming its columns the way
+/// [`read_catalog`] reads them
+#[cfg(any(feature = "postgres", feature = "mysql"))]
+struct Catalog {
+  /// `name`
+  tables: &'static str,
+  /// `table_name`, `name`, `data_type`, `nullable`, `column_default`, `auto_increment`
+  columns: &'static str,
+  /// One row per column of a `PRIMARY KEY`, `UNIQUE` or `FOREIGN KEY` constraint:
+  /// `table_name`, `name`, `kind`, `column_name`, `referenced_table`,
+  /// `referenced_column`, `on_delete`, `on_update`
+  constraints: &'static str,
+  /// `table_name`, `name`, `expression`
+  checks: &'static str,
+  /// Returns a row when the catalog of the server has the table `checks` reads
+  has_checks: Option<&'static str>,
+  /// One row per column: `table_name`, `name`, `is_unique`, `column_name`,
+  /// `is_expression`, `predicate`
+  indexes: &'static str,
+  /// `name`, `definition`
+  views: &'static str,
+  /// One row per value: `name`, `value`
+  enums: Option<&'static str>,
+  /// `name`, `start_value`, `increment`
+  sequences: Option<&'static str>,
+  /// `table_name`, `name`
+  triggers: &'static str,
+}
+
+#[cfg(any(feature = "postgres", feature = "mysql"))]
+fn read_catalog(session: &mut dyn Session, catalog: &Catalog) -> Result<DatabaseSchema> {
+  let mut tables: Vec<Table> = session
+    .query(catalog.tables)?
+    .iter()
+    .map(|row| Table {
+      name: row.get_string("name").unwrap_or_default(),
+      ..Table::default()
+    })
+    .collect();
+
+  for row in session.query(catalog.columns)?.iter() {
+    // views have columns too
+    if let Some(table) = table_mut(&mut tables, row) {
+      table.columns.push(Column {
+        name: row.get_string("name").unwrap_or_default(),
+        data_type: row.get_string("data_type").unwrap_or_default(),
+        nullable: row.get_bool("nullable").unwrap_or(true),
+        default: row.get_string("column_default"),
+        auto_increment: row.get_bool("auto_increment").unwrap_or(false),
+      });
+    }
+  }
+
+  let rows = session.query(catalog.constraints)?;
+  for group in group_rows(&rows) {
+    let first = group[0];
+    let table = match table_mut(&mut tables, first) {
+      Some(table) => table,
+      None => continue,
+    };
+    let name = first.get_string("name");
+    let columns = strings(&group, "column_name");
+    match first.get_string("kind").as_deref() {
+      Some("PRIMARY KEY") => table.primary_key = Some(PrimaryKey { name, columns }),
+      Some("UNIQUE") => table
+        .unique_constraints
+        .push(UniqueConstraint { name, columns }),
+      Some("FOREIGN KEY") => table.foreign_keys.push(ForeignKey {
+        name,
+        columns,
+        referenced_table: first.get_string("referenced_table").unwrap_or_default(),
+        referenced_columns: strings(&group, "referenced_column"),
+        on_delete: first.get_string("on_delete").unwrap_or_default(),
+        on_update: first.get_string("on_update").unwrap_or_default(),
+      }),
+      _ => {}
+    }
+  }
+
+  let has_checks = match catalog.has_checks {
+    Some(sql) => !session.query(sql)?.is_empty(),
+    None => true,
+  };
+  let checks = match has_checks {
+    true => session.query(catalog.checks)?,
+    false => Vec::new(),
+  };
+  for row in checks.iter() {
+    if let Some(table) = table_mut(&mut tables, row) {
+      table.checks.push(CheckConstraint {
+        name: row.get_string("name"),
+        expression: row.get_string("expression").unwrap_or_default(),
+      });
+    }
+  }
+
+  let rows = session.query(catalog.indexes)?;
+  for group in group_rows(&rows) {
+    if let Some(table) = table_mut(&mut tables, group[0]) {
+      table.indexes.push(Index {
+        name: group[0].get_string("name").unwrap_or_default(),
+        columns: strings(&group, "column_name"),
+        expressions: group
+          .iter()
+          .any(|row| row.get_bool("is_expression") == Some(true)),
+        unique: group[0].get_bool("is_unique").unwrap_or(false),
+        predicate: group[0].get_string("predicate"),
+      });
+    }
+  }
+
+  let views = session
+    .query(catalog.views)?
+    .iter()
+    .map(|row| View {
+      name: row.get_string("name").unwrap_or_default(),
+      definition: row.get_string("definition"),
+    })
+    .collect();
+
+  let enums = match catalog.enums {
+    Some(sql) => group_rows(&session.query(sql)?)
+      .iter()
+      .map(|group| EnumType {
+        name: group[0].get_string("name").unwrap_or_default(),
+        values: strings(group, "value"),
+      })
+      .collect(),
+    None => Vec::new(),
+  };
+
+  let sequences = match catalog.sequences {
+    Some(sql) => session
+      .query(sql)?
+      .iter()
+      .map(|row| Sequence {
+        name: row.get_string("name").unwrap_or_default(),
+        start: row.get_i64("start_value").unwrap_or(1),
+        increment: row.get_i64("increment").unwrap_or(1),
+      })
+      .collect(),
+    None => Vec::new(),
+  };
+
+  let triggers = session
+    .query(catalog.triggers)?
+    .iter()
+    .map(|row| Trigger {
+      name: row.get_string("name").unwrap_or_default(),
+      table: row.get_string("table_name").unwrap_or_default(),
+    })
+    .collect();
+
+  Ok(DatabaseSchema {
+    tables,
+    views,
+    enums,
+    sequences,
+    triggers,
+  })
+}
+
+/// The columns of a constraint or index come back one row each, ordered by
+/// position. Group the rows of each one, telling them apart by table and name.
+#[cfg(any(feature = "postgres", feature = "mysql"))]
+fn group_rows(rows: &[Row]) -> Vec<Vec<&Row>> {
+  let key = |row: &Row| (row.get_string("table_name"), row.get_string("name"));
+  let mut groups: Vec<Vec<&Row>> = Vec::new();
+  for row in rows.iter() {
+    match groups.last_mut() {
+      Some(group) if key(group[0]) == key(row) => group.push(row),
+      _ => groups.push(vec![row]),
+    }
+  }
+  groups
+}
+
+/// Read one column of every row in a group
+#[cfg(any(feature = "postgres", feature = "mysql"))]
+fn strings(group: &[&Row], column: &str) -> Vec<String> {
+  group
+    .iter()
+    .filter_map(|row| row.get_string(column))
+    .collect()
+}
+
+/// The table a row of a catalog query belongs to
+#[cfg(any(feature = "postgres", feature = "mysql"))]
+fn table_mut<'a>(tables: &'a mut [Table], row: &Row) -> Option<&'a mut Table> {
+  let name = row.get_string("table_name")?;
+  tables.iter_mut().find(|table| table.name == name)
+}
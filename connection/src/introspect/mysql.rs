@@ -0,0 +1,58 @@
+use super::{read_catalog, Catalog, DatabaseSchema};
+use crate::{Result, Session};
+
+/// Read from `information_schema`, restricted to the database of the session.
+/// Unique indexes are listed as constraints, MySQL makes no difference.
+const CATALOG: Catalog = Catalog {
+  tables: "SELECT TABLE_NAME AS name FROM information_schema.TABLES \
+    WHERE TABLE_SCHEMA = DATABASE() AND TABLE_TYPE = 'BASE TABLE' \
+    ORDER BY TABLE_NAME",
+  columns: "SELECT TABLE_NAME AS table_name, COLUMN_NAME AS name, COLUMN_TYPE AS data_type, \
+    IS_NULLABLE = 'YES' AS nullable, COLUMN_DEFAULT AS column_default, \
+    EXTRA LIKE '%auto_increment%' AS auto_increment \
+    FROM information_schema.COLUMNS \
+    WHERE TABLE_SCHEMA = DATABASE() \
+    ORDER BY TABLE_NAME, ORDINAL_POSITION",
+  constraints: "SELECT tc.TABLE_NAME AS table_name, tc.CONSTRAINT_NAME AS name, \
+    tc.CONSTRAINT_TYPE AS kind, k.COLUMN_NAME AS column_name, \
+    k.REFERENCED_TABLE_NAME AS referenced_table, k.REFERENCED_COLUMN_NAME AS referenced_column, \
+    rc.DELETE_RULE AS on_delete, rc.UPDATE_RULE AS on_update \
+    FROM information_schema.TABLE_CONSTRAINTS tc \
+    JOIN information_schema.KEY_COLUMN_USAGE k ON k.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA \
+      AND k.CONSTRAINT_NAME = tc.CONSTRAINT_NAME AND k.TABLE_NAME = tc.TABLE_NAME \
+    LEFT JOIN information_schema.REFERENTIAL_CONSTRAINTS rc \
+      ON rc.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA \
+      AND rc.CONSTRAINT_NAME = tc.CONSTRAINT_NAME AND rc.TABLE_NAME = tc.TABLE_NAME \
+    WHERE tc.TABLE_SCHEMA = DATABASE() \
+      AND tc.CONSTRAINT_TYPE IN ('PRIMARY KEY', 'UNIQUE', 'FOREIGN KEY') \
+    ORDER BY tc.TABLE_NAME, tc.CONSTRAINT_NAME, k.ORDINAL_POSITION",
+  // CHECK_CONSTRAINTS has no table name, hence the join
+  checks: "SELECT tc.TABLE_NAME AS table_name, cc.CONSTRAINT_NAME AS name, \
+    cc.CHECK_CLAUSE AS expression \
+    FROM information_schema.CHECK_CONSTRAINTS cc \
+    JOIN information_schema.TABLE_CONSTRAINTS tc ON tc.CONSTRAINT_SCHEMA = cc.CONSTRAINT_SCHEMA \
+      AND tc.CONSTRAINT_NAME = cc.CONSTRAINT_NAME AND tc.CONSTRAINT_TYPE = 'CHECK' \
+    WHERE cc.CONSTRAINT_SCHEMA = DATABASE() \
+    ORDER BY tc.TABLE_NAME, cc.CONSTRAINT_NAME",
+  // MySQL enforces check constraints and lists them from 8.0.16 on
+  has_checks: Some(
+    "SELECT TABLE_NAME AS name FROM information_schema.TABLES \
+    WHERE TABLE_SCHEMA = 'information_schema' AND TABLE_NAME = 'CHECK_CONSTRAINTS'",
+  ),
+  indexes: "SELECT TABLE_NAME AS table_name, INDEX_NAME AS name, NON_UNIQUE = 0 AS is_unique, \
+    COLUMN_NAME AS column_name, COLUMN_NAME IS NULL AS is_expression \
+    FROM information_schema.STATISTICS \
+    WHERE TABLE_SCHEMA = DATABASE() AND NON_UNIQUE = 1 \
+    ORDER BY TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX",
+  views: "SELECT TABLE_NAME AS name, VIEW_DEFINITION AS definition \
+    FROM information_schema.VIEWS WHERE TABLE_SCHEMA = DATABASE() ORDER BY TABLE_NAME",
+  enums: None,
+  sequences: None,
+  triggers: "SELECT EVENT_OBJECT_TABLE AS table_name, TRIGGER_NAME AS name \
+    FROM information_schema.TRIGGERS WHERE TRIGGER_SCHEMA = DATABASE() \
+    ORDER BY EVENT_OBJECT_TABLE, TRIGGER_NAME",
+};
+
+pub(super) fn introspect(session: &mut dyn Session) -> Result<DatabaseSchema> {
+  read_catalog(session, &CATALOG)
+}
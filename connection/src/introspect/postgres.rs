@@ -0,0 +1,85 @@
+use super::{read_catalog, Catalog, DatabaseSchema};
+use crate::{Result, Session};
+
+/// Read from `pg_catalog`, `information_schema` leaves out enum types,
+/// expression defaults and the indexes not backing a constraint
+const CATALOG: Catalog = Catalog {
+  tables: "SELECT c.relname AS name \
+    FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+    WHERE n.nspname = current_schema() AND c.relkind IN ('r', 'p') \
+    ORDER BY c.relname",
+  columns: "SELECT c.relname AS table_name, a.attname AS name, \
+    format_type(a.atttypid, a.atttypmod) AS data_type, NOT a.attnotnull AS nullable, \
+    pg_get_expr(d.adbin, d.adrelid) AS column_default, \
+    (a.attidentity <> '' OR coalesce(pg_get_expr(d.adbin, d.adrelid), '') LIKE 'nextval(%') \
+      AS auto_increment \
+    FROM pg_attribute a \
+    JOIN pg_class c ON c.oid = a.attrelid \
+    JOIN pg_namespace n ON n.oid = c.relnamespace \
+    LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+    WHERE n.nspname = current_schema() AND c.relkind IN ('r', 'p') \
+      AND a.attnum > 0 AND NOT a.attisdropped \
+    ORDER BY c.relname, a.attnum",
+  constraints: "SELECT c.relname AS table_name, con.conname AS name, \
+    CASE con.contype WHEN 'p' THEN 'PRIMARY KEY' WHEN 'u' THEN 'UNIQUE' ELSE 'FOREIGN KEY' END \
+      AS kind, \
+    a.attname AS column_name, rc.relname AS referenced_table, ra.attname AS referenced_column, \
+    CASE con.confdeltype WHEN 'r' THEN 'RESTRICT' WHEN 'c' THEN 'CASCADE' \
+      WHEN 'n' THEN 'SET NULL' WHEN 'd' THEN 'SET DEFAULT' ELSE 'NO ACTION' END AS on_delete, \
+    CASE con.confupdtype WHEN 'r' THEN 'RESTRICT' WHEN 'c' THEN 'CASCADE' \
+      WHEN 'n' THEN 'SET NULL' WHEN 'd' THEN 'SET DEFAULT' ELSE 'NO ACTION' END AS on_update \
+    FROM pg_constraint con \
+    JOIN pg_class c ON c.oid = con.conrelid \
+    JOIN pg_namespace n ON n.oid = c.relnamespace \
+    CROSS JOIN LATERAL unnest(con.conkey, con.confkey) WITH ORDINALITY AS k(attnum, fattnum, position) \
+    JOIN pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum \
+    LEFT JOIN pg_class rc ON rc.oid = con.confrelid \
+    LEFT JOIN pg_attribute ra ON ra.attrelid = con.confrelid AND ra.attnum = k.fattnum \
+    WHERE n.nspname = current_schema() AND con.contype IN ('p', 'u', 'f') \
+    ORDER BY c.relname, con.conname, k.position",
+  checks: "SELECT c.relname AS table_name, con.conname AS name, \
+    pg_get_expr(con.conbin, con.conrelid) AS expression \
+    FROM pg_constraint con \
+    JOIN pg_class c ON c.oid = con.conrelid \
+    JOIN pg_namespace n ON n.oid = c.relnamespace \
+    WHERE n.nspname = current_schema() AND con.contype = 'c' \
+    ORDER BY c.relname, con.conname",
+  has_checks: None,
+  indexes: "SELECT t.relname AS table_name, i.relname AS name, ix.indisunique AS is_unique, \
+    a.attname AS column_name, k.attnum = 0 AS is_expression, \
+    pg_get_expr(ix.indpred, ix.indrelid) AS predicate \
+    FROM pg_index ix \
+    JOIN pg_class i ON i.oid = ix.indexrelid \
+    JOIN pg_class t ON t.oid = ix.indrelid \
+    JOIN pg_namespace n ON n.oid = t.relnamespace \
+    CROSS JOIN LATERAL unnest(ix.indkey::int2[]) WITH ORDINALITY AS k(attnum, position) \
+    LEFT JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = k.attnum \
+    WHERE n.nspname = current_schema() AND NOT EXISTS ( \
+      SELECT 1 FROM pg_constraint con \
+      WHERE con.conindid = ix.indexrelid AND con.contype IN ('p', 'u')) \
+    ORDER BY t.relname, i.relname, k.position",
+  views: "SELECT viewname AS name, definition FROM pg_views \
+    WHERE schemaname = current_schema() ORDER BY viewname",
+  enums: Some(
+    "SELECT t.typname AS name, e.enumlabel AS value \
+    FROM pg_type t \
+    JOIN pg_enum e ON e.enumtypid = t.oid \
+    JOIN pg_namespace n ON n.oid = t.typnamespace \
+    WHERE n.nspname = current_schema() \
+    ORDER BY t.typname, e.enumsortorder",
+  ),
+  sequences: Some(
+    "SELECT sequence_name AS name, start_value, increment FROM information_schema.sequences \
+    WHERE sequence_schema = current_schema() ORDER BY sequence_name",
+  ),
+  triggers: "SELECT c.relname AS table_name, t.tgname AS name \
+    FROM pg_trigger t \
+    JOIN pg_class c ON c.oid = t.tgrelid \
+    JOIN pg_namespace n ON n.oid = c.relnamespace \
+    WHERE n.nspname = current_schema() AND NOT t.tgisinternal \
+    ORDER BY c.relname, t.tgname",
+};
+
+pub(super) fn introspect(session: &mut dyn Session) -> Result<DatabaseSchema> {
+  read_catalog(session, &CATALOG)
+}
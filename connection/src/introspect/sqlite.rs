@@ -0,0 +1,456 @@
+use super::{
+  CheckConstraint, Column, DatabaseSchema, ForeignKey, Index, PrimaryKey, Table, Trigger,
+  UniqueConstraint, View,
+};
+use crate::{Result, Session};
+
+/// Read the tables from `sqlite_master` and their details from the `PRAGMA`
+/// statements. Check constraints are only kept in the `CREATE TABLE`
+/// statement, they are read back from it.
+pub(super) fn introspect(session: &mut dyn Session) -> Result<DatabaseSchema> {
+  let mut tables = Vec::new();
+  let rows = session.query(
+    "SELECT name, sql FROM sqlite_master \
+     WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+  )?;
+  for row in rows.iter() {
+    let name = row.get_string("name").unwrap_or_default();
+    let sql = row.get_string("sql").unwrap_or_default();
+    tables.push(table(session, name, &sql)?);
+  }
+
+  // a foreign key without columns references the primary key
+  let primary_keys: Vec<(String, Vec<String>)> = tables
+    .iter()
+    .filter_map(|t| Some((t.name.clone(), t.primary_key.as_ref()?.columns.clone())))
+    .collect();
+  for key in tables.iter_mut().flat_map(|t| t.foreign_keys.iter_mut()) {
+    if key.referenced_columns.is_empty() {
+      if let Some((_, columns)) = primary_keys
+        .iter()
+        .find(|(t, _)| *t == key.referenced_table)
+      {
+        key.referenced_columns = columns.clone();
+      }
+    }
+  }
+
+  let views = session
+    .query("SELECT name, sql FROM sqlite_master WHERE type = 'view' ORDER BY name")?
+    .iter()
+    .map(|row| View {
+      name: row.get_string("name").unwrap_or_default(),
+      definition: row.get_string("sql"),
+    })
+    .collect();
+
+  let triggers = session
+    .query(
+      "SELECT tbl_name, name FROM sqlite_master WHERE type = 'trigger' ORDER BY tbl_name, name",
+    )?
+    .iter()
+    .map(|row| Trigger {
+      name: row.get_string("name").unwrap_or_default(),
+      table: row.get_string("tbl_name").unwrap_or_default(),
+    })
+    .collect();
+
+  Ok(DatabaseSchema {
+    tables,
+    views,
+    triggers,
+    ..DatabaseSchema::default()
+  })
+}
+
+fn table(session: &mut dyn Session, name: String, sql: &str) -> Result<Table> {
+  let quoted = format!("\"{}\"", name.replace('"', "\"\""));
+
+  let info = session.query(&format!("PRAGMA table_info({})", quoted))?;
+  let mut key: Vec<(i64, String)> = info
+    .iter()
+    .filter(|row| row.get_i64("pk").unwrap_or(0) > 0)
+    .map(|row| {
+      (
+        row.get_i64("pk").unwrap_or(0),
+        row.get_string("name").unwrap_or_default(),
+      )
+    })
+    .collect();
+  key.sort();
+  let key: Vec<String> = key.into_iter().map(|(_, column)| column).collect();
+
+  let columns = info
+    .iter()
+    .map(|row| {
+      let name = row.get_string("name").unwrap_or_default();
+      let data_type = row.get_string("type").unwrap_or_default();
+      // an `INTEGER PRIMARY KEY` is an alias of the rowid
+      let auto_increment = key == [name.clone()] && data_type.eq_ignore_ascii_case("INTEGER");
+      Column {
+        nullable: row.get_i64("notnull") == Some(0) && !key.contains(&name),
+        default: row.get_string("dflt_value"),
+        name,
+        data_type,
+        auto_increment,
+      }
+    })
+    .collect();
+
+  let mut rows = session.query(&format!("PRAGMA foreign_key_list({})", quoted))?;
+  rows.sort_by_key(|row| (row.get_i64("id"), row.get_i64("seq")));
+  let mut foreign_keys: Vec<(Option<i64>, ForeignKey)> = Vec::new();
+  for row in rows.iter() {
+    let id = row.get_i64("id");
+    if foreign_keys.last().map(|(last, _)| *last) != Some(id) {
+      foreign_keys.push((
+        id,
+        ForeignKey {
+          name: None,
+          columns: Vec::new(),
+          referenced_table: row.get_string("table").unwrap_or_default(),
+          referenced_columns: Vec::new(),
+          on_delete: row.get_string("on_delete").unwrap_or_default(),
+          on_update: row.get_string("on_update").unwrap_or_default(),
+        },
+      ));
+    }
+    let (_, key) = foreign_keys.last_mut().unwrap();
+    key.columns.extend(row.get_string("from"));
+    key.referenced_columns.extend(row.get_string("to"));
+  }
+
+  let mut indexes = Vec::new();
+  let mut unique_constraints = Vec::new();
+  for row in session
+    .query(&format!("PRAGMA index_list({})", quoted))?
+    .iter()
+  {
+    let index = row.get_string("name").unwrap_or_default();
+    let mut info = session.query(&format!(
+      "PRAGMA index_info(\"{}\")",
+      index.replace('"', "\"\"")
+    ))?;
+    info.sort_by_key(|row| row.get_i64("seqno"));
+    // expressions have no column name
+    let columns = info
+      .iter()
+      .filter_map(|row| row.get_string("name"))
+      .collect::<Vec<_>>();
+    let expressions = columns.len() < info.len();
+    match row.get_string("origin").as_deref() {
+      Some("pk") => {}
+      Some("u") => unique_constraints.push(UniqueConstraint {
+        name: None,
+        columns,
+      }),
+      _ => {
+        let predicate = if row.get_bool("partial") == Some(true) {
+          let sql = session.query(&format!(
+            "SELECT sql FROM sqlite_master WHERE type = 'index' AND name = '{}'",
+            index.replace('\'', "''")
+          ))?;
+          sql
+            .first()
+            .and_then(|row| predicate(&row.get_string("sql")?))
+        } else {
+          None
+        };
+        indexes.push(Index {
+          name: index,
+          columns,
+          expressions,
+          unique: row.get_bool("unique").unwrap_or(false),
+          predicate,
+        })
+      }
+    }
+  }
+  indexes.sort_by(|a: &Index, b| a.name.cmp(&b.name));
+
+  Ok(Table {
+    name,
+    columns,
+    primary_key: if key.is_empty() {
+      None
+    } else {
+      Some(PrimaryKey {
+        name: None,
+        columns: key,
+      })
+    },
+    foreign_keys: foreign_keys.into_iter().map(|(_, key)| key).collect(),
+    indexes,
+    unique_constraints,
+    checks: checks(sql),
+  })
+}
+
+/// A word, quoted name, string or punctuation character of a statement
+struct Token<'a> {
+  text: &'a str,
+  start: usize,
+  end: usize,
+}
+
+fn tokens(sql: &str) -> Vec<Token<'_>> {
+  let bytes = sql.as_bytes();
+  let mut tokens = Vec::new();
+  let mut i = 0;
+  while i < bytes.len() {
+    let start = i;
+    match bytes[i] {
+      b if b.is_ascii_whitespace() => {
+        i += 1;
+        continue;
+      }
+      b'-' if bytes.get(i + 1) == Some(&b'-') => {
+        while i < bytes.len() && bytes[i] != b'\n' {
+          i += 1;
+        }
+        continue;
+      }
+      b'/' if bytes.get(i + 1) == Some(&b'*') => {
+        i = sql[i + 2..]
+          .find("*/")
+          .map_or(bytes.len(), |end| i + 2 + end + 2);
+        continue;
+      }
+      quote @ (b'\'' | b'"' | b'`' | b'[') => {
+        let close = if quote == b'[' { b']' } else { quote };
+        i += 1;
+        loop {
+          match bytes.get(i) {
+            None => break,
+            // a doubled quote is part of the text
+            Some(&b) if b == close && bytes.get(i + 1) == Some(&close) && close != b']' => i += 2,
+            Some(&b) if b == close => {
+              i += 1;
+              break;
+            }
+            Some(_) => i += 1,
+          }
+        }
+      }
+      b if b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80 => {
+        while i < bytes.len()
+          && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] >= 0x80)
+        {
+          i += 1;
+        }
+      }
+      _ => i += 1,
+    }
+    tokens.push(Token {
+      text: &sql[start..i],
+      start,
+      end: i,
+    });
+  }
+  tokens
+}
+
+/// The `CHECK (...)` constraints of a `CREATE TABLE` statement, with the name
+/// given by a preceding `CONSTRAINT name`
+fn checks(sql: &str) -> Vec<CheckConstraint> {
+  let tokens = tokens(sql);
+  let mut checks = Vec::new();
+  for (i, token) in tokens.iter().enumerate() {
+    let open = match tokens.get(i + 1) {
+      Some(open) if token.text.eq_ignore_ascii_case("CHECK") && open.text == "(" => open,
+      _ => continue,
+    };
+    let mut depth = 0;
+    let close = tokens[i + 1..].iter().find(|t| {
+      match t.text {
+        "(" => depth += 1,
+        ")" => depth -= 1,
+        _ => {}
+      }
+      depth == 0
+    });
+    let close = match close {
+      Some(close) => close,
+      None => continue,
+    };
+    let name = match i.checked_sub(2).map(|at| &tokens[at]) {
+      Some(keyword) if keyword.text.eq_ignore_ascii_case("CONSTRAINT") => {
+        Some(unquote(tokens[i - 1].text))
+      }
+      _ => None,
+    };
+    checks.push(CheckConstraint {
+      name,
+      expression: sql[open.end..close.start].trim().to_string(),
+    });
+  }
+  checks
+}
+
+/// The condition following the `WHERE` of a `CREATE INDEX` statement
+fn predicate(sql: &str) -> Option<String> {
+  let mut depth = 0;
+  let condition = tokens(sql).into_iter().find(|token| {
+    match token.text {
+      "(" => depth += 1,
+      ")" => depth -= 1,
+      _ => {}
+    }
+    depth == 0 && token.text.eq_ignore_ascii_case("WHERE")
+  })?;
+  Some(
+    sql[condition.end..]
+      .trim()
+      .trim_end_matches(';')
+      .trim()
+      .to_string(),
+  )
+}
+
+fn unquote(name: &str) -> String {
+  let quoted = |open: char, close: char| name.starts_with(open) && name.ends_with(close);
+  if name.len() < 2 {
+    name.to_string()
+  } else if quoted('[', ']') {
+    name[1..name.len() - 1].to_string()
+  } else if quoted('"', '"') || quoted('`', '`') || quoted('\'', '\'') {
+    let q = &name[..1];
+    name[1..name.len() - 1].replace(&q.repeat(2), q)
+  } else {
+    name.to_string()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  extern crate tempfile;
+
+  use super::*;
+  use crate::{Connection, ConnectionOption, Datasource, Provider};
+
+  #[test]
+  fn test_introspect() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut connection = Connection::new(&ConnectionOption {
+      datasource: Datasource {
+        provider: Provider::SQLite,
+        url: format!("sqlite://{}", dir.path().join("test.db").display()),
+      },
+      ..ConnectionOption::default()
+    });
+    connection.connect().unwrap();
+    connection
+      .session()
+      .unwrap()
+      .batch_execute(
+        "CREATE TABLE users ( \
+           id INTEGER PRIMARY KEY AUTOINCREMENT, \
+           email TEXT NOT NULL UNIQUE, \
+           age INTEGER DEFAULT 18 CHECK (age >= 0) \
+         ); \
+         CREATE TABLE posts ( \
+           id INTEGER NOT NULL, \
+           author_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE, \
+           title TEXT, \
+           PRIMARY KEY (id, author_id), \
+           CONSTRAINT \"title length\" CHECK (length(title) > 0) \
+         ); \
+         CREATE INDEX posts_title ON posts(title, author_id); \
+         CREATE INDEX posts_lower_title ON posts(author_id, lower(title)); \
+         CREATE UNIQUE INDEX published ON posts(title) WHERE (title IS NOT NULL); \
+         CREATE TRIGGER touch AFTER UPDATE ON users BEGIN SELECT 1; END; \
+         CREATE VIEW adults AS SELECT * FROM users WHERE age >= 18;",
+      )
+      .unwrap();
+
+    let schema = connection.introspect().unwrap();
+    assert_eq!(
+      schema
+        .tables
+        .iter()
+        .map(|t| &t.name[..])
+        .collect::<Vec<_>>(),
+      vec!["posts", "users"]
+    );
+
+    let users = schema.table("users").unwrap();
+    let id = users.column("id").unwrap();
+    assert!(id.auto_increment && !id.nullable);
+    assert_eq!(users.column("email").unwrap().data_type, "TEXT");
+    assert!(!users.column("email").unwrap().nullable);
+    assert_eq!(users.column("age").unwrap().default.as_deref(), Some("18"));
+    assert_eq!(users.unique_constraints[0].columns, vec!["email"]);
+    assert_eq!(
+      users.checks,
+      vec![CheckConstraint {
+        name: None,
+        expression: "age >= 0".into()
+      }]
+    );
+
+    let posts = schema.table("posts").unwrap();
+    assert!(!posts.column("id").unwrap().auto_increment);
+    assert_eq!(
+      posts.primary_key.as_ref().unwrap().columns,
+      vec!["id", "author_id"]
+    );
+    assert_eq!(
+      posts.foreign_keys,
+      vec![ForeignKey {
+        name: None,
+        columns: vec!["author_id".into()],
+        referenced_table: "users".into(),
+        referenced_columns: vec!["id".into()],
+        on_delete: "CASCADE".into(),
+        on_update: "NO ACTION".into(),
+      }]
+    );
+    assert_eq!(
+      posts.indexes,
+      vec![
+        Index {
+          name: "posts_lower_title".into(),
+          columns: vec!["author_id".into()],
+          expressions: true,
+          unique: false,
+          predicate: None,
+        },
+        Index {
+          name: "posts_title".into(),
+          columns: vec!["title".into(), "author_id".into()],
+          expressions: false,
+          unique: false,
+          predicate: None,
+        },
+        Index {
+          name: "published".into(),
+          columns: vec!["title".into()],
+          expressions: false,
+          unique: true,
+          predicate: Some("(title IS NOT NULL)".into()),
+        }
+      ]
+    );
+    assert_eq!(posts.checks[0].name.as_deref(), Some("title length"));
+    assert_eq!(posts.checks[0].expression, "length(title) > 0");
+
+    assert_eq!(schema.views[0].name, "adults");
+    assert!(schema.enums.is_empty());
+    assert_eq!(
+      schema.triggers,
+      vec![Trigger {
+        name: "touch".into(),
+        table: "users".into()
+      }]
+    );
+  }
+
+  #[test]
+  fn test_checks_skip_quoted_text() {
+    let checks = checks(
+      "CREATE TABLE t (a TEXT DEFAULT 'check (x)', \"check\" INT CHECK (\"check\" IN (1, 2)))",
+    );
+    assert_eq!(checks.len(), 1);
+    assert_eq!(checks[0].expression, "\"check\" IN (1, 2)");
+  }
+}
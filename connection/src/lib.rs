@@ -5,15 +5,35 @@ mod connection;
 mod datasource;
 mod driver;
 mod errors;
+mod hooks;
+mod introspect;
 mod manager;
+mod metrics;
 mod option;
+mod pool;
+mod session;
+mod shutdown;
+mod url;
 
-use self::driver::{create_driver, Driver};
-use crate::errors::Error;
+use self::driver::create_driver;
+pub use crate::errors::Error;
 use std::result;
 
 pub use self::connection::Connection;
 pub use self::datasource::{Datasource, Provider};
+pub use self::driver::{Driver, DriverOptions};
+pub use self::hooks::{Hook, Hooks};
+pub use self::introspect::{
+  introspect, CheckConstraint, Column, DatabaseSchema, EnumType, ForeignKey, Index, PrimaryKey,
+  Sequence, Table, Trigger, UniqueConstraint, View,
+};
 pub use self::manager::ConnectionManager;
+#[cfg(feature = "prometheus")]
+pub use self::metrics::render_prometheus;
+pub use self::metrics::{HistogramSnapshot, Metrics, MetricsSnapshot, PoolState, BUCKETS};
 pub use self::option::{ConnectionOption, ConnectionOptionManager};
+pub use self::pool::PooledSession;
+pub use self::session::{Row, Session, Value};
+pub use self::shutdown::{Canceller, InFlight, ShutdownReport};
+pub use self::url::{ConnectionUrl, Host, TargetSessionAttrs};
 pub type Result<T> = result::Result<T, Error>;
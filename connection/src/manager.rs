@@ -1,19 +1,12 @@
-use crate::{Connection, ConnectionOption, Result};
+use crate::{Connection, ConnectionOption, Error, MetricsSnapshot, Result, ShutdownReport};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ConnectionManager {
   connections: HashMap<String, Connection>,
 }
 
-impl Default for ConnectionManager {
-  fn default() -> Self {
-    ConnectionManager {
-      connections: HashMap::new(),
-    }
-  }
-}
-
 impl ConnectionManager {
   pub fn new() -> Self {
     Self::default()
@@ -24,11 +17,29 @@ impl ConnectionManager {
   }
 
   pub fn has(&self, name: &str) -> bool {
-    self.connections.contains_key(&name.to_string())
+    self.connections.contains_key(name)
   }
 
   pub fn get(&self, name: &str) -> Option<&'_ Connection> {
-    self.connections.get(&name.to_string())
+    self.connections.get(name)
+  }
+
+  pub fn get_mut(&mut self, name: &str) -> Option<&'_ mut Connection> {
+    self.connections.get_mut(name)
+  }
+
+  // Take a copy of the metrics of every connection, ordered by name
+  pub fn metrics(&self) -> Vec<MetricsSnapshot> {
+    let mut snapshots: Vec<MetricsSnapshot> =
+      self.connections.values().map(Connection::metrics).collect();
+    snapshots.sort_by(|a, b| a.connection.cmp(&b.connection));
+    snapshots
+  }
+
+  // Render the metrics of every connection in the Prometheus text format
+  #[cfg(feature = "prometheus")]
+  pub fn render_prometheus(&self) -> String {
+    crate::metrics::render_prometheus(&self.metrics())
   }
 
   // Check the size of connections initialized
@@ -39,12 +50,11 @@ impl ConnectionManager {
   pub fn create(&mut self, option: &ConnectionOption) -> Result<()> {
     let name = option.clone().name.unwrap_or_else(|| "default".to_string());
     // check if the connection is already registered
-    let existing = self.has(&name);
-
-    if existing {
-      // TODO: should log into the console only
-      panic!("Connection exist already")
+    if self.has(&name) {
+      return Err(Error::DuplicateConnection(name));
     }
+    let datasource = &option.datasource;
+    datasource.provider.verify_url(&datasource.url)?;
 
     let connection = Connection::new(option);
     self.connections.insert(name, connection);
@@ -56,7 +66,7 @@ impl ConnectionManager {
   pub fn remove(&mut self, name: &str) -> Result<Connection> {
     match self.connections.remove(name) {
       Some(connection) => Ok(connection),
-      None => panic!("Error connection does not exist"),
+      None => Err(Error::UnknownConnection(name.to_string())),
     }
   }
 
@@ -69,5 +79,91 @@ impl ConnectionManager {
     Ok(())
   }
 
+  // stop every connection handing out sessions, then close them once their
+  // running statements finish or the timeout passes
+  pub fn shutdown(&mut self, timeout: Duration) -> ShutdownReport {
+    let deadline = Instant::now() + timeout;
+    for connection in self.connections.values() {
+      connection.drain();
+    }
+
+    let mut names: Vec<String> = self.connections.keys().cloned().collect();
+    names.sort();
+
+    let mut report = ShutdownReport::default();
+    for name in names {
+      let connection = self.connections.get_mut(&name).unwrap();
+      match connection.shutdown(deadline) {
+        Ok(true) => report.closed.push(name),
+        Ok(false) => report.timed_out.push(name),
+        Err(error) => report.failed.push((name, error)),
+      }
+    }
+    report
+  }
+
   // add fn
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{Datasource, Provider};
+
+  fn option(url: &str) -> ConnectionOption {
+    ConnectionOption {
+      datasource: Datasource {
+        provider: Provider::SQLite,
+        url: url.to_string(),
+      },
+      ..ConnectionOption::default()
+    }
+  }
+
+  #[test]
+  fn test_create_duplicate_connection() {
+    let mut manager = ConnectionManager::new();
+    manager.create(&option("sqlite://test.db")).unwrap();
+    match manager.create(&option("sqlite://other.db")) {
+      Err(Error::DuplicateConnection(name)) => assert_eq!(name, "default"),
+      other => panic!("unexpected result {:?}", other),
+    }
+    assert_eq!(manager.size(), 1);
+  }
+
+  #[test]
+  fn test_create_with_invalid_url() {
+    let mut manager = ConnectionManager::new();
+    match manager.create(&option("postgres://localhost/app")) {
+      Err(Error::InvalidConnectionUrl { url, .. }) => assert_eq!(url, "postgres://localhost/app"),
+      other => panic!("unexpected result {:?}", other),
+    }
+    assert!(!manager.has("default"));
+  }
+
+  #[test]
+  fn test_connect_twice() {
+    let mut connection = Connection::new(&option("sqlite://:memory:"));
+    assert!(matches!(
+      connection.disconnect(),
+      Err(Error::BadConnection(_))
+    ));
+    connection.connect().unwrap();
+    match connection.connect() {
+      Err(Error::BadConnection(message)) => {
+        assert_eq!(message, "The connection `default` is already connected")
+      }
+      other => panic!("unexpected result {:?}", other),
+    }
+    connection.disconnect().unwrap();
+  }
+
+  #[test]
+  fn test_remove_unknown_connection() {
+    let mut manager = ConnectionManager::new();
+    assert!(matches!(
+      manager.remove("missing"),
+      Err(Error::UnknownConnection(_))
+    ));
+  }
+}
@@ -0,0 +1,386 @@
+use crate::Error;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds in seconds of the latency histogram buckets
+pub const BUCKETS: [f64; 12] = [
+  0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// A latency histogram with fixed buckets, safe to update from many sessions
+#[derive(Debug, Default)]
+pub(crate) struct Histogram {
+  buckets: [AtomicU64; BUCKETS.len()],
+  count: AtomicU64,
+  sum_micros: AtomicU64,
+}
+
+impl Histogram {
+  pub(crate) fn observe(&self, duration: Duration) {
+    let seconds = duration.as_secs_f64();
+    for (bound, bucket) in BUCKETS.iter().zip(self.buckets.iter()) {
+      if seconds <= *bound {
+        bucket.fetch_add(1, Ordering::Relaxed);
+      }
+    }
+    self.count.fetch_add(1, Ordering::Relaxed);
+    self
+      .sum_micros
+      .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+  }
+
+  pub(crate) fn snapshot(&self) -> HistogramSnapshot {
+    HistogramSnapshot {
+      buckets: BUCKETS
+        .iter()
+        .zip(self.buckets.iter())
+        .map(|(bound, bucket)| (*bound, bucket.load(Ordering::Relaxed)))
+        .collect(),
+      count: self.count.load(Ordering::Relaxed),
+      sum: Duration::from_micros(self.sum_micros.load(Ordering::Relaxed)),
+    }
+  }
+}
+
+/// The counters and histograms kept for one connection
+#[derive(Debug, Default)]
+pub struct Metrics {
+  acquire_wait: Histogram,
+  query_latency: Histogram,
+  queries: AtomicU64,
+  errors: Mutex<BTreeMap<&'static str, u64>>,
+  reconnects: AtomicU64,
+  pool_filled: AtomicBool,
+}
+
+impl Metrics {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub(crate) fn record_acquire(&self, wait: Duration) {
+    self.acquire_wait.observe(wait);
+  }
+
+  pub(crate) fn record_query(&self, latency: Duration) {
+    self.queries.fetch_add(1, Ordering::Relaxed);
+    self.query_latency.observe(latency);
+  }
+
+  pub(crate) fn record_error(&self, error: &Error) {
+    let mut errors = self.errors.lock().unwrap_or_else(|e| e.into_inner());
+    *errors.entry(error.kind()).or_insert(0) += 1;
+  }
+
+  /// Count a session opened by the pool, sessions opened while filling a new pool are not reconnects
+  pub(crate) fn record_session_opened(&self) {
+    if self.pool_filled.load(Ordering::Relaxed) {
+      self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  pub(crate) fn set_pool_filled(&self, filled: bool) {
+    self.pool_filled.store(filled, Ordering::Relaxed);
+  }
+
+  pub(crate) fn snapshot(&self, connection: &str, pool: PoolState) -> MetricsSnapshot {
+    MetricsSnapshot {
+      connection: connection.to_string(),
+      active_sessions: pool.connections.saturating_sub(pool.idle),
+      idle_sessions: pool.idle,
+      max_sessions: pool.max_size,
+      acquire_wait: self.acquire_wait.snapshot(),
+      queries: self.queries.load(Ordering::Relaxed),
+      query_latency: self.query_latency.snapshot(),
+      errors: self
+        .errors
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(kind, count)| (kind.to_string(), *count))
+        .collect(),
+      reconnects: self.reconnects.load(Ordering::Relaxed),
+    }
+  }
+}
+
+/// The size of a session pool at a point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PoolState {
+  /// Sessions currently open, idle or checked out
+  pub connections: u32,
+  /// Sessions open and waiting in the pool
+  pub idle: u32,
+  pub max_size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramSnapshot {
+  /// Cumulative count of observations for each bucket upper bound in seconds
+  pub buckets: Vec<(f64, u64)>,
+  pub count: u64,
+  pub sum: Duration,
+}
+
+impl HistogramSnapshot {
+  pub fn mean(&self) -> Option<Duration> {
+    if self.count == 0 {
+      None
+    } else {
+      Some(Duration::from_secs_f64(
+        self.sum.as_secs_f64() / self.count as f64,
+      ))
+    }
+  }
+}
+
+/// A copy of the metrics of a connection, taken with `ConnectionManager::metrics`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+  pub connection: String,
+  pub active_sessions: u32,
+  pub idle_sessions: u32,
+  pub max_sessions: u32,
+  /// Time spent waiting for a session to be checked out of the pool
+  pub acquire_wait: HistogramSnapshot,
+  pub queries: u64,
+  pub query_latency: HistogramSnapshot,
+  /// Number of errors by `Error::kind`
+  pub errors: BTreeMap<String, u64>,
+  /// Sessions the pool opened to replace closed or broken ones
+  pub reconnects: u64,
+}
+
+/// Render snapshots in the Prometheus text exposition format
+#[cfg(feature = "prometheus")]
+pub fn render_prometheus(snapshots: &[MetricsSnapshot]) -> String {
+  use std::fmt::Write;
+
+  fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+  }
+
+  fn label(value: &str) -> String {
+    value
+      .replace('\\', "\\\\")
+      .replace('"', "\\\"")
+      .replace('\n', "\\n")
+  }
+
+  fn histogram(out: &mut String, name: &str, connection: &str, histogram: &HistogramSnapshot) {
+    for (bound, count) in histogram.buckets.iter() {
+      let _ = writeln!(
+        out,
+        "{}_bucket{{connection=\"{}\",le=\"{}\"}} {}",
+        name, connection, bound, count
+      );
+    }
+    let _ = writeln!(
+      out,
+      "{}_bucket{{connection=\"{}\",le=\"+Inf\"}} {}",
+      name, connection, histogram.count
+    );
+    let _ = writeln!(
+      out,
+      "{}_sum{{connection=\"{}\"}} {}",
+      name,
+      connection,
+      histogram.sum.as_secs_f64()
+    );
+    let _ = writeln!(
+      out,
+      "{}_count{{connection=\"{}\"}} {}",
+      name, connection, histogram.count
+    );
+  }
+
+  type Gauge = (&'static str, &'static str, fn(&MetricsSnapshot) -> u64);
+
+  let mut out = String::new();
+  let gauges: [Gauge; 3] = [
+    (
+      "spectre_pool_active_sessions",
+      "Sessions currently checked out of the pool.",
+      |s| u64::from(s.active_sessions),
+    ),
+    (
+      "spectre_pool_idle_sessions",
+      "Sessions open and waiting in the pool.",
+      |s| u64::from(s.idle_sessions),
+    ),
+    (
+      "spectre_pool_max_sessions",
+      "Maximum number of sessions the pool may open.",
+      |s| u64::from(s.max_sessions),
+    ),
+  ];
+  for (name, help, value) in gauges.iter() {
+    header(&mut out, name, "gauge", help);
+    for snapshot in snapshots.iter() {
+      let _ = writeln!(
+        out,
+        "{}{{connection=\"{}\"}} {}",
+        name,
+        label(&snapshot.connection),
+        value(snapshot)
+      );
+    }
+  }
+
+  header(
+    &mut out,
+    "spectre_pool_acquire_wait_seconds",
+    "histogram",
+    "Time spent waiting for a session from the pool.",
+  );
+  for snapshot in snapshots.iter() {
+    histogram(
+      &mut out,
+      "spectre_pool_acquire_wait_seconds",
+      &label(&snapshot.connection),
+      &snapshot.acquire_wait,
+    );
+  }
+
+  header(
+    &mut out,
+    "spectre_queries_total",
+    "counter",
+    "Statements executed through pooled sessions.",
+  );
+  for snapshot in snapshots.iter() {
+    let _ = writeln!(
+      out,
+      "spectre_queries_total{{connection=\"{}\"}} {}",
+      label(&snapshot.connection),
+      snapshot.queries
+    );
+  }
+
+  header(
+    &mut out,
+    "spectre_query_duration_seconds",
+    "histogram",
+    "Time spent executing statements.",
+  );
+  for snapshot in snapshots.iter() {
+    histogram(
+      &mut out,
+      "spectre_query_duration_seconds",
+      &label(&snapshot.connection),
+      &snapshot.query_latency,
+    );
+  }
+
+  header(
+    &mut out,
+    "spectre_errors_total",
+    "counter",
+    "Errors raised by the connection, by kind.",
+  );
+  for snapshot in snapshots.iter() {
+    for (kind, count) in snapshot.errors.iter() {
+      let _ = writeln!(
+        out,
+        "spectre_errors_total{{connection=\"{}\",kind=\"{}\"}} {}",
+        label(&snapshot.connection),
+        label(kind),
+        count
+      );
+    }
+  }
+
+  header(
+    &mut out,
+    "spectre_reconnects_total",
+    "counter",
+    "Sessions opened to replace closed or broken ones.",
+  );
+  for snapshot in snapshots.iter() {
+    let _ = writeln!(
+      out,
+      "spectre_reconnects_total{{connection=\"{}\"}} {}",
+      label(&snapshot.connection),
+      snapshot.reconnects
+    );
+  }
+
+  out
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_histogram_buckets_are_cumulative() {
+    let histogram = Histogram::default();
+    histogram.observe(Duration::from_micros(500));
+    histogram.observe(Duration::from_millis(20));
+    histogram.observe(Duration::from_secs(30));
+    let snapshot = histogram.snapshot();
+
+    assert_eq!(snapshot.count, 3);
+    assert_eq!(snapshot.buckets[0], (0.001, 1));
+    assert_eq!(snapshot.buckets[3], (0.025, 2));
+    assert_eq!(snapshot.buckets[11], (10.0, 2));
+    assert_eq!(
+      snapshot.sum,
+      Duration::from_micros(500) + Duration::from_millis(20) + Duration::from_secs(30)
+    );
+  }
+
+  #[test]
+  fn test_errors_by_kind() {
+    let metrics = Metrics::new();
+    metrics.record_error(&Error::BadConnection("down".into()));
+    metrics.record_error(&Error::BadConnection("down".into()));
+    metrics.record_error(&Error::invalid_url("localhost", "bad"));
+    let snapshot = metrics.snapshot("default", PoolState::default());
+
+    assert_eq!(snapshot.errors.get("bad_connection"), Some(&2));
+    assert_eq!(snapshot.errors.get("invalid_connection_url"), Some(&1));
+  }
+
+  #[test]
+  fn test_reconnects_only_after_pool_is_filled() {
+    let metrics = Metrics::new();
+    metrics.record_session_opened();
+    metrics.set_pool_filled(true);
+    metrics.record_session_opened();
+    assert_eq!(
+      metrics.snapshot("default", PoolState::default()).reconnects,
+      1
+    );
+  }
+
+  #[cfg(feature = "prometheus")]
+  #[test]
+  fn test_render_prometheus() {
+    let metrics = Metrics::new();
+    metrics.record_query(Duration::from_millis(2));
+    metrics.record_error(&Error::BadConnection("down".into()));
+    let snapshot = metrics.snapshot(
+      "de\"fault",
+      PoolState {
+        connections: 3,
+        idle: 1,
+        max_size: 10,
+      },
+    );
+    let text = render_prometheus(&[snapshot]);
+
+    assert!(text.contains("# TYPE spectre_queries_total counter"));
+    assert!(text.contains("spectre_pool_active_sessions{connection=\"de\\\"fault\"} 2"));
+    assert!(text.contains(
+      "spectre_query_duration_seconds_bucket{connection=\"de\\\"fault\",le=\"0.005\"} 1"
+    ));
+    assert!(text.contains("spectre_query_duration_seconds_count{connection=\"de\\\"fault\"} 1"));
+    assert!(
+      text.contains("spectre_errors_total{connection=\"de\\\"fault\",kind=\"bad_connection\"} 1")
+    );
+  }
+}
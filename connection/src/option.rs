@@ -1,4 +1,4 @@
-use crate::Datasource;
+use crate::{Datasource, Hooks, Result, Session};
 use std::collections::HashMap;
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -9,8 +9,17 @@ pub struct ConnectionOption {
   #[serde(default)]
   pub datasource: Datasource,
   pub auto_migrate: Option<bool>,
+  // destructive commands such as `migrate fresh` refuse to run unless forced
+  #[serde(default)]
+  pub production: Option<bool>,
   #[serde(default)]
   pub logging: Option<bool>,
+  // SQL statements executed on every new pooled session
+  #[serde(default)]
+  pub on_connect: Option<Vec<String>>,
+  // callbacks registered programmatically, run around the pooled sessions
+  #[serde(skip)]
+  pub hooks: Hooks,
   // entities/models, migrations, subscribers
 }
 
@@ -18,9 +27,12 @@ impl Default for ConnectionOption {
   fn default() -> Self {
     ConnectionOption {
       name: Some(String::from("default")),
-      logging: Some(false),
+      logging: None,
       auto_migrate: Some(false),
+      production: None,
       datasource: Datasource::default(),
+      on_connect: None,
+      hooks: Hooks::default(),
     }
   }
 }
@@ -41,6 +53,46 @@ impl ConnectionOption {
   pub fn normalize() {
     unimplemented!()
   }
+
+  /// Add a SQL statement to run on every new pooled session
+  #[inline(always)]
+  pub fn on_connect(mut self, statement: &str) -> Self {
+    self
+      .on_connect
+      .get_or_insert_with(Vec::new)
+      .push(statement.into());
+    self
+  }
+
+  /// Register a callback to run on every new pooled session, after the `on_connect` statements
+  #[inline(always)]
+  pub fn after_connect<F>(mut self, hook: F) -> Self
+  where
+    F: Fn(&mut dyn Session) -> Result<()> + Send + Sync + 'static,
+  {
+    self.hooks.after_connect(hook);
+    self
+  }
+
+  /// Register a callback to run every time a session is checked out of the pool
+  #[inline(always)]
+  pub fn after_acquire<F>(mut self, hook: F) -> Self
+  where
+    F: Fn(&mut dyn Session) -> Result<()> + Send + Sync + 'static,
+  {
+    self.hooks.after_acquire(hook);
+    self
+  }
+
+  /// Register a callback to run every time a session is given back to the pool
+  #[inline(always)]
+  pub fn before_release<F>(mut self, hook: F) -> Self
+  where
+    F: Fn(&mut dyn Session) -> Result<()> + Send + Sync + 'static,
+  {
+    self.hooks.before_release(hook);
+    self
+  }
 }
 
 #[derive(Debug)]
@@ -63,12 +115,12 @@ impl ConnectionOptionManager {
   }
   // Check if there is a connection
   pub fn has(&self, name: &str) -> bool {
-    self.options.contains_key(&name.to_string())
+    self.options.contains_key(name)
   }
 
   // Get a connection option by name
   pub fn get(&self, name: &str) -> Option<&'_ ConnectionOption> {
-    self.options.get(&name.to_string())
+    self.options.get(name)
   }
 
   // Check how much connection option there is
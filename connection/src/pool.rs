@@ -0,0 +1,190 @@
+use crate::driver::DriverOptions;
+use crate::metrics::{Metrics, PoolState};
+use crate::{Canceller, Driver, Error, Hooks, InFlight, Result, Row, Session};
+use r2d2::{CustomizeConnection, ManageConnection, Pool, PooledConnection};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Calls `Driver::post_connect` on every new session opened by the pool.
+///
+/// The driver held here is a copy taken before the pool was built, so it only
+/// carries the connection settings and hooks.
+#[derive(Debug)]
+pub(crate) struct PostConnect<D> {
+  driver: D,
+  metrics: Arc<Metrics>,
+}
+
+impl<D, C> CustomizeConnection<C, Error> for PostConnect<D>
+where
+  D: Driver + 'static,
+  C: Session,
+{
+  fn on_acquire(&self, conn: &mut C) -> Result<()> {
+    self.metrics.record_session_opened();
+    self
+      .driver
+      .post_connect(conn)
+      .inspect_err(|error| self.metrics.record_error(error))
+  }
+}
+
+/// Build a pool for the manager, running the driver's `post_connect` on each new session
+pub(crate) fn build_pool<M, D>(manager: M, driver: D, metrics: &Arc<Metrics>) -> Result<Pool<M>>
+where
+  M: ManageConnection<Error = Error>,
+  M::Connection: Session,
+  D: Driver + 'static,
+{
+  metrics.set_pool_filled(false);
+  let pool = Pool::builder()
+    .connection_customizer(Box::new(PostConnect {
+      driver,
+      metrics: metrics.clone(),
+    }))
+    .build(manager)
+    .map_err(|error| {
+      let error = Error::from(error);
+      metrics.record_error(&error);
+      error
+    })?;
+  metrics.set_pool_filled(true);
+  Ok(pool)
+}
+
+/// The number of open and idle sessions of a pool, empty when the driver is not connected
+pub(crate) fn pool_state<M: ManageConnection>(pool: &Option<Pool<M>>) -> PoolState {
+  match pool {
+    Some(pool) => {
+      let state = pool.state();
+      PoolState {
+        connections: state.connections,
+        idle: state.idle_connections,
+        max_size: pool.max_size(),
+      }
+    }
+    None => PoolState::default(),
+  }
+}
+
+impl<M> Session for PooledConnection<M>
+where
+  M: ManageConnection,
+  M::Connection: Session,
+{
+  fn batch_execute(&mut self, sql: &str) -> Result<()> {
+    (**self).batch_execute(sql)
+  }
+
+  fn execute(&mut self, sql: &str) -> Result<u64> {
+    (**self).execute(sql)
+  }
+
+  fn query(&mut self, sql: &str) -> Result<Vec<Row>> {
+    (**self).query(sql)
+  }
+
+  fn canceller(&self) -> Option<Canceller> {
+    (**self).canceller()
+  }
+}
+
+/// A session checked out of a connection pool.
+///
+/// The `after_acquire` hooks have already run when this is handed out, the
+/// `before_release` hooks run when it is released or dropped. Every statement
+/// is registered as in flight while it runs, so a shutdown can wait for it or
+/// cancel it.
+pub struct PooledSession {
+  session: Box<dyn Session + Send>,
+  hooks: Arc<Hooks>,
+  metrics: Arc<Metrics>,
+  in_flight: Arc<InFlight>,
+  canceller: Option<Canceller>,
+  released: bool,
+}
+
+impl PooledSession {
+  pub(crate) fn acquire<M>(pool: &Pool<M>, options: &DriverOptions) -> Result<Self>
+  where
+    M: ManageConnection<Error = Error>,
+    M::Connection: Session,
+  {
+    let metrics = &options.metrics;
+    options.in_flight.check_open()?;
+
+    let started = Instant::now();
+    let conn = pool.get().map_err(|error| {
+      let error = Error::from(error);
+      metrics.record_error(&error);
+      error
+    })?;
+    metrics.record_acquire(started.elapsed());
+
+    let mut session = PooledSession {
+      canceller: conn.canceller(),
+      session: Box::new(conn),
+      hooks: options.hooks.clone(),
+      metrics: metrics.clone(),
+      in_flight: options.in_flight.clone(),
+      released: false,
+    };
+
+    if let Err(error) = session.hooks.run_after_acquire(&mut *session.session) {
+      session.metrics.record_error(&error);
+      session.released = true;
+      return Err(error);
+    }
+
+    Ok(session)
+  }
+
+  /// Time a statement and count it, recording the error kind when it fails
+  fn measure<T>(&mut self, run: impl FnOnce(&mut dyn Session) -> Result<T>) -> Result<T> {
+    let id = self.in_flight.start(self.canceller.clone())?;
+    let started = Instant::now();
+    let result = run(&mut *self.session);
+    self.metrics.record_query(started.elapsed());
+    self.in_flight.finish(id);
+    if let Err(ref error) = result {
+      self.metrics.record_error(error);
+    }
+    result
+  }
+
+  /// Give the session back to the pool, reporting errors from the `before_release` hooks
+  pub fn release(mut self) -> Result<()> {
+    self.released = true;
+    self.hooks.run_before_release(&mut *self.session)
+  }
+}
+
+impl Drop for PooledSession {
+  fn drop(&mut self) {
+    if !self.released {
+      let _ = self.hooks.run_before_release(&mut *self.session);
+    }
+  }
+}
+
+impl Session for PooledSession {
+  fn batch_execute(&mut self, sql: &str) -> Result<()> {
+    self.measure(|session| session.batch_execute(sql))
+  }
+
+  fn execute(&mut self, sql: &str) -> Result<u64> {
+    self.measure(|session| session.execute(sql))
+  }
+
+  fn query(&mut self, sql: &str) -> Result<Vec<Row>> {
+    self.measure(|session| session.query(sql))
+  }
+}
+
+impl std::fmt::Debug for PooledSession {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    f.debug_struct("PooledSession")
+      .field("released", &self.released)
+      .finish()
+  }
+}
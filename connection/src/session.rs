@@ -0,0 +1,174 @@
+use crate::{Canceller, Result};
+use std::fmt;
+
+/// A single value read back from the database.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+  Null,
+  Integer(i64),
+  Real(f64),
+  Text(String),
+  Blob(Vec<u8>),
+}
+
+impl Value {
+  pub fn is_null(&self) -> bool {
+    matches!(self, Value::Null)
+  }
+
+  /// Read the value as text, converting numbers and utf-8 blobs
+  pub fn as_string(&self) -> Option<String> {
+    match self {
+      Value::Null => None,
+      Value::Integer(i) => Some(i.to_string()),
+      Value::Real(f) => Some(f.to_string()),
+      Value::Text(s) => Some(s.clone()),
+      Value::Blob(b) => String::from_utf8(b.clone()).ok(),
+    }
+  }
+
+  /// Read the value as an integer, parsing text returned by text protocols
+  pub fn as_i64(&self) -> Option<i64> {
+    match self {
+      Value::Integer(i) => Some(*i),
+      Value::Real(f) => Some(*f as i64),
+      Value::Null => None,
+      _ => self.as_string().and_then(|s| s.trim().parse().ok()),
+    }
+  }
+
+  /// Read the value as a boolean, accepting the textual forms used by each provider
+  pub fn as_bool(&self) -> Option<bool> {
+    match self {
+      Value::Integer(i) => Some(*i != 0),
+      Value::Null => None,
+      _ => match self.as_string()?.to_lowercase().as_str() {
+        "t" | "true" | "1" | "yes" | "y" | "on" => Some(true),
+        "f" | "false" | "0" | "no" | "n" | "off" => Some(false),
+        _ => None,
+      },
+    }
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Value::Null => f.write_str("NULL"),
+      Value::Blob(b) => write!(f, "<{} bytes>", b.len()),
+      _ => f.write_str(&self.as_string().unwrap_or_default()),
+    }
+  }
+}
+
+/// A row returned from a query, keeping the column names in order
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Row {
+  columns: Vec<String>,
+  values: Vec<Value>,
+}
+
+impl Row {
+  pub fn new(columns: Vec<String>, values: Vec<Value>) -> Self {
+    Row { columns, values }
+  }
+
+  pub fn columns(&self) -> &[String] {
+    &self.columns
+  }
+
+  pub fn values(&self) -> &[Value] {
+    &self.values
+  }
+
+  pub fn len(&self) -> usize {
+    self.values.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.values.is_empty()
+  }
+
+  // Get a value by its column name, column names are compared case insensitively
+  pub fn get(&self, column: &str) -> Option<&Value> {
+    self
+      .columns
+      .iter()
+      .position(|c| c.eq_ignore_ascii_case(column))
+      .and_then(|i| self.values.get(i))
+  }
+
+  pub fn get_string(&self, column: &str) -> Option<String> {
+    self.get(column).and_then(Value::as_string)
+  }
+
+  pub fn get_i64(&self, column: &str) -> Option<i64> {
+    self.get(column).and_then(Value::as_i64)
+  }
+
+  pub fn get_bool(&self, column: &str) -> Option<bool> {
+    self.get(column).and_then(Value::as_bool)
+  }
+}
+
+/// A live session with the database, either owned by a pool or checked out of it.
+///
+/// Statements are sent as plain SQL text so the same script can be printed,
+/// reviewed and executed unchanged.
+pub trait Session {
+  /// Execute one or more statements separated by `;`, discarding any result rows
+  fn batch_execute(&mut self, sql: &str) -> Result<()>;
+
+  /// Execute a single statement and return the number of affected rows
+  fn execute(&mut self, sql: &str) -> Result<u64>;
+
+  /// Run a single query and collect every row it returns
+  fn query(&mut self, sql: &str) -> Result<Vec<Row>>;
+
+  /// A handle that cancels the statement this session is running from another thread
+  fn canceller(&self) -> Option<Canceller> {
+    None
+  }
+}
+
+impl<S: Session + ?Sized> Session for Box<S> {
+  fn batch_execute(&mut self, sql: &str) -> Result<()> {
+    (**self).batch_execute(sql)
+  }
+
+  fn execute(&mut self, sql: &str) -> Result<u64> {
+    (**self).execute(sql)
+  }
+
+  fn query(&mut self, sql: &str) -> Result<Vec<Row>> {
+    (**self).query(sql)
+  }
+
+  fn canceller(&self) -> Option<Canceller> {
+    (**self).canceller()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_row_get_is_case_insensitive() {
+    let row = Row::new(
+      vec!["Name".into(), "count".into()],
+      vec![Value::Text("users".into()), Value::Text("3".into())],
+    );
+    assert_eq!(row.get_string("name"), Some("users".to_string()));
+    assert_eq!(row.get_i64("COUNT"), Some(3));
+    assert!(row.get("missing").is_none());
+  }
+
+  #[test]
+  fn test_value_as_bool() {
+    assert_eq!(Value::Text("t".into()).as_bool(), Some(true));
+    assert_eq!(Value::Text("NO".into()).as_bool(), Some(false));
+    assert_eq!(Value::Integer(0).as_bool(), Some(false));
+    assert_eq!(Value::Null.as_bool(), None);
+  }
+}
@@ -0,0 +1,218 @@
+use crate::{Error, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Instant;
+
+/// Cancels the statement a session is running, from any thread
+pub type Canceller = Arc<dyn Fn() -> Result<()> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Gate {
+  /// Sessions are handed out and run statements
+  #[default]
+  Open,
+  /// No new sessions are handed out, sessions already checked out may finish their work
+  Draining,
+  /// Nothing runs anymore
+  Closed,
+}
+
+#[derive(Default)]
+struct Running {
+  gate: Gate,
+  next_id: u64,
+  queries: HashMap<u64, Option<Canceller>>,
+}
+
+/// The statements running on the sessions of one connection.
+///
+/// Shutting a connection down closes the gate in two steps: first no new
+/// sessions are handed out while running statements get the chance to
+/// finish, then whatever is still running is cancelled.
+#[derive(Default)]
+pub struct InFlight {
+  running: Mutex<Running>,
+  finished: Condvar,
+}
+
+impl InFlight {
+  fn lock(&self) -> MutexGuard<'_, Running> {
+    self.running.lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  /// The number of statements running right now
+  pub fn len(&self) -> usize {
+    self.lock().queries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub(crate) fn open(&self) {
+    self.lock().gate = Gate::Open;
+  }
+
+  pub(crate) fn drain(&self) {
+    let mut running = self.lock();
+    if running.gate == Gate::Open {
+      running.gate = Gate::Draining;
+    }
+  }
+
+  pub(crate) fn close(&self) {
+    self.lock().gate = Gate::Closed;
+  }
+
+  /// Fail when the connection no longer hands out sessions
+  pub(crate) fn check_open(&self) -> Result<()> {
+    match self.lock().gate {
+      Gate::Open => Ok(()),
+      _ => Err(Error::ShuttingDown),
+    }
+  }
+
+  /// Register a statement about to run, fails once the connection is closed
+  pub(crate) fn start(&self, canceller: Option<Canceller>) -> Result<u64> {
+    let mut running = self.lock();
+    if running.gate == Gate::Closed {
+      return Err(Error::ShuttingDown);
+    }
+    let id = running.next_id;
+    running.next_id += 1;
+    running.queries.insert(id, canceller);
+    Ok(id)
+  }
+
+  pub(crate) fn finish(&self, id: u64) {
+    let mut running = self.lock();
+    running.queries.remove(&id);
+    if running.queries.is_empty() {
+      self.finished.notify_all();
+    }
+  }
+
+  /// Block until every running statement has finished, returns false when the deadline passed first
+  pub(crate) fn wait(&self, deadline: Instant) -> bool {
+    let mut running = self.lock();
+    while !running.queries.is_empty() {
+      let now = Instant::now();
+      if now >= deadline {
+        return false;
+      }
+      running = self
+        .finished
+        .wait_timeout(running, deadline - now)
+        .unwrap_or_else(|e| e.into_inner())
+        .0;
+    }
+    true
+  }
+
+  /// Cancel every running statement, returning the errors of the cancellations that failed
+  pub(crate) fn cancel(&self) -> Vec<Error> {
+    // cancelling talks to the server, so it happens outside the lock
+    let cancellers: Vec<Canceller> = self.lock().queries.values().flatten().cloned().collect();
+    cancellers
+      .iter()
+      .filter_map(|cancel| cancel().err())
+      .collect()
+  }
+}
+
+impl fmt::Debug for InFlight {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let running = self.lock();
+    f.debug_struct("InFlight")
+      .field("gate", &running.gate)
+      .field("queries", &running.queries.len())
+      .finish()
+  }
+}
+
+/// The outcome of shutting down the connections of a `ConnectionManager`
+#[derive(Debug, Default)]
+pub struct ShutdownReport {
+  /// Connections closed with no statement left running
+  pub closed: Vec<String>,
+  /// Connections that still ran statements at the deadline, those statements were cancelled
+  pub timed_out: Vec<String>,
+  /// Connections whose driver failed to close
+  pub failed: Vec<(String, Error)>,
+}
+
+impl ShutdownReport {
+  /// Whether every connection closed before the deadline without errors
+  pub fn is_clean(&self) -> bool {
+    self.timed_out.is_empty() && self.failed.is_empty()
+  }
+}
+
+impl fmt::Display for ShutdownReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} connection(s) closed", self.closed.len())?;
+    if !self.timed_out.is_empty() {
+      write!(f, ", statements cancelled on {}", self.timed_out.join(", "))?;
+    }
+    for (name, error) in self.failed.iter() {
+      write!(f, ", `{}` failed to close: {}", name, error)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::time::Duration;
+
+  #[test]
+  fn test_gate() {
+    let in_flight = InFlight::default();
+    assert!(in_flight.check_open().is_ok());
+
+    in_flight.drain();
+    assert!(in_flight.check_open().is_err());
+    let id = in_flight.start(None).unwrap();
+    in_flight.finish(id);
+
+    in_flight.close();
+    assert!(in_flight.start(None).is_err());
+    in_flight.open();
+    assert!(in_flight.check_open().is_ok());
+  }
+
+  #[test]
+  fn test_wait_until_deadline() {
+    let in_flight = InFlight::default();
+    assert!(in_flight.wait(Instant::now()));
+
+    let id = in_flight.start(None).unwrap();
+    assert!(!in_flight.wait(Instant::now() + Duration::from_millis(10)));
+    in_flight.finish(id);
+    assert!(in_flight.wait(Instant::now()));
+  }
+
+  #[test]
+  fn test_cancel_running_statements() {
+    let cancelled = Arc::new(AtomicUsize::new(0));
+    let c = cancelled.clone();
+    let in_flight = InFlight::default();
+    in_flight
+      .start(Some(Arc::new(move || {
+        c.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+      })))
+      .unwrap();
+    in_flight
+      .start(Some(Arc::new(|| Err(Error::BadConnection("gone".into())))))
+      .unwrap();
+    in_flight.start(None).unwrap();
+
+    assert_eq!(in_flight.cancel().len(), 1);
+    assert_eq!(cancelled.load(Ordering::SeqCst), 1);
+    assert_eq!(in_flight.len(), 3);
+  }
+}
@@ -22,11 +22,10 @@ pub fn search_for_directory_containing_file(path: &Path, file: &str) -> Result<P
 }
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
   ProjectRootNotFound(PathBuf),
   IoError(io::Error),
-  #[doc(hidden)]
-  __Nonexhaustive,
 }
 
 impl From<io::Error> for Error {
@@ -45,17 +44,17 @@ impl fmt::Display for Error {
         "Unable to find Cargo.toml in {:?} or any parent directories.",
         p
       ),
-      _ => unreachable!(),
+      IoError(ref error) => error.fmt(f),
     }
   }
 }
 
 impl PartialEq for Error {
   fn eq(&self, other: &Self) -> bool {
-    match (self, other) {
-      (&ProjectRootNotFound(_), &ProjectRootNotFound(_)) => true,
-      _ => false,
-    }
+    matches!(
+      (self, other),
+      (&ProjectRootNotFound(_), &ProjectRootNotFound(_))
+    )
   }
 }
 
@@ -80,7 +79,7 @@ mod tests {
 
     assert_eq!(
       Ok(temp_path.into()),
-      search_for_directory_containing_file(&temp_path, "Cargo.toml")
+      search_for_directory_containing_file(temp_path, "Cargo.toml")
     );
     dir.close().unwrap();
   }
@@ -98,7 +97,7 @@ mod tests {
     fs::create_dir(deeply_nested).unwrap();
     assert_eq!(
       Ok(temp_path.into()),
-      search_for_directory_containing_file(&temp_path, "Cargo.toml")
+      search_for_directory_containing_file(temp_path, "Cargo.toml")
     );
     dir.close().unwrap();
   }
@@ -109,7 +108,7 @@ mod tests {
     let temp_path = dir.path();
     assert_eq!(
       Err(Error::ProjectRootNotFound(temp_path.into())),
-      search_for_directory_containing_file(&temp_path, "Cargo.toml")
+      search_for_directory_containing_file(temp_path, "Cargo.toml")
     );
     dir.close().unwrap();
   }
@@ -0,0 +1,95 @@
+extern crate proc_macro;
+
+use proc_macro::{TokenStream, TokenTree};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Bundle the migration files of a directory into the binary, for
+/// `Spectre::embedded_migrations`.
+///
+/// The directory is relative to the crate being built and defaults to
+/// `migrations`:
+///
+/// ```ignore
+/// static MIGRATIONS: &[spectre::EmbeddedFile] = spectre::embed_migrations!("migrations");
+/// ```
+///
+/// Editing a migration rebuilds the crate. A new migration is only picked up
+/// once the crate is rebuilt, add `println!("cargo:rerun-if-changed=migrations")`
+/// to its build script to do that on every change of the directory.
+#[proc_macro]
+pub fn embed_migrations(input: TokenStream) -> TokenStream {
+  match expand(input) {
+    Ok(tokens) => tokens,
+    Err(message) => format!("compile_error!({:?})", message).parse().unwrap(),
+  }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+  let relative = directory(input)?.unwrap_or_else(|| "migrations".to_string());
+  let root = env::var("CARGO_MANIFEST_DIR").map_err(|e| e.to_string())?;
+  let dir = Path::new(&root).join(&relative);
+  let files =
+    files(&dir).map_err(|e| format!("cannot read the migrations in `{}`: {}", dir.display(), e))?;
+
+  let entries: String = files
+    .iter()
+    .map(|(path, file)| {
+      format!(
+        "::spectre::EmbeddedFile {{ path: {:?}, contents: include_str!({:?}) }},",
+        path,
+        file.display().to_string()
+      )
+    })
+    .collect();
+  format!("&[{}]", entries)
+    .parse()
+    .map_err(|e| format!("{:?}", e))
+}
+
+/// The directory given to the macro, as a string literal
+fn directory(input: TokenStream) -> Result<Option<String>, String> {
+  let tokens: Vec<TokenTree> = input.into_iter().collect();
+  let literal = match tokens.as_slice() {
+    [] => return Ok(None),
+    [TokenTree::Literal(literal)] => literal.to_string(),
+    _ => String::new(),
+  };
+  if literal.len() >= 2 && literal.starts_with('"') && literal.ends_with('"') {
+    Ok(Some(literal[1..literal.len() - 1].to_string()))
+  } else {
+    Err(
+      "expected the migrations directory as a string, e.g. `embed_migrations!(\"migrations\")`"
+        .to_string(),
+    )
+  }
+}
+
+/// The `.sql` files of the directory and the scripts of its migration
+/// directories, by their path relative to it
+fn files(dir: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+  let mut files = Vec::new();
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    let name = path
+      .file_name()
+      .map(|name| name.to_string_lossy().into_owned())
+      .unwrap_or_default();
+    if name.starts_with('.') {
+      continue;
+    }
+    if path.is_file() && name.ends_with(".sql") {
+      files.push((name, path));
+    } else if path.is_dir() {
+      for script in ["up.sql", "down.sql"].iter() {
+        if path.join(script).is_file() {
+          files.push((format!("{}/{}", name, script), path.join(script)));
+        }
+      }
+    }
+  }
+  files.sort();
+  Ok(files)
+}
@@ -0,0 +1,98 @@
+use crate::Dialect;
+use connection::{Result, Row, Session};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// A migration written in Rust, for data changes that need application logic.
+///
+/// It is ordered among the SQL migrations by its name and recorded in the
+/// same history table.
+///
+/// ```ignore
+/// struct BackfillNames;
+///
+/// impl RustMigration for BackfillNames {
+///   fn name(&self) -> &str {
+///     "20261019120000_backfill_names"
+///   }
+///
+///   fn up(&self, tx: &mut Tx) -> Result<(), Box<dyn Error + Send + Sync>> {
+///     for row in tx.query("SELECT id, email FROM users WHERE name IS NULL")? {
+///       // ...
+///     }
+///     Ok(())
+///   }
+///
+///   fn down(&self, _tx: &mut Tx) -> Result<(), Box<dyn Error + Send + Sync>> {
+///     Ok(())
+///   }
+/// }
+/// ```
+pub trait RustMigration: Send + Sync {
+  /// The timestamp followed by a description, e.g. `20261019120000_backfill_names`
+  fn name(&self) -> &str;
+
+  fn up(&self, tx: &mut Tx) -> std::result::Result<(), Box<dyn Error + Send + Sync>>;
+
+  fn down(&self, tx: &mut Tx) -> std::result::Result<(), Box<dyn Error + Send + Sync>>;
+
+  /// Whether `down` reverts the migration. An irreversible one is treated like
+  /// a SQL migration without a down script, a rollback refuses it unless forced.
+  fn reversible(&self) -> bool {
+    true
+  }
+
+  /// Whether to run inside a transaction where the dialect allows, like
+  /// `-- spectre:no-transaction` does for SQL migrations
+  fn transaction(&self) -> bool {
+    true
+  }
+}
+
+/// The session a Rust migration runs its statements with, inside the
+/// transaction of the migration where there is one
+pub struct Tx<'a> {
+  session: &'a mut dyn Session,
+  dialect: Dialect,
+}
+
+impl<'a> Tx<'a> {
+  pub(crate) fn new(session: &'a mut dyn Session, dialect: Dialect) -> Self {
+    Tx { session, dialect }
+  }
+
+  #[inline(always)]
+  pub fn dialect(&self) -> Dialect {
+    self.dialect
+  }
+
+  pub fn batch_execute(&mut self, sql: &str) -> Result<()> {
+    self.session.batch_execute(sql)
+  }
+
+  pub fn execute(&mut self, sql: &str) -> Result<u64> {
+    self.session.execute(sql)
+  }
+
+  pub fn query(&mut self, sql: &str) -> Result<Vec<Row>> {
+    self.session.query(sql)
+  }
+}
+
+/// The Rust half of a [`Migration`](crate::Migration) built with
+/// [`Migration::from_code`](crate::Migration::from_code)
+#[derive(Clone)]
+pub struct Code(pub(crate) Arc<dyn RustMigration>);
+
+impl fmt::Debug for Code {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_tuple("Code").field(&self.0.name()).finish()
+  }
+}
+
+impl PartialEq for Code {
+  fn eq(&self, other: &Self) -> bool {
+    self.0.name() == other.0.name()
+  }
+}
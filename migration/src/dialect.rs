@@ -0,0 +1,73 @@
+use connection::Provider;
+
+/// The SQL flavour spoken by a connection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+  Postgres,
+  MySQL,
+  SQLite,
+}
+
+impl Dialect {
+  /// Whether schema changes can be rolled back along with the rest of a transaction.
+  ///
+  /// MySQL commits implicitly around every DDL statement, so its migrations run
+  /// without a surrounding transaction.
+  pub fn transactional_ddl(self) -> bool {
+    !matches!(self, Dialect::MySQL)
+  }
+
+  /// Quote a value as a string literal
+  pub fn quote(self, value: &str) -> String {
+    let escaped = match self {
+      Dialect::MySQL => value.replace('\\', "\\\\").replace('\'', "''"),
+      _ => value.replace('\'', "''"),
+    };
+    format!("'{}'", escaped)
+  }
+
+  /// Quote a table, column or other identifier
+  pub fn quote_ident(self, name: &str) -> String {
+    match self {
+      Dialect::MySQL => format!("`{}`", name.replace('`', "``")),
+      _ => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+  }
+
+  pub(crate) fn begin(self) -> &'static str {
+    match self {
+      Dialect::MySQL => "START TRANSACTION",
+      _ => "BEGIN",
+    }
+  }
+}
+
+impl From<&Provider> for Dialect {
+  fn from(provider: &Provider) -> Self {
+    match *provider {
+      #[cfg(feature = "postgres")]
+      Provider::Postgres => Dialect::Postgres,
+      #[cfg(feature = "mysql")]
+      Provider::MySQL => Dialect::MySQL,
+      #[cfg(feature = "sqlite")]
+      Provider::SQLite => Dialect::SQLite,
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_quote() {
+    assert_eq!(Dialect::Postgres.quote("it's"), "'it''s'");
+    assert_eq!(Dialect::MySQL.quote("a\\'b"), "'a\\\\''b'");
+  }
+
+  #[test]
+  fn test_quote_ident() {
+    assert_eq!(Dialect::SQLite.quote_ident("user\"s"), "\"user\"\"s\"");
+    assert_eq!(Dialect::MySQL.quote_ident("order"), "`order`");
+  }
+}
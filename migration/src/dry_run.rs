@@ -0,0 +1,101 @@
+use crate::Dialect;
+use connection::{Result, Row, Session};
+
+/// A session recording the statements that would change the database instead
+/// of running them. Queries still reach the wrapped session, so the history
+/// table can be read to decide what to run.
+pub struct DryRun<'a> {
+  session: &'a mut dyn Session,
+  statements: Vec<String>,
+}
+
+impl<'a> DryRun<'a> {
+  pub fn new(session: &'a mut dyn Session) -> Self {
+    DryRun {
+      session,
+      statements: Vec::new(),
+    }
+  }
+
+  #[inline(always)]
+  pub fn statements(&self) -> &[String] {
+    &self.statements
+  }
+
+  pub fn into_statements(self) -> Vec<String> {
+    self.statements
+  }
+}
+
+impl<'a> Session for DryRun<'a> {
+  fn batch_execute(&mut self, sql: &str) -> Result<()> {
+    self.statements.push(sql.to_string());
+    Ok(())
+  }
+
+  fn execute(&mut self, sql: &str) -> Result<u64> {
+    self.statements.push(sql.to_string());
+    Ok(0)
+  }
+
+  fn query(&mut self, sql: &str) -> Result<Vec<Row>> {
+    self.session.query(sql)
+  }
+}
+
+/// Join statements into a script that can be run as is, one statement per line.
+///
+/// MySQL statements holding `;` themselves, such as routine bodies, are wrapped
+/// in `DELIMITER` lines for the `mysql` client. A comment standing for a Rust
+/// migration is kept on a line of its own.
+pub fn script(statements: &[String], dialect: Dialect) -> String {
+  statements
+    .iter()
+    .map(|statement| {
+      let statement = statement.trim_end().trim_end_matches(';');
+      if statement.starts_with("--") && !statement.contains('\n') {
+        format!("{}\n", statement)
+      } else if dialect == Dialect::MySQL && statement.contains(';') {
+        let delimiter = ["$$", "//", ";;"]
+          .iter()
+          .find(|delimiter| !statement.contains(*delimiter))
+          .unwrap_or(&"$$");
+        format!(
+          "DELIMITER {}\n{} {}\nDELIMITER ;\n",
+          delimiter, statement, delimiter
+        )
+      } else {
+        format!("{};\n", statement)
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::Script;
+
+  #[test]
+  fn test_mysql_script_round_trips() {
+    let statements = vec![
+      "CREATE PROCEDURE touch()\nBEGIN\n  UPDATE users SET name = 'a';\nEND".to_string(),
+      "SELECT 1;".to_string(),
+    ];
+    let sql = script(&statements, Dialect::MySQL);
+    assert_eq!(
+      sql,
+      "DELIMITER $$\nCREATE PROCEDURE touch()\nBEGIN\n  UPDATE users SET name = 'a';\nEND $$\nDELIMITER ;\nSELECT 1;\n"
+    );
+    assert_eq!(
+      Script::new(&sql).statements(Dialect::MySQL).unwrap(),
+      vec![statements[0].clone(), "SELECT 1".to_string()]
+    );
+    assert_eq!(script(&statements[1..], Dialect::Postgres), "SELECT 1;\n");
+    let skipped = vec!["-- Rust migration 20261018130000_backfill (not shown)".to_string()];
+    assert_eq!(
+      script(&skipped, Dialect::MySQL),
+      "-- Rust migration 20261018130000_backfill (not shown)\n"
+    );
+  }
+}
@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use std::{error, fmt, io};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+  /// A migration file or directory could not be read.
+  IoError(PathBuf, io::Error),
+  /// A migration on disk does not follow the expected layout.
+  InvalidMigration(PathBuf, String),
+  /// The connection failed while reading or writing the history table.
+  ConnectionError(connection::Error),
+  /// An applied migration has no down script to revert it with.
+  MissingDownScript(String),
+  /// A migration script or its history bookkeeping failed.
+  MigrationFailed {
+    name: String,
+    source: Box<dyn error::Error + Send + Sync>,
+  },
+  /// An applied migration was edited, its checksum no longer matches the history.
+  ChecksumMismatch { name: String, path: PathBuf },
+  /// Another process kept the migration lock for longer than the timeout.
+  /// `table` names the SQLite lock table, whose row outlives a crashed run.
+  LockTimeout {
+    timeout: Duration,
+    table: Option<&'static str>,
+  },
+  /// No migration has the given name.
+  UnknownMigration(String),
+  /// Only some of the migrations squashed into a baseline were applied.
+  PartialBaseline { name: String, missing: Vec<String> },
+  /// A rollback targeted the migrations recorded by a baseline without forcing it.
+  BaselineRollback,
+}
+
+impl From<connection::Error> for Error {
+  fn from(e: connection::Error) -> Self {
+    Error::ConnectionError(e)
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Error::IoError(ref path, ref err) => write!(f, "{}: {}", path.display(), err),
+      Error::InvalidMigration(ref path, ref reason) => {
+        write!(f, "Invalid migration `{}`: {}", path.display(), reason)
+      }
+      Error::ConnectionError(ref err) => err.fmt(f),
+      Error::MissingDownScript(ref name) => {
+        write!(f, "Migration `{}` has no down script to revert it", name)
+      }
+      Error::MigrationFailed {
+        ref name,
+        ref source,
+      } => write!(f, "Migration `{}` failed: {}", name, source),
+      Error::ChecksumMismatch { ref name, ref path } => write!(
+        f,
+        "Migration `{}` was changed after it was applied, `{}` no longer matches its checksum",
+        name,
+        path.display()
+      ),
+      Error::LockTimeout { ref timeout, table } => {
+        write!(
+          f,
+          "Timed out after {:?} waiting for another process to finish migrating",
+          timeout
+        )?;
+        match table {
+          Some(table) => write!(
+            f,
+            ". A run that crashed leaves its row in `{}` behind, when no migration is running clear it with `DELETE FROM {}`",
+            table, table
+          ),
+          None => Ok(()),
+        }
+      }
+      Error::UnknownMigration(ref name) => write!(f, "There is no migration named `{}`", name),
+      Error::PartialBaseline {
+        ref name,
+        ref missing,
+      } => write!(
+        f,
+        "Migration `{}` replaces migrations of which only some were applied, `{}` are missing",
+        name,
+        missing.join("`, `")
+      ),
+      Error::BaselineRollback => write!(
+        f,
+        "Batch {} holds migrations recorded by `migrate baseline`, their down scripts would run against a schema they never created",
+        crate::BASELINE_BATCH
+      ),
+    }
+  }
+}
+
+impl error::Error for Error {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match self {
+      Error::IoError(_, err) => Some(err),
+      Error::ConnectionError(err) => Some(err),
+      Error::MigrationFailed { source, .. } => Some(source.as_ref()),
+      _ => None,
+    }
+  }
+}
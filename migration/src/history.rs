@@ -0,0 +1,114 @@
+use crate::{Dialect, REPEATABLE_PREFIX};
+use connection::{Result, Session};
+use std::time::Duration;
+
+/// The table recording which migrations were applied to a database
+pub const HISTORY_TABLE: &str = "spectre_migrations";
+
+/// A row of the history table
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedMigration {
+  pub name: String,
+  pub checksum: String,
+  /// The run of `up` that applied the migration, counting from 1, or 0 for
+  /// migrations recorded by `baseline`
+  pub batch: i64,
+  pub applied_at: String,
+  pub duration: Duration,
+}
+
+impl AppliedMigration {
+  /// Whether the row records the last run of a repeatable migration
+  pub fn is_repeatable(&self) -> bool {
+    self.name.starts_with(REPEATABLE_PREFIX)
+  }
+}
+
+pub(crate) fn create_table_sql(dialect: Dialect) -> String {
+  let applied_at = match dialect {
+    Dialect::Postgres => "TIMESTAMP",
+    Dialect::MySQL => "DATETIME",
+    Dialect::SQLite => "TEXT",
+  };
+  format!(
+    "CREATE TABLE IF NOT EXISTS {} (\
+     name VARCHAR(255) NOT NULL PRIMARY KEY, \
+     checksum CHAR(64) NOT NULL, \
+     batch INTEGER NOT NULL, \
+     applied_at {} NOT NULL DEFAULT CURRENT_TIMESTAMP, \
+     duration_ms BIGINT NOT NULL)",
+    HISTORY_TABLE, applied_at
+  )
+}
+
+/// Create the history table when missing.
+///
+/// Returns whether the table can be read, which is not the case during a dry
+/// run against a database that was never migrated.
+pub(crate) fn ensure_table(session: &mut dyn Session, dialect: Dialect) -> Result<bool> {
+  if !exists(session) {
+    session.batch_execute(&create_table_sql(dialect))?;
+    return Ok(exists(session));
+  }
+  Ok(true)
+}
+
+fn exists(session: &mut dyn Session) -> bool {
+  session
+    .query(&format!("SELECT name FROM {} WHERE 1 = 0", HISTORY_TABLE))
+    .is_ok()
+}
+
+pub(crate) fn insert_sql(
+  dialect: Dialect,
+  name: &str,
+  checksum: &str,
+  batch: i64,
+  duration: Duration,
+) -> String {
+  format!(
+    "INSERT INTO {} (name, checksum, batch, duration_ms) VALUES ({}, {}, {}, {})",
+    HISTORY_TABLE,
+    dialect.quote(name),
+    dialect.quote(checksum),
+    batch,
+    duration.as_millis()
+  )
+}
+
+pub(crate) fn update_checksum_sql(dialect: Dialect, name: &str, checksum: &str) -> String {
+  format!(
+    "UPDATE {} SET checksum = {} WHERE name = {}",
+    HISTORY_TABLE,
+    dialect.quote(checksum),
+    dialect.quote(name)
+  )
+}
+
+pub(crate) fn delete_sql(dialect: Dialect, name: &str) -> String {
+  format!(
+    "DELETE FROM {} WHERE name = {}",
+    HISTORY_TABLE,
+    dialect.quote(name)
+  )
+}
+
+/// Read the history table in the order the migrations were applied
+pub(crate) fn applied(session: &mut dyn Session) -> Result<Vec<AppliedMigration>> {
+  let rows = session.query(&format!(
+    "SELECT name, checksum, batch, applied_at, duration_ms FROM {} ORDER BY batch, name",
+    HISTORY_TABLE
+  ))?;
+  Ok(
+    rows
+      .iter()
+      .map(|row| AppliedMigration {
+        name: row.get_string("name").unwrap_or_default(),
+        checksum: row.get_string("checksum").unwrap_or_default(),
+        batch: row.get_i64("batch").unwrap_or(1),
+        applied_at: row.get_string("applied_at").unwrap_or_default(),
+        duration: Duration::from_millis(row.get_i64("duration_ms").unwrap_or(0).max(0) as u64),
+      })
+      .collect(),
+  )
+}
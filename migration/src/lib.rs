@@ -0,0 +1,30 @@
+extern crate connection;
+extern crate sha2;
+
+mod code;
+mod dialect;
+mod dry_run;
+mod errors;
+mod history;
+mod lint;
+mod lock;
+mod migration;
+mod migrator;
+mod schema;
+mod script;
+
+pub use self::code::{Code, RustMigration, Tx};
+pub use self::dialect::Dialect;
+pub use self::dry_run::{script, DryRun};
+pub use self::errors::Error;
+pub use self::history::{AppliedMigration, HISTORY_TABLE};
+pub use self::lint::{lint, Finding, Rule, Severity, LINT_IGNORE};
+pub use self::lock::LOCK_TABLE;
+pub use self::migration::{discover, embedded, EmbeddedFile, Migration, REPEATABLE_PREFIX};
+pub use self::migrator::{
+  MigrationRun, Migrator, RollbackTarget, BASELINE_BATCH, DEFAULT_LOCK_TIMEOUT,
+};
+pub use self::schema::DroppedObject;
+pub use self::script::{Script, NO_TRANSACTION, REPLACES};
+
+pub type Result<T> = std::result::Result<T, Error>;
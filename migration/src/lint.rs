@@ -0,0 +1,479 @@
+use crate::{Dialect, Error, Migration, Result};
+use std::fmt;
+
+/// Silences rules for the statement below it, e.g.
+/// `-- spectre:lint-ignore drop-column, rename-column`
+pub const LINT_IGNORE: &str = "-- spectre:lint-ignore";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+  Warning,
+  Error,
+}
+
+impl fmt::Display for Severity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Severity::Warning => f.write_str("warning"),
+      Severity::Error => f.write_str("error"),
+    }
+  }
+}
+
+/// A risky pattern looked for in migration scripts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Rule {
+  /// The data of the table is gone
+  DropTable,
+  /// The data of the column is gone, and code still reading it breaks
+  DropColumn,
+  /// Fails on a table holding rows, they have no value for the column
+  NotNullWithoutDefault,
+  /// Postgres blocks writes to the table until the index is built
+  NonConcurrentIndex,
+  /// Code still using the old name breaks
+  RenameColumn,
+  /// The table is rewritten under an exclusive lock
+  ChangeColumnType,
+}
+
+impl Rule {
+  pub const ALL: [Rule; 6] = [
+    Rule::DropTable,
+    Rule::DropColumn,
+    Rule::NotNullWithoutDefault,
+    Rule::NonConcurrentIndex,
+    Rule::RenameColumn,
+    Rule::ChangeColumnType,
+  ];
+
+  /// The name used to silence the rule
+  pub fn id(self) -> &'static str {
+    match self {
+      Rule::DropTable => "drop-table",
+      Rule::DropColumn => "drop-column",
+      Rule::NotNullWithoutDefault => "not-null-without-default",
+      Rule::NonConcurrentIndex => "non-concurrent-index",
+      Rule::RenameColumn => "rename-column",
+      Rule::ChangeColumnType => "change-column-type",
+    }
+  }
+
+  pub fn severity(self) -> Severity {
+    match self {
+      Rule::NonConcurrentIndex | Rule::ChangeColumnType => Severity::Warning,
+      _ => Severity::Error,
+    }
+  }
+
+  pub fn from_id(id: &str) -> Option<Rule> {
+    Rule::ALL.iter().copied().find(|rule| rule.id() == id)
+  }
+}
+
+/// A statement of a migration breaking a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+  pub rule: Rule,
+  pub migration: String,
+  /// The position of the statement in the up script, from 1
+  pub statement: usize,
+  pub message: String,
+}
+
+impl fmt::Display for Finding {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{}[{}] {} (statement {}): {}",
+      self.rule.severity(),
+      self.rule.id(),
+      self.migration,
+      self.statement,
+      self.message
+    )
+  }
+}
+
+/// Look for risky statements in the up script of a migration.
+///
+/// Tables created by the same migration are left alone, nothing reads them
+/// and they hold no rows yet.
+pub fn lint(migration: &Migration, dialect: Dialect) -> Result<Vec<Finding>> {
+  let statements = migration
+    .up
+    .statements(dialect)
+    .map_err(|reason| Error::InvalidMigration(migration.path.clone(), reason))?;
+
+  let mut created = Vec::new();
+  let mut findings = Vec::new();
+  for (i, statement) in statements.iter().enumerate() {
+    let ignored = ignored_rules(statement);
+    for (rule, message) in check(&tokens(statement, dialect), dialect, &mut created) {
+      if !ignored.contains(&rule) {
+        findings.push(Finding {
+          rule,
+          migration: migration.name.clone(),
+          statement: i + 1,
+          message,
+        });
+      }
+    }
+  }
+  Ok(findings)
+}
+
+/// The rules silenced by `-- spectre:lint-ignore` comments of a statement
+fn ignored_rules(statement: &str) -> Vec<Rule> {
+  statement
+    .lines()
+    .filter_map(|line| line.trim().strip_prefix(LINT_IGNORE))
+    .flat_map(|ids| ids.split(|c: char| c == ',' || c.is_whitespace()))
+    .filter_map(Rule::from_id)
+    .collect()
+}
+
+fn check(tokens: &[Token], dialect: Dialect, created: &mut Vec<String>) -> Vec<(Rule, String)> {
+  let is = |i: usize, keyword: &str| tokens.get(i).is_some_and(|token| token.is(keyword));
+  let mut findings = Vec::new();
+
+  if is(0, "CREATE") {
+    let mut i = 1;
+    while ["TEMP", "TEMPORARY", "UNLOGGED", "GLOBAL", "LOCAL"]
+      .iter()
+      .any(|keyword| is(i, keyword))
+    {
+      i += 1;
+    }
+    if is(i, "TABLE") {
+      i += 1;
+      if is(i, "IF") {
+        i += 3;
+      }
+      created.push(name(tokens, i).0);
+      return findings;
+    }
+
+    let i = if is(1, "UNIQUE") { 2 } else { 1 };
+    if is(i, "INDEX") && !is(i + 1, "CONCURRENTLY") && dialect == Dialect::Postgres {
+      if let Some(on) = tokens.iter().position(|token| token.is("ON")) {
+        let start = if is(on + 1, "ONLY") { on + 2 } else { on + 1 };
+        let table = name(tokens, start).0;
+        if !created.contains(&table) {
+          findings.push((
+            Rule::NonConcurrentIndex,
+            format!(
+              "creates an index on `{}` without CONCURRENTLY, writes to the table wait until it is built",
+              table
+            ),
+          ));
+        }
+      }
+    }
+  } else if is(0, "DROP") && is(1, "TABLE") {
+    let start = if is(2, "IF") { 4 } else { 2 };
+    for table in split_top_level(&tokens[start.min(tokens.len())..]) {
+      let table = name(table, 0).0;
+      if !table.is_empty() && !created.contains(&table) {
+        findings.push((Rule::DropTable, format!("drops the table `{}`", table)));
+      }
+    }
+  } else if is(0, "ALTER") && is(1, "TABLE") {
+    let mut i = 2;
+    if is(i, "IF") {
+      i += 2;
+    }
+    if is(i, "ONLY") {
+      i += 1;
+    }
+    let (table, i) = name(tokens, i);
+    if !created.contains(&table) {
+      for action in split_top_level(&tokens[i.min(tokens.len())..]) {
+        alter_action(action, &table, &mut findings);
+      }
+    }
+  }
+  findings
+}
+
+/// One action of an `ALTER TABLE`, e.g. `DROP COLUMN email`
+fn alter_action(tokens: &[Token], table: &str, findings: &mut Vec<(Rule, String)>) {
+  let is = |i: usize, keyword: &str| tokens.get(i).is_some_and(|token| token.is(keyword));
+  let is_any = |i: usize, keywords: &[&str]| keywords.iter().any(|keyword| is(i, keyword));
+  let text = |i: usize| tokens.get(i).map_or("", |token| token.text.as_str());
+  let constraints = [
+    "CONSTRAINT",
+    "PRIMARY",
+    "UNIQUE",
+    "FOREIGN",
+    "CHECK",
+    "INDEX",
+    "KEY",
+    "FULLTEXT",
+    "SPATIAL",
+    "PARTITION",
+  ];
+
+  if is(0, "DROP") && !is_any(1, &constraints) && !is(1, "DEFAULT") {
+    let mut i = if is(1, "COLUMN") { 2 } else { 1 };
+    if is(i, "IF") {
+      i += 2;
+    }
+    findings.push((
+      Rule::DropColumn,
+      format!("drops the column `{}` of `{}`", text(i), table),
+    ));
+  } else if is(0, "ADD") && !is_any(1, &constraints) {
+    let mut i = if is(1, "COLUMN") { 2 } else { 1 };
+    if is(i, "IF") {
+      i += 3;
+    }
+    let definition = &tokens[(i + 1).min(tokens.len())..];
+    let not_null = definition
+      .windows(2)
+      .any(|pair| pair[0].is("NOT") && pair[1].is("NULL"));
+    let filled = definition.iter().any(|token| {
+      ["DEFAULT", "GENERATED", "AUTO_INCREMENT", "AUTOINCREMENT"]
+        .iter()
+        .any(|keyword| token.is(keyword))
+    });
+    if not_null && !filled {
+      findings.push((
+        Rule::NotNullWithoutDefault,
+        format!(
+          "adds the NOT NULL column `{}` to `{}` without a default, the rows already there have no value for it",
+          text(i),
+          table
+        ),
+      ));
+    }
+  } else if is(0, "RENAME") && !is_any(1, &["TO", "AS", "CONSTRAINT", "INDEX", "KEY"]) {
+    let i = if is(1, "COLUMN") { 2 } else { 1 };
+    findings.push((
+      Rule::RenameColumn,
+      format!(
+        "renames the column `{}` of `{}` to `{}`",
+        text(i),
+        table,
+        text(i + 2)
+      ),
+    ));
+  } else if is(0, "CHANGE") {
+    // MySQL spells out the whole column again, under its old and new name
+    let i = if is(1, "COLUMN") { 2 } else { 1 };
+    if text(i) != text(i + 1) {
+      findings.push((
+        Rule::RenameColumn,
+        format!(
+          "renames the column `{}` of `{}` to `{}`",
+          text(i),
+          table,
+          text(i + 1)
+        ),
+      ));
+    }
+    findings.push((Rule::ChangeColumnType, redefines(text(i + 1), table)));
+  } else if is(0, "MODIFY") {
+    let i = if is(1, "COLUMN") { 2 } else { 1 };
+    findings.push((Rule::ChangeColumnType, redefines(text(i), table)));
+  } else if is(0, "ALTER") {
+    let i = if is(1, "COLUMN") { 2 } else { 1 };
+    let set_data_type = is(i + 1, "SET") && is(i + 2, "DATA") && is(i + 3, "TYPE");
+    if is(i + 1, "TYPE") || set_data_type {
+      findings.push((
+        Rule::ChangeColumnType,
+        format!(
+          "changes the type of the column `{}` of `{}`, the table is rewritten while locked",
+          text(i),
+          table
+        ),
+      ));
+    }
+  }
+}
+
+fn redefines(column: &str, table: &str) -> String {
+  format!(
+    "redefines the column `{}` of `{}`, the table may be rewritten while locked",
+    column, table
+  )
+}
+
+/// A possibly schema-qualified name starting at `i`, and the index after it
+fn name(tokens: &[Token], i: usize) -> (String, usize) {
+  let mut name = match tokens.get(i) {
+    Some(token) => token.text.clone(),
+    None => return (String::new(), i),
+  };
+  let mut i = i + 1;
+  while tokens.get(i).is_some_and(|token| token.is(".")) {
+    if let Some(part) = tokens.get(i + 1) {
+      name = format!("{}.{}", name, part.text);
+    }
+    i += 2;
+  }
+  (name, i)
+}
+
+/// Split on the commas outside of parentheses
+fn split_top_level(tokens: &[Token]) -> Vec<&[Token]> {
+  let mut parts = Vec::new();
+  let mut depth = 0;
+  let mut start = 0;
+  for (i, token) in tokens.iter().enumerate() {
+    if token.is("(") {
+      depth += 1;
+    } else if token.is(")") {
+      depth -= 1;
+    } else if token.is(",") && depth == 0 {
+      parts.push(&tokens[start..i]);
+      start = i + 1;
+    }
+  }
+  if start < tokens.len() {
+    parts.push(&tokens[start..]);
+  }
+  parts
+}
+
+/// A word, punctuation, or quoted identifier or string of a statement
+#[derive(Debug)]
+struct Token {
+  text: String,
+  quoted: bool,
+}
+
+impl Token {
+  fn is(&self, keyword: &str) -> bool {
+    !self.quoted && self.text.eq_ignore_ascii_case(keyword)
+  }
+}
+
+/// The tokens of a statement, leaving out its comments
+fn tokens(sql: &str, dialect: Dialect) -> Vec<Token> {
+  let chars: Vec<char> = sql.chars().collect();
+  let mut tokens = Vec::new();
+  let mut i = 0;
+  while i < chars.len() {
+    let c = chars[i];
+    let next = chars.get(i + 1).copied();
+    if c.is_whitespace() {
+      i += 1;
+    } else if (c == '-' && next == Some('-')) || (c == '#' && dialect == Dialect::MySQL) {
+      while i < chars.len() && chars[i] != '\n' {
+        i += 1;
+      }
+    } else if c == '/' && next == Some('*') {
+      i += 2;
+      while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+        i += 1;
+      }
+      i += 2;
+    } else if c == '\'' || c == '"' || c == '`' {
+      // a doubled quote stands for the quote itself
+      let mut text = String::new();
+      i += 1;
+      while i < chars.len() {
+        if chars[i] == c && chars.get(i + 1) == Some(&c) {
+          text.push(c);
+          i += 2;
+        } else if chars[i] == c {
+          i += 1;
+          break;
+        } else {
+          text.push(chars[i]);
+          i += 1;
+        }
+      }
+      tokens.push(Token { text, quoted: true });
+    } else if c.is_alphanumeric() || c == '_' || c == '$' {
+      let start = i;
+      while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+        i += 1;
+      }
+      tokens.push(Token {
+        text: chars[start..i].iter().collect(),
+        quoted: false,
+      });
+    } else {
+      tokens.push(Token {
+        text: c.to_string(),
+        quoted: false,
+      });
+      i += 1;
+    }
+  }
+  tokens
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::Script;
+  use std::path::PathBuf;
+
+  fn rules(sql: &str, dialect: Dialect) -> Vec<&'static str> {
+    let migration = Migration {
+      name: "20261019120000_test".to_string(),
+      path: PathBuf::from("20261019120000_test.sql"),
+      up: Script::new(sql),
+      down: None,
+      checksum: String::new(),
+      code: None,
+      replaces: Vec::new(),
+    };
+    lint(&migration, dialect)
+      .unwrap()
+      .iter()
+      .map(|finding| finding.rule.id())
+      .collect()
+  }
+
+  #[test]
+  fn test_lint_rules() {
+    let sql = "DROP TABLE IF EXISTS sessions;\n\
+               ALTER TABLE users DROP COLUMN email, ADD COLUMN age INTEGER NOT NULL, \
+               ADD COLUMN name TEXT NOT NULL DEFAULT '', DROP CONSTRAINT users_email_key;\n\
+               ALTER TABLE users RENAME COLUMN name TO full_name;\n\
+               ALTER TABLE users ALTER COLUMN age TYPE BIGINT;\n\
+               CREATE INDEX users_age_idx ON users (age);\n\
+               CREATE INDEX CONCURRENTLY users_name_idx ON users (name);";
+    assert_eq!(
+      rules(sql, Dialect::Postgres),
+      vec![
+        "drop-table",
+        "drop-column",
+        "not-null-without-default",
+        "rename-column",
+        "change-column-type",
+        "non-concurrent-index"
+      ]
+    );
+    assert_eq!(
+      rules(
+        "ALTER TABLE `users` CHANGE `name` `full_name` TEXT, MODIFY age BIGINT",
+        Dialect::MySQL
+      ),
+      vec!["rename-column", "change-column-type", "change-column-type"]
+    );
+  }
+
+  #[test]
+  fn test_lint_skips_created_tables() {
+    let sql = "CREATE TABLE users (id INTEGER, email TEXT);\n\
+               CREATE INDEX users_email_idx ON users (email);\n\
+               ALTER TABLE users ADD COLUMN age INTEGER NOT NULL;\n\
+               -- 'DROP TABLE users' in a comment\n\
+               SELECT 'DROP TABLE users';";
+    assert!(rules(sql, Dialect::Postgres).is_empty());
+  }
+
+  #[test]
+  fn test_lint_ignore() {
+    let sql = "-- spectre:lint-ignore drop-column, rename-column\n\
+               ALTER TABLE users DROP COLUMN email, RENAME COLUMN name TO full_name;\n\
+               DROP TABLE sessions;";
+    assert_eq!(rules(sql, Dialect::SQLite), vec!["drop-table"]);
+  }
+}
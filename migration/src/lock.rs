@@ -0,0 +1,112 @@
+use crate::history::HISTORY_TABLE;
+use crate::{Dialect, Error, Result};
+use connection::Session;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Key of the Postgres advisory lock, `spectre!` in ASCII
+const ADVISORY_LOCK_KEY: i64 = 0x7370_6563_7472_6521;
+
+/// Table holding the single row that marks a migration in progress on SQLite
+pub const LOCK_TABLE: &str = "spectre_migrations_lock";
+
+/// How often SQLite retries taking the lock row
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Wait until no other process is migrating the database and take the lock.
+///
+/// Postgres and MySQL release the lock when the session ends. The SQLite lock
+/// row outlives a crashed process and has to be deleted by hand.
+pub(crate) fn acquire(
+  session: &mut dyn Session,
+  dialect: Dialect,
+  timeout: Duration,
+) -> Result<()> {
+  match dialect {
+    Dialect::Postgres if timeout.is_zero() => {
+      // a zero `lock_timeout` waits forever, try the lock once instead
+      let rows = session.query(&format!(
+        "SELECT pg_try_advisory_lock({}) AS locked",
+        ADVISORY_LOCK_KEY
+      ))?;
+      match rows.first().and_then(|row| row.get_bool("locked")) {
+        Some(true) => Ok(()),
+        _ => Err(Error::LockTimeout {
+          timeout,
+          table: None,
+        }),
+      }
+    }
+    Dialect::Postgres => {
+      session.batch_execute(&format!("SET lock_timeout = {}", timeout.as_millis()))?;
+      let locked =
+        session.batch_execute(&format!("SELECT pg_advisory_lock({})", ADVISORY_LOCK_KEY));
+      session.batch_execute("RESET lock_timeout")?;
+      locked.map_err(|e| {
+        if e.is_lock_unavailable() {
+          Error::LockTimeout {
+            timeout,
+            table: None,
+          }
+        } else {
+          e.into()
+        }
+      })
+    }
+    Dialect::MySQL => {
+      // GET_LOCK waits in whole seconds
+      let seconds = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+      let rows = session.query(&format!(
+        "SELECT GET_LOCK('{}', {}) AS locked",
+        HISTORY_TABLE, seconds
+      ))?;
+      match rows.first().and_then(|row| row.get_i64("locked")) {
+        Some(1) => Ok(()),
+        _ => Err(Error::LockTimeout {
+          timeout,
+          table: None,
+        }),
+      }
+    }
+    Dialect::SQLite => {
+      session.batch_execute(&format!(
+        "CREATE TABLE IF NOT EXISTS {} (\
+         id INTEGER NOT NULL PRIMARY KEY, \
+         locked_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP)",
+        LOCK_TABLE
+      ))?;
+      let deadline = Instant::now() + timeout;
+      let insert = format!("INSERT INTO {} (id) VALUES (1)", LOCK_TABLE);
+      loop {
+        match session.batch_execute(&insert) {
+          Ok(()) => return Ok(()),
+          Err(e) if e.is_constraint_violation() || e.is_lock_unavailable() => {
+            if Instant::now() >= deadline {
+              return Err(Error::LockTimeout {
+                timeout,
+                table: Some(LOCK_TABLE),
+              });
+            }
+            thread::sleep(POLL_INTERVAL);
+          }
+          Err(e) => return Err(e.into()),
+        }
+      }
+    }
+  }
+}
+
+pub(crate) fn release(session: &mut dyn Session, dialect: Dialect) -> Result<()> {
+  match dialect {
+    Dialect::Postgres => {
+      session.query(&format!("SELECT pg_advisory_unlock({})", ADVISORY_LOCK_KEY))?;
+    }
+    Dialect::MySQL => {
+      session.query(&format!("SELECT RELEASE_LOCK('{}')", HISTORY_TABLE))?;
+    }
+    Dialect::SQLite => {
+      session.batch_execute(&format!("DELETE FROM {} WHERE id = 1", LOCK_TABLE))?;
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,412 @@
+use crate::script::{replaced, split_markers};
+use crate::{Code, Error, Result, RustMigration, Script};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A migration found in the migrations directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+  /// The file or directory name without extension, starting with the timestamp
+  /// that orders the migrations, or with `R__` for repeatable migrations
+  pub name: String,
+  pub path: PathBuf,
+  pub up: Script,
+  pub down: Option<Script>,
+  /// Hex encoded SHA-256 of the migration files
+  pub checksum: String,
+  /// Set for migrations written in Rust, run in place of the scripts
+  pub code: Option<Code>,
+  /// The migrations squashed into this baseline, listed in its up script
+  /// with `-- spectre:replaces`
+  pub replaces: Vec<String>,
+}
+
+impl Migration {
+  /// Whether the migration is repeatable: re-applied after the versioned
+  /// migrations whenever its checksum changes, and never reverted
+  pub fn is_repeatable(&self) -> bool {
+    self.name.starts_with(REPEATABLE_PREFIX)
+  }
+
+  /// Wrap a migration written in Rust, named with a timestamp like the files.
+  ///
+  /// It has no file, so its scripts are empty and the checksum only covers its name.
+  pub fn from_code(code: impl RustMigration + 'static) -> Result<Self> {
+    let name = code.name().to_string();
+    if !has_timestamp(&name) {
+      return Err(invalid(Path::new(&name), NO_TIMESTAMP));
+    }
+    let script = Script {
+      sql: String::new(),
+      transaction: code.transaction(),
+    };
+    Ok(Migration {
+      path: PathBuf::new(),
+      checksum: checksum(&[name.as_bytes()]),
+      up: script.clone(),
+      down: code.reversible().then_some(script),
+      code: Some(Code(Arc::new(code))),
+      replaces: Vec::new(),
+      name,
+    })
+  }
+
+  /// Read a single `.sql` file, split on its `-- +up` and `-- +down` markers
+  fn from_file(path: &Path) -> Result<Self> {
+    Migration::from_source(path, &read(path)?)
+  }
+
+  fn from_source(path: &Path, source: &str) -> Result<Self> {
+    let (up, down) = split_markers(source).map_err(|e| invalid(path, e))?;
+    Ok(Migration {
+      name: file_name(path).trim_end_matches(".sql").to_string(),
+      path: path.to_path_buf(),
+      checksum: checksum(&[source.as_bytes()]),
+      replaces: replaced(&up.sql),
+      up,
+      down,
+      code: None,
+    })
+  }
+
+  /// Read a directory holding an `up.sql` script and an optional `down.sql`
+  fn from_dir(path: &Path) -> Result<Self> {
+    let up_path = path.join("up.sql");
+    if !up_path.is_file() {
+      return Err(invalid(path, NO_UP_SCRIPT));
+    }
+    let up = read(&up_path)?;
+    let down_path = path.join("down.sql");
+    let down = if down_path.is_file() {
+      Some(read(&down_path)?)
+    } else {
+      None
+    };
+    Ok(Migration::from_scripts(path, &up, down.as_deref()))
+  }
+
+  fn from_scripts(path: &Path, up: &str, down: Option<&str>) -> Self {
+    let mut parts = vec![up.as_bytes()];
+    if let Some(down) = down {
+      parts.push(down.as_bytes());
+    }
+    Migration {
+      name: file_name(path),
+      path: path.to_path_buf(),
+      checksum: checksum(&parts),
+      up: Script::new(up),
+      down: down.map(Script::new),
+      code: None,
+      replaces: replaced(up),
+    }
+  }
+}
+
+/// A migration file bundled into the binary by `spectre::embed_migrations!`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmbeddedFile {
+  /// Relative to the migrations directory, e.g. `20261018120000_create_users/up.sql`
+  pub path: &'static str,
+  pub contents: &'static str,
+}
+
+/// Read embedded migration files the way [`discover`] reads the directory
+/// they came from, giving them the same names and checksums
+pub fn embedded(files: &[EmbeddedFile]) -> Result<Vec<Migration>> {
+  let contents = |path: &str| {
+    files
+      .iter()
+      .find(|file| file.path == path)
+      .map(|file| file.contents)
+  };
+  let mut names: Vec<&str> = files
+    .iter()
+    .filter_map(|file| file.path.split('/').next())
+    .collect();
+  names.sort_unstable();
+  names.dedup();
+
+  let mut migrations = Vec::new();
+  for name in names {
+    let path = Path::new(name);
+    let is_sql = name.ends_with(".sql");
+    let repeatable = is_sql && is_repeatable(name);
+    if !has_timestamp(name) && !repeatable {
+      return Err(invalid(path, NO_TIMESTAMP));
+    }
+    match contents(name) {
+      Some(source) if is_sql => migrations.push(Migration::from_source(path, source)?),
+      _ => {
+        let up =
+          contents(&format!("{}/up.sql", name)).ok_or_else(|| invalid(path, NO_UP_SCRIPT))?;
+        let down = contents(&format!("{}/down.sql", name));
+        migrations.push(Migration::from_scripts(path, up, down));
+      }
+    }
+  }
+  Ok(migrations)
+}
+
+/// The start of the file name of a repeatable migration, e.g. `R__refresh_views.sql`
+pub const REPEATABLE_PREFIX: &str = "R__";
+
+const NO_UP_SCRIPT: &str = "the directory has no `up.sql`";
+const NO_TIMESTAMP: &str = "the name must start with a timestamp, e.g. \
+   `20261018120000_create_users`, or with `R__` for a repeatable `.sql` file";
+
+/// Find every migration in the directory, ordered by name.
+///
+/// A migration is named after the time it was created followed by a
+/// description, e.g. `20261018120000_create_users`. It is either a directory
+/// holding `up.sql` and an optional `down.sql`, or a single `.sql` file using
+/// `-- +up` and `-- +down` markers. Other files, such as a README, are skipped.
+///
+/// A `.sql` file named `R__` followed by a description, e.g.
+/// `R__refresh_views.sql`, is a repeatable migration. Its name sorts after the
+/// timestamps, so it comes after every versioned migration.
+pub fn discover(dir: &Path) -> Result<Vec<Migration>> {
+  let entries = fs::read_dir(dir).map_err(|e| Error::IoError(dir.to_path_buf(), e))?;
+  let mut paths = Vec::new();
+  for entry in entries {
+    let path = entry
+      .map_err(|e| Error::IoError(dir.to_path_buf(), e))?
+      .path();
+    if !file_name(&path).starts_with('.') {
+      paths.push(path);
+    }
+  }
+  paths.sort();
+
+  let mut migrations = Vec::new();
+  for path in paths.iter() {
+    let is_sql = path.is_file() && path.extension().is_some_and(|ext| ext == "sql");
+    let is_migration_dir = path.is_dir() && path.join("up.sql").is_file();
+    let timestamped = has_timestamp(&file_name(path));
+    let repeatable = is_sql && is_repeatable(&file_name(path));
+
+    if (is_sql || is_migration_dir) && !timestamped && !repeatable {
+      return Err(invalid(path, NO_TIMESTAMP));
+    }
+    if path.is_dir() && timestamped {
+      migrations.push(Migration::from_dir(path)?);
+    } else if is_sql {
+      migrations.push(Migration::from_file(path)?);
+    }
+  }
+  Ok(migrations)
+}
+
+/// Whether the name starts with digits followed by `_` and a description
+fn has_timestamp(name: &str) -> bool {
+  let digits = name.bytes().take_while(u8::is_ascii_digit).count();
+  digits > 0 && name[digits..].starts_with('_') && name.len() > digits + 1
+}
+
+/// Whether the file name is `R__` followed by a description
+fn is_repeatable(name: &str) -> bool {
+  let name = name.trim_end_matches(".sql");
+  name.len() > REPEATABLE_PREFIX.len() && name.starts_with(REPEATABLE_PREFIX)
+}
+
+fn invalid(path: &Path, reason: impl Into<String>) -> Error {
+  Error::InvalidMigration(path.to_path_buf(), reason.into())
+}
+
+fn read(path: &Path) -> Result<String> {
+  fs::read_to_string(path).map_err(|e| Error::IoError(path.to_path_buf(), e))
+}
+
+fn file_name(path: &Path) -> String {
+  path
+    .file_name()
+    .map(|name| name.to_string_lossy().into_owned())
+    .unwrap_or_default()
+}
+
+fn checksum(parts: &[&[u8]]) -> String {
+  let mut hasher = Sha256::new();
+  for (i, part) in parts.iter().enumerate() {
+    if i > 0 {
+      hasher.update([0u8]);
+    }
+    hasher.update(part);
+  }
+  hasher
+    .finalize()
+    .iter()
+    .map(|byte| format!("{:02x}", byte))
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  extern crate tempfile;
+
+  use super::*;
+
+  #[test]
+  fn test_discover() {
+    let dir = tempfile::tempdir().unwrap();
+    let create_users = dir.path().join("20261018120000_create_users");
+    fs::create_dir(&create_users).unwrap();
+    fs::write(
+      create_users.join("up.sql"),
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .unwrap();
+    fs::write(create_users.join("down.sql"), "DROP TABLE users;").unwrap();
+    fs::write(
+      dir.path().join("20261017090000_create_posts.sql"),
+      "CREATE TABLE posts (id INTEGER);",
+    )
+    .unwrap();
+    fs::write(dir.path().join("README.md"), "notes").unwrap();
+    fs::create_dir(dir.path().join("fixtures")).unwrap();
+
+    let migrations = discover(dir.path()).unwrap();
+    let names: Vec<&str> = migrations.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(
+      names,
+      vec!["20261017090000_create_posts", "20261018120000_create_users"]
+    );
+    assert_eq!(migrations[1].up.sql, "CREATE TABLE users (id INTEGER);");
+    assert_eq!(
+      migrations[1].down.as_ref().unwrap().sql,
+      "DROP TABLE users;"
+    );
+    assert!(migrations[0].down.is_none());
+    assert_eq!(migrations[0].checksum.len(), 64);
+  }
+
+  #[test]
+  fn test_discover_single_file_with_markers() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+      dir.path().join("20261018120000_add_index.sql"),
+      "-- +up\n-- spectre:no-transaction\nCREATE INDEX CONCURRENTLY idx ON users (name);\n-- +down\nDROP INDEX idx;\n",
+    )
+    .unwrap();
+
+    let migrations = discover(dir.path()).unwrap();
+    assert!(!migrations[0].up.transaction);
+    assert_eq!(
+      migrations[0].down.as_ref().unwrap().sql,
+      "DROP INDEX idx;\n"
+    );
+  }
+
+  #[test]
+  fn test_discover_invalid_names() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("create_users.sql"), "SELECT 1;").unwrap();
+    assert!(matches!(
+      discover(dir.path()),
+      Err(Error::InvalidMigration(..))
+    ));
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("R__.sql"), "SELECT 1;").unwrap();
+    assert!(matches!(
+      discover(dir.path()),
+      Err(Error::InvalidMigration(..))
+    ));
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("20261018120000_empty")).unwrap();
+    assert!(matches!(
+      discover(dir.path()),
+      Err(Error::InvalidMigration(..))
+    ));
+  }
+
+  #[test]
+  fn test_discover_repeatable() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("R__refresh_views.sql"), "SELECT 1;").unwrap();
+    fs::write(
+      dir.path().join("20261018120000_create_users.sql"),
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .unwrap();
+
+    let migrations = discover(dir.path()).unwrap();
+    assert_eq!(migrations[1].name, "R__refresh_views");
+    assert!(migrations[1].is_repeatable());
+    assert!(!migrations[0].is_repeatable());
+
+    let files = [EmbeddedFile {
+      path: "R__refresh_views.sql",
+      contents: "SELECT 1;",
+    }];
+    assert_eq!(
+      embedded(&files).unwrap()[0].checksum,
+      migrations[1].checksum
+    );
+  }
+
+  #[test]
+  fn test_embedded() {
+    let dir = tempfile::tempdir().unwrap();
+    let create_users = dir.path().join("20261018120000_create_users");
+    fs::create_dir(&create_users).unwrap();
+    fs::write(
+      create_users.join("up.sql"),
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .unwrap();
+    fs::write(create_users.join("down.sql"), "DROP TABLE users;").unwrap();
+    fs::write(
+      dir.path().join("20261017090000_create_posts.sql"),
+      "-- +up\nCREATE TABLE posts (id INTEGER);\n-- +down\nDROP TABLE posts;\n",
+    )
+    .unwrap();
+
+    let files = [
+      EmbeddedFile {
+        path: "20261018120000_create_users/down.sql",
+        contents: "DROP TABLE users;",
+      },
+      EmbeddedFile {
+        path: "20261018120000_create_users/up.sql",
+        contents: "CREATE TABLE users (id INTEGER);",
+      },
+      EmbeddedFile {
+        path: "20261017090000_create_posts.sql",
+        contents: "-- +up\nCREATE TABLE posts (id INTEGER);\n-- +down\nDROP TABLE posts;\n",
+      },
+    ];
+    let migrations = embedded(&files).unwrap();
+    let discovered = discover(dir.path()).unwrap();
+    assert_eq!(migrations.len(), 2);
+    for (migration, on_disk) in migrations.iter().zip(discovered.iter()) {
+      assert_eq!(migration.name, on_disk.name);
+      assert_eq!(migration.checksum, on_disk.checksum);
+      assert_eq!(migration.down, on_disk.down);
+    }
+
+    let only_down = [EmbeddedFile {
+      path: "20261018120000_create_users/down.sql",
+      contents: "DROP TABLE users;",
+    }];
+    assert!(matches!(
+      embedded(&only_down),
+      Err(Error::InvalidMigration(..))
+    ));
+  }
+
+  #[test]
+  fn test_checksum() {
+    assert_eq!(
+      checksum(&[b"abc"]),
+      "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_ne!(checksum(&[b"ab", b"c"]), checksum(&[b"abc"]));
+  }
+
+  #[test]
+  fn test_missing_directory() {
+    assert!(discover(Path::new("/nonexistent/migrations")).is_err());
+  }
+}
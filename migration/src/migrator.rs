@@ -0,0 +1,1234 @@
+use crate::history::{self, AppliedMigration};
+use crate::{lock, schema, Dialect, DroppedObject, DryRun, Error, Migration, Result, Tx};
+use connection::Session;
+use std::time::{Duration, Instant};
+
+/// How long a run waits for another process to finish migrating the same database
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The batch of migrations recorded by [`Migrator::baseline`] without running them
+pub const BASELINE_BATCH: i64 = 0;
+
+/// A migration applied or reverted by a run of the migrator. A squashed
+/// baseline only recorded as applied has batch [`BASELINE_BATCH`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationRun {
+  pub name: String,
+  pub batch: i64,
+  pub duration: Duration,
+}
+
+/// The applied migrations a rollback reverts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RollbackTarget {
+  /// Every migration applied by the last run of `up`
+  LastBatch,
+  /// The given number of migrations, newest first
+  Steps(usize),
+  /// Every migration of the given batch
+  Batch(i64),
+}
+
+/// Applies the migrations of a directory to a database, keeping track of them
+/// in the history table
+#[derive(Debug, Clone)]
+pub struct Migrator {
+  dialect: Dialect,
+  migrations: Vec<Migration>,
+  lock_timeout: Duration,
+  // dry runs change nothing, so they do not wait for the lock
+  locking: bool,
+  // dry runs record statements instead of running them, Rust migrations are skipped
+  dry_run: bool,
+}
+
+impl Migrator {
+  pub fn new(dialect: Dialect, migrations: Vec<Migration>) -> Self {
+    Migrator {
+      dialect,
+      migrations,
+      lock_timeout: DEFAULT_LOCK_TIMEOUT,
+      locking: true,
+      dry_run: false,
+    }
+  }
+
+  /// Add a migration, such as one written in Rust, in order of its name among the others
+  pub fn migration(mut self, migration: Migration) -> Self {
+    let at = self
+      .migrations
+      .partition_point(|other| other.name <= migration.name);
+    self.migrations.insert(at, migration);
+    self
+  }
+
+  /// Set how long `up`, `rollback`, `reset` and `repair` wait for the migration lock
+  #[inline(always)]
+  pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+    self.lock_timeout = timeout;
+    self
+  }
+
+  #[inline(always)]
+  pub fn dialect(&self) -> Dialect {
+    self.dialect
+  }
+
+  #[inline(always)]
+  pub fn migrations(&self) -> &[Migration] {
+    &self.migrations
+  }
+
+  /// Read the history table, creating it when missing
+  pub fn applied(&self, session: &mut dyn Session) -> Result<Vec<AppliedMigration>> {
+    if history::ensure_table(session, self.dialect)? {
+      Ok(history::applied(session)?)
+    } else {
+      Ok(Vec::new())
+    }
+  }
+
+  /// The applied migrations whose files changed since they were applied,
+  /// leaving out repeatable migrations which are meant to change
+  pub fn changed(&self, applied: &[AppliedMigration]) -> Vec<&Migration> {
+    self
+      .migrations
+      .iter()
+      .filter(|migration| {
+        !migration.is_repeatable()
+          && applied
+            .iter()
+            .any(|a| a.name == migration.name && a.checksum != migration.checksum)
+      })
+      .collect()
+  }
+
+  /// Fail on the first applied migration whose file changed
+  pub fn verify(&self, applied: &[AppliedMigration]) -> Result<()> {
+    match self.changed(applied).first() {
+      Some(migration) => Err(Error::ChecksumMismatch {
+        name: migration.name.clone(),
+        path: migration.path.clone(),
+      }),
+      None => Ok(()),
+    }
+  }
+
+  /// The migrations not recorded in the history table, in the order they would be applied
+  pub fn pending(&self, session: &mut dyn Session) -> Result<Vec<&Migration>> {
+    let applied = self.applied(session)?;
+    self.verify(&applied)?;
+    Ok(self.outstanding(&applied))
+  }
+
+  /// The versioned migrations not applied yet, followed by the repeatable
+  /// ones never applied or changed since their last run
+  fn outstanding(&self, applied: &[AppliedMigration]) -> Vec<&Migration> {
+    let versioned = self.migrations.iter().filter(|migration| {
+      !migration.is_repeatable() && !applied.iter().any(|a| a.name == migration.name)
+    });
+    let repeatable = self.migrations.iter().filter(|migration| {
+      migration.is_repeatable()
+        && !applied
+          .iter()
+          .any(|a| a.name == migration.name && a.checksum == migration.checksum)
+    });
+    versioned.chain(repeatable).collect()
+  }
+
+  /// Apply every pending migration in order as a new batch, stopping at the first failure
+  pub fn up(&self, session: &mut dyn Session) -> Result<Vec<MigrationRun>> {
+    self.locked(session, |session| self.apply(session))
+  }
+
+  fn apply(&self, session: &mut dyn Session) -> Result<Vec<MigrationRun>> {
+    let applied = self.applied(session)?;
+    self.verify(&applied)?;
+    self.apply_pending(session, &applied)
+  }
+
+  fn apply_pending(
+    &self,
+    session: &mut dyn Session,
+    applied: &[AppliedMigration],
+  ) -> Result<Vec<MigrationRun>> {
+    let batch = applied.iter().map(|a| a.batch).max().unwrap_or(0) + 1;
+    let mut runs = Vec::new();
+    for migration in self.outstanding(applied) {
+      if self.mark_baseline(session, migration, applied)? {
+        runs.push(MigrationRun {
+          name: migration.name.clone(),
+          batch: BASELINE_BATCH,
+          duration: Duration::default(),
+        });
+        continue;
+      }
+      let duration = self.run(session, migration, Direction::Up, |duration| {
+        let mut statements = Vec::new();
+        // a repeatable migration keeps a single row, recording its last run
+        if migration.is_repeatable() {
+          statements.push(history::delete_sql(self.dialect, &migration.name));
+        }
+        statements.push(history::insert_sql(
+          self.dialect,
+          &migration.name,
+          &migration.checksum,
+          batch,
+          duration,
+        ));
+        statements
+      })?;
+      runs.push(MigrationRun {
+        name: migration.name.clone(),
+        batch,
+        duration,
+      });
+    }
+    Ok(runs)
+  }
+
+  /// Record the migrations up to and including `until` as applied without
+  /// running them, for a database that already has their changes. They go in
+  /// batch 0, which marks them as baselined in the history. Repeatable
+  /// migrations are left to run on the next `up`.
+  pub fn baseline(&self, session: &mut dyn Session, until: &str) -> Result<Vec<&Migration>> {
+    let end = self
+      .migrations
+      .iter()
+      .position(|migration| migration.name == until && !migration.is_repeatable())
+      .ok_or_else(|| Error::UnknownMigration(until.to_string()))?;
+
+    self.locked(session, |session| {
+      let applied = self.applied(session)?;
+      let marked: Vec<&Migration> = self.migrations[..=end]
+        .iter()
+        .filter(|migration| {
+          !migration.is_repeatable() && !applied.iter().any(|a| a.name == migration.name)
+        })
+        .collect();
+      for migration in marked.iter() {
+        session.batch_execute(&history::insert_sql(
+          self.dialect,
+          &migration.name,
+          &migration.checksum,
+          BASELINE_BATCH,
+          Duration::default(),
+        ))?;
+      }
+      Ok(marked)
+    })
+  }
+
+  /// Record a baseline as applied in place of the migrations squashed into
+  /// it when the database already ran them, returns whether it did. It goes
+  /// in batch 0 like [`Migrator::baseline`], so rollbacks leave it alone.
+  fn mark_baseline(
+    &self,
+    session: &mut dyn Session,
+    migration: &Migration,
+    applied: &[AppliedMigration],
+  ) -> Result<bool> {
+    let missing: Vec<String> = migration
+      .replaces
+      .iter()
+      .filter(|name| !applied.iter().any(|a| &a.name == *name))
+      .cloned()
+      .collect();
+    if migration.replaces.is_empty() || missing.len() == migration.replaces.len() {
+      return Ok(false);
+    }
+    if !missing.is_empty() {
+      return Err(Error::PartialBaseline {
+        name: migration.name.clone(),
+        missing,
+      });
+    }
+
+    let mut statements = vec![history::insert_sql(
+      self.dialect,
+      &migration.name,
+      &migration.checksum,
+      BASELINE_BATCH,
+      Duration::default(),
+    )];
+    for name in migration.replaces.iter() {
+      statements.push(history::delete_sql(self.dialect, name));
+    }
+    // the history never lists both the baseline and the migrations it replaces
+    session.batch_execute(self.dialect.begin())?;
+    let result = statements
+      .iter()
+      .try_for_each(|statement| session.batch_execute(statement));
+    match result {
+      Ok(()) => session.batch_execute("COMMIT")?,
+      Err(source) => {
+        let _ = session.batch_execute("ROLLBACK");
+        return Err(Error::MigrationFailed {
+          name: migration.name.clone(),
+          source: source.into(),
+        });
+      }
+    }
+    Ok(true)
+  }
+
+  /// Record the current checksum of every applied migration whose file was
+  /// intentionally edited
+  pub fn repair(&self, session: &mut dyn Session) -> Result<Vec<&Migration>> {
+    self.locked(session, |session| {
+      let applied = self.applied(session)?;
+      let changed = self.changed(&applied);
+      for migration in changed.iter() {
+        session.batch_execute(&history::update_checksum_sql(
+          self.dialect,
+          &migration.name,
+          &migration.checksum,
+        ))?;
+      }
+      Ok(changed)
+    })
+  }
+
+  /// Revert applied migrations, newest first. Repeatable migrations are not
+  /// reverted, they run again when their checksum changes.
+  pub fn rollback(
+    &self,
+    session: &mut dyn Session,
+    target: RollbackTarget,
+    force: bool,
+  ) -> Result<Vec<MigrationRun>> {
+    self.locked(session, |session| {
+      let mut applied = self.applied(session)?;
+      // baselined migrations never ran here, only an explicit forced rollback reverts them
+      if target == RollbackTarget::Batch(BASELINE_BATCH) {
+        if !force {
+          return Err(Error::BaselineRollback);
+        }
+      } else {
+        applied.retain(|a| a.batch != BASELINE_BATCH);
+      }
+      // a last batch that only re-ran repeatable migrations has nothing to revert
+      let last_batch = applied.last().map(|a| a.batch);
+      applied.retain(|a| !a.is_repeatable());
+      let applied = applied.into_iter().rev();
+      let targets: Vec<AppliedMigration> = match target {
+        RollbackTarget::LastBatch => applied.filter(|a| Some(a.batch) == last_batch).collect(),
+        RollbackTarget::Steps(steps) => applied.take(steps).collect(),
+        RollbackTarget::Batch(batch) => applied.filter(|a| a.batch == batch).collect(),
+      };
+      self.revert(session, &targets, force)
+    })
+  }
+
+  /// Revert every applied migration but the baselined ones, newest first, and
+  /// forget the runs of the repeatable ones so the next `up` applies them again
+  pub fn reset(&self, session: &mut dyn Session, force: bool) -> Result<Vec<MigrationRun>> {
+    self.locked(session, |session| {
+      let (repeatable, versioned): (Vec<AppliedMigration>, Vec<AppliedMigration>) = self
+        .applied(session)?
+        .into_iter()
+        .partition(AppliedMigration::is_repeatable);
+      let targets: Vec<AppliedMigration> = versioned
+        .into_iter()
+        .rev()
+        .filter(|a| a.batch != BASELINE_BATCH)
+        .collect();
+      let runs = self.revert(session, &targets, force)?;
+      for applied in repeatable.iter() {
+        session.batch_execute(&history::delete_sql(self.dialect, &applied.name))?;
+      }
+      Ok(runs)
+    })
+  }
+
+  /// Drop every object of the schema, including those no migration created,
+  /// then apply all migrations from scratch
+  pub fn fresh(
+    &self,
+    session: &mut dyn Session,
+  ) -> Result<(Vec<DroppedObject>, Vec<MigrationRun>)> {
+    self.locked(session, |session| {
+      let dropped = schema::drop_all(session, self.dialect)?;
+      // the history went with everything else, a dry run would still read the old one
+      session.batch_execute(&history::create_table_sql(self.dialect))?;
+      Ok((dropped, self.apply_pending(session, &[])?))
+    })
+  }
+
+  /// Run `f` against a session that records the statements changing the
+  /// database instead of executing them, returning its result and the script
+  pub fn dry_run<T>(
+    &self,
+    session: &mut dyn Session,
+    f: impl FnOnce(&Migrator, &mut dyn Session) -> Result<T>,
+  ) -> Result<(T, Vec<String>)> {
+    let migrator = Migrator {
+      locking: false,
+      dry_run: true,
+      ..self.clone()
+    };
+    let mut recorder = DryRun::new(session);
+    let value = f(&migrator, &mut recorder)?;
+    Ok((value, recorder.into_statements()))
+  }
+
+  /// Hold the migration lock while `f` reads and changes the history, so
+  /// concurrent runs apply each migration once and the waiting ones see the
+  /// history left by the first
+  fn locked<T>(
+    &self,
+    session: &mut dyn Session,
+    f: impl FnOnce(&mut dyn Session) -> Result<T>,
+  ) -> Result<T> {
+    if !self.locking {
+      return f(session);
+    }
+    lock::acquire(session, self.dialect, self.lock_timeout)?;
+    let result = f(session);
+    let released = lock::release(session, self.dialect);
+    let value = result?;
+    released.map(|_| value)
+  }
+
+  /// Run the down scripts of the given migrations in order and remove them from the history.
+  ///
+  /// Nothing is reverted when one of them has no down script, unless `force`
+  /// is set, in which case those are only removed from the history.
+  fn revert(
+    &self,
+    session: &mut dyn Session,
+    targets: &[AppliedMigration],
+    force: bool,
+  ) -> Result<Vec<MigrationRun>> {
+    let mut reverts = Vec::new();
+    for applied in targets.iter() {
+      let down = self
+        .migrations
+        .iter()
+        .find(|migration| migration.name == applied.name)
+        .filter(|migration| migration.down.is_some());
+      if down.is_none() && !force {
+        return Err(Error::MissingDownScript(applied.name.clone()));
+      }
+      reverts.push((applied, down));
+    }
+
+    let mut runs = Vec::new();
+    for (applied, down) in reverts {
+      let delete = history::delete_sql(self.dialect, &applied.name);
+      let duration = match down {
+        Some(migration) => self.run(session, migration, Direction::Down, |_| vec![delete])?,
+        None => {
+          session
+            .batch_execute(&delete)
+            .map_err(|source| Error::MigrationFailed {
+              name: applied.name.clone(),
+              source: source.into(),
+            })?;
+          Duration::default()
+        }
+      };
+      runs.push(MigrationRun {
+        name: applied.name.clone(),
+        batch: applied.batch,
+        duration,
+      });
+    }
+    Ok(runs)
+  }
+
+  /// Run the statements of a script one by one, or the code of a Rust
+  /// migration, followed by the history bookkeeping, inside a transaction
+  /// when the script and dialect allow
+  fn run(
+    &self,
+    session: &mut dyn Session,
+    migration: &Migration,
+    direction: Direction,
+    bookkeeping: impl FnOnce(Duration) -> Vec<String>,
+  ) -> Result<Duration> {
+    let script = match direction {
+      Direction::Up => &migration.up,
+      Direction::Down => migration
+        .down
+        .as_ref()
+        .expect("only migrations with a down script are reverted"),
+    };
+    let statements = script
+      .statements(self.dialect)
+      .map_err(|reason| Error::InvalidMigration(migration.path.clone(), reason))?;
+    let transaction = script.transaction && self.dialect.transactional_ddl();
+
+    let execute = || -> std::result::Result<Duration, Box<dyn std::error::Error + Send + Sync>> {
+      if transaction {
+        session.batch_execute(self.dialect.begin())?;
+      }
+
+      let started = Instant::now();
+      let result = match migration.code {
+        // user code would query and change the live database, it is only named
+        Some(_) if self.dry_run => session
+          .batch_execute(&format!("-- Rust migration {} (not shown)", migration.name))
+          .map_err(Into::into),
+        Some(ref code) => {
+          let mut tx = Tx::new(session, self.dialect);
+          match direction {
+            Direction::Up => code.0.up(&mut tx),
+            Direction::Down => code.0.down(&mut tx),
+          }
+        }
+        None => statements
+          .iter()
+          .try_for_each(|statement| session.batch_execute(statement))
+          .map_err(Into::into),
+      }
+      .and_then(|_| {
+        let duration = started.elapsed();
+        bookkeeping(duration)
+          .iter()
+          .try_for_each(|statement| session.batch_execute(statement))
+          .map(|_| duration)
+          .map_err(Into::into)
+      });
+
+      match result {
+        Ok(duration) if transaction => Ok(session.batch_execute("COMMIT").map(|_| duration)?),
+        Err(error) if transaction => {
+          let _ = session.batch_execute("ROLLBACK");
+          Err(error)
+        }
+        result => result,
+      }
+    };
+
+    execute().map_err(|source| Error::MigrationFailed {
+      name: migration.name.clone(),
+      source,
+    })
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+  Up,
+  Down,
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod test {
+  extern crate tempfile;
+
+  use super::*;
+  use crate::RustMigration;
+  use connection::{Connection, ConnectionOption, Datasource, Provider};
+  use std::fs;
+
+  fn connection(dir: &tempfile::TempDir) -> Connection {
+    let mut connection = Connection::new(&ConnectionOption {
+      datasource: Datasource {
+        provider: Provider::SQLite,
+        url: format!("sqlite://{}", dir.path().join("test.db").display()),
+      },
+      ..ConnectionOption::default()
+    });
+    connection.connect().unwrap();
+    connection
+  }
+
+  fn migrator(dir: &tempfile::TempDir) -> Migrator {
+    Migrator::new(
+      Dialect::SQLite,
+      crate::discover(&dir.path().join("migrations")).unwrap(),
+    )
+  }
+
+  #[test]
+  fn test_up() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+      migrations.join("20261018120000_create_users.sql"),
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+    )
+    .unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+
+    let runs = migrator(&dir).up(&mut session).unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].name, "20261018120000_create_users");
+    session.query("SELECT * FROM users").unwrap();
+
+    fs::write(
+      migrations.join("20261018130000_create_posts.sql"),
+      "CREATE TABLE posts (id INTEGER PRIMARY KEY);",
+    )
+    .unwrap();
+    let migrator = migrator(&dir);
+    assert_eq!(migrator.pending(&mut session).unwrap().len(), 1);
+    assert_eq!(migrator.up(&mut session).unwrap().len(), 1);
+    assert!(migrator.up(&mut session).unwrap().is_empty());
+
+    let applied = migrator.applied(&mut session).unwrap();
+    assert_eq!(applied.len(), 2);
+    assert_eq!(applied[0].checksum, migrator.migrations()[0].checksum);
+    assert!(!applied[0].applied_at.is_empty());
+    assert_eq!((applied[0].batch, applied[1].batch), (1, 2));
+  }
+
+  #[test]
+  fn test_checksum_mismatch_and_repair() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    let path = migrations.join("20261018120000_create_users.sql");
+    fs::write(&path, "CREATE TABLE users (id INTEGER);").unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+    migrator(&dir).up(&mut session).unwrap();
+
+    fs::write(&path, "CREATE TABLE users (id INTEGER, name TEXT);").unwrap();
+    let migrator = migrator(&dir);
+    match migrator.up(&mut session) {
+      Err(Error::ChecksumMismatch {
+        name,
+        path: changed,
+      }) => {
+        assert_eq!(name, "20261018120000_create_users");
+        assert_eq!(changed, path);
+      }
+      other => panic!("unexpected result {:?}", other),
+    }
+    assert!(migrator.pending(&mut session).is_err());
+
+    let repaired = migrator.repair(&mut session).unwrap();
+    assert_eq!(repaired.len(), 1);
+    assert!(migrator.pending(&mut session).unwrap().is_empty());
+    assert!(migrator.repair(&mut session).unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_fresh() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+      migrations.join("20261018120000_create_users.sql"),
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+    let migrator = migrator(&dir);
+    migrator.up(&mut session).unwrap();
+    session
+      .batch_execute("INSERT INTO users VALUES (1); CREATE TABLE scratch (id INTEGER);")
+      .unwrap();
+
+    let (dropped, runs) = migrator.fresh(&mut session).unwrap();
+    assert_eq!(dropped.len(), 3);
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].batch, 1);
+    assert!(session.query("SELECT * FROM scratch").is_err());
+    assert!(session.query("SELECT * FROM users").unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_dry_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+      migrations.join("20261018120000_create_users.sql"),
+      "-- +up\nCREATE TABLE users (id INTEGER);\n-- +down\nDROP TABLE users;\n",
+    )
+    .unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+    let migrator = migrator(&dir);
+
+    let (runs, statements) = migrator
+      .dry_run(&mut session, |migrator, session| migrator.up(session))
+      .unwrap();
+    assert_eq!(runs.len(), 1);
+    assert!(statements[0].starts_with("CREATE TABLE IF NOT EXISTS spectre_migrations"));
+    assert_eq!(
+      statements[1..3],
+      ["BEGIN", "CREATE TABLE users (id INTEGER)"]
+    );
+    assert!(statements[3].starts_with("INSERT INTO spectre_migrations"));
+    assert_eq!(statements[4], "COMMIT");
+    assert!(session.query("SELECT * FROM spectre_migrations").is_err());
+
+    migrator.up(&mut session).unwrap();
+    let (_, statements) = migrator
+      .dry_run(&mut session, |migrator, session| {
+        migrator.rollback(session, RollbackTarget::LastBatch, false)
+      })
+      .unwrap();
+    assert_eq!(
+      statements,
+      [
+        "BEGIN",
+        "DROP TABLE users",
+        "DELETE FROM spectre_migrations WHERE name = '20261018120000_create_users'",
+        "COMMIT"
+      ]
+    );
+    session.query("SELECT * FROM users").unwrap();
+  }
+
+  #[test]
+  fn test_lock_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+      migrations.join("20261018120000_create_users.sql"),
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .unwrap();
+    let holder = connection(&dir);
+    let mut holding = holder.session().unwrap();
+    lock::acquire(&mut holding, Dialect::SQLite, Duration::from_secs(1)).unwrap();
+
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+    let migrator = migrator(&dir).lock_timeout(Duration::from_millis(200));
+    let error = migrator.up(&mut session).unwrap_err();
+    assert!(matches!(error, Error::LockTimeout { .. }));
+    assert!(error
+      .to_string()
+      .contains("DELETE FROM spectre_migrations_lock"));
+    assert!(migrator.applied(&mut session).unwrap().is_empty());
+
+    lock::release(&mut holding, Dialect::SQLite).unwrap();
+    assert_eq!(migrator.up(&mut session).unwrap().len(), 1);
+    // the lock is given back after the run
+    lock::acquire(&mut holding, Dialect::SQLite, Duration::from_millis(200)).unwrap();
+  }
+
+  #[test]
+  fn test_concurrent_up_applies_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+      migrations.join("20261018120000_create_users.sql"),
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .unwrap();
+
+    let applied: usize = std::thread::scope(|scope| {
+      let handles: Vec<_> = (0..4)
+        .map(|_| {
+          scope.spawn(|| {
+            let connection = connection(&dir);
+            let mut session = connection.session().unwrap();
+            migrator(&dir).up(&mut session).unwrap().len()
+          })
+        })
+        .collect();
+      handles.into_iter().map(|h| h.join().unwrap()).sum()
+    });
+    assert_eq!(applied, 1);
+  }
+
+  #[test]
+  fn test_rollback_by_batch() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    for (version, table) in [(1, "users"), (2, "posts")].iter() {
+      fs::write(
+        migrations.join(format!("2026101812000{}_create_{}.sql", version, table)),
+        format!(
+          "-- +up\nCREATE TABLE {0} (id INTEGER);\n-- +down\nDROP TABLE {0};\n",
+          table
+        ),
+      )
+      .unwrap();
+    }
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+    migrator(&dir).up(&mut session).unwrap();
+
+    fs::write(
+      migrations.join("20261018130000_create_tags.sql"),
+      "-- +up\nCREATE TABLE tags (id INTEGER);\n-- +down\nDROP TABLE tags;\n",
+    )
+    .unwrap();
+    let migrator = migrator(&dir);
+    let runs = migrator.up(&mut session).unwrap();
+    assert_eq!(runs[0].batch, 2);
+
+    let runs = migrator
+      .rollback(&mut session, RollbackTarget::Batch(1), false)
+      .unwrap();
+    assert_eq!(runs.len(), 2);
+    assert!(runs.iter().all(|run| run.batch == 1));
+
+    let runs = migrator
+      .rollback(&mut session, RollbackTarget::LastBatch, false)
+      .unwrap();
+    assert_eq!(runs[0].name, "20261018130000_create_tags");
+    assert!(migrator.applied(&mut session).unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_lock_error_is_not_a_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("migrations")).unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+    session
+      .batch_execute("CREATE VIEW spectre_migrations_lock AS SELECT 1 AS id")
+      .unwrap();
+
+    let migrator = migrator(&dir).lock_timeout(Duration::from_secs(5));
+    assert!(matches!(
+      migrator.up(&mut session),
+      Err(Error::ConnectionError(_))
+    ));
+  }
+
+  #[test]
+  fn test_failed_migration_is_rolled_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+      migrations.join("20261018120000_broken.sql"),
+      "CREATE TABLE users (id INTEGER); INSERT INTO missing VALUES (1);",
+    )
+    .unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+
+    match migrator(&dir).up(&mut session) {
+      Err(Error::MigrationFailed { name, .. }) => assert_eq!(name, "20261018120000_broken"),
+      other => panic!("unexpected result {:?}", other),
+    }
+    assert!(session.query("SELECT * FROM users").is_err());
+    assert!(migrator(&dir).applied(&mut session).unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_no_transaction_directive() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    // sqlite refuses to vacuum inside a transaction
+    fs::write(migrations.join("20261018120000_vacuum.sql"), "VACUUM;").unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+    assert!(migrator(&dir).up(&mut session).is_err());
+
+    fs::write(
+      migrations.join("20261018120000_vacuum.sql"),
+      "-- spectre:no-transaction\nVACUUM;",
+    )
+    .unwrap();
+    assert_eq!(migrator(&dir).up(&mut session).unwrap().len(), 1);
+  }
+
+  #[test]
+  fn test_rollback_and_reset() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+      migrations.join("20261018120000_create_users.sql"),
+      "-- +up\nCREATE TABLE users (id INTEGER);\n-- +down\nDROP TABLE users;\n",
+    )
+    .unwrap();
+    let create_posts = migrations.join("20261018130000_create_posts");
+    fs::create_dir(&create_posts).unwrap();
+    fs::write(
+      create_posts.join("up.sql"),
+      "CREATE TABLE posts (id INTEGER);",
+    )
+    .unwrap();
+    fs::write(create_posts.join("down.sql"), "DROP TABLE posts;").unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+    let migrator = migrator(&dir);
+    migrator.up(&mut session).unwrap();
+
+    let runs = migrator
+      .rollback(&mut session, RollbackTarget::Steps(1), false)
+      .unwrap();
+    assert_eq!(runs[0].name, "20261018130000_create_posts");
+    assert!(session.query("SELECT * FROM posts").is_err());
+    session.query("SELECT * FROM users").unwrap();
+    assert_eq!(migrator.pending(&mut session).unwrap().len(), 1);
+
+    migrator.up(&mut session).unwrap();
+    let runs = migrator.reset(&mut session, false).unwrap();
+    let names: Vec<&str> = runs.iter().map(|run| run.name.as_str()).collect();
+    assert_eq!(
+      names,
+      vec!["20261018130000_create_posts", "20261018120000_create_users"]
+    );
+    assert!(session.query("SELECT * FROM users").is_err());
+    assert!(migrator.applied(&mut session).unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_rollback_without_down_script() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+      migrations.join("20261018120000_create_users.sql"),
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+    let migrator = migrator(&dir);
+    migrator.up(&mut session).unwrap();
+
+    assert!(matches!(
+      migrator.rollback(&mut session, RollbackTarget::LastBatch, false),
+      Err(Error::MissingDownScript(_))
+    ));
+    assert_eq!(migrator.applied(&mut session).unwrap().len(), 1);
+
+    migrator
+      .rollback(&mut session, RollbackTarget::LastBatch, true)
+      .unwrap();
+    assert!(migrator.applied(&mut session).unwrap().is_empty());
+    // the table is left in place, only the history row is removed
+    session.query("SELECT * FROM users").unwrap();
+  }
+
+  struct BackfillNames;
+
+  impl RustMigration for BackfillNames {
+    fn name(&self) -> &str {
+      "20261018130000_backfill_names"
+    }
+
+    fn up(&self, tx: &mut Tx) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+      for row in tx.query("SELECT id FROM users")? {
+        let id = row.get_i64("id").ok_or("no id")?;
+        tx.execute(&format!(
+          "UPDATE users SET name = 'user {}' WHERE id = {}",
+          id, id
+        ))?;
+      }
+      Ok(())
+    }
+
+    fn down(
+      &self,
+      tx: &mut Tx,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+      tx.execute("UPDATE users SET name = NULL")?;
+      Err("cannot tell the names apart".into())
+    }
+  }
+
+  #[test]
+  fn test_rust_migration() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+      migrations.join("20261018120000_create_users.sql"),
+      "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);\nINSERT INTO users (id) VALUES (1);",
+    )
+    .unwrap();
+    fs::write(
+      migrations.join("20261018140000_require_names.sql"),
+      "CREATE TABLE named AS SELECT * FROM users WHERE name IS NOT NULL;",
+    )
+    .unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+
+    let migrator = migrator(&dir).migration(Migration::from_code(BackfillNames).unwrap());
+    // a dry run names the Rust migration, its code would query a table not created yet
+    let (_, statements) = migrator
+      .dry_run(&mut session, |migrator, session| migrator.up(session))
+      .unwrap();
+    assert!(statements
+      .iter()
+      .any(|s| s == "-- Rust migration 20261018130000_backfill_names (not shown)"));
+    let runs = migrator.up(&mut session).unwrap();
+    let names: Vec<&str> = runs.iter().map(|run| run.name.as_str()).collect();
+    assert_eq!(
+      names,
+      vec![
+        "20261018120000_create_users",
+        "20261018130000_backfill_names",
+        "20261018140000_require_names"
+      ]
+    );
+    let rows = session.query("SELECT name FROM named").unwrap();
+    assert_eq!(rows[0].get_string("name").unwrap(), "user 1");
+
+    // a failing down rolls back its statements and keeps the history
+    session.batch_execute("DROP TABLE named").unwrap();
+    session
+      .batch_execute("DELETE FROM spectre_migrations WHERE name LIKE '%_require_names'")
+      .unwrap();
+    match migrator.rollback(&mut session, RollbackTarget::Steps(1), false) {
+      Err(Error::MigrationFailed { name, source }) => {
+        assert_eq!(name, "20261018130000_backfill_names");
+        assert_eq!(source.to_string(), "cannot tell the names apart");
+      }
+      other => panic!("expected the down to fail, got {:?}", other),
+    }
+    let rows = session.query("SELECT name FROM users").unwrap();
+    assert_eq!(rows[0].get_string("name").unwrap(), "user 1");
+    assert_eq!(migrator.applied(&mut session).unwrap().len(), 2);
+  }
+
+  struct Irreversible(&'static str);
+
+  impl RustMigration for Irreversible {
+    fn name(&self) -> &str {
+      self.0
+    }
+
+    fn up(&self, _: &mut Tx) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+      Ok(())
+    }
+
+    fn down(
+      &self,
+      _: &mut Tx,
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+      unreachable!("an irreversible migration is never reverted")
+    }
+
+    fn reversible(&self) -> bool {
+      false
+    }
+  }
+
+  #[test]
+  fn test_rust_migration_checks() {
+    assert!(matches!(
+      Migration::from_code(Irreversible("backfill_names")),
+      Err(Error::InvalidMigration(..))
+    ));
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("migrations")).unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+    let migration = Migration::from_code(Irreversible("20261018130000_purge")).unwrap();
+    let migrator = migrator(&dir).migration(migration);
+    migrator.up(&mut session).unwrap();
+    assert!(matches!(
+      migrator.rollback(&mut session, RollbackTarget::LastBatch, false),
+      Err(Error::MissingDownScript(_))
+    ));
+    let runs = migrator
+      .rollback(&mut session, RollbackTarget::LastBatch, true)
+      .unwrap();
+    assert_eq!(runs[0].name, "20261018130000_purge");
+  }
+
+  #[test]
+  fn test_baseline() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    let create_users = migrations.join("20261018120000_create_users.sql");
+    let add_name = migrations.join("20261018130000_add_name.sql");
+    fs::write(
+      &create_users,
+      "CREATE TABLE users (id INTEGER PRIMARY KEY);",
+    )
+    .unwrap();
+    fs::write(&add_name, "ALTER TABLE users ADD COLUMN name TEXT;").unwrap();
+    let other = tempfile::tempdir().unwrap();
+    let (connection, other_connection) = (connection(&dir), connection(&other));
+    let mut session = connection.session().unwrap();
+    migrator(&dir).up(&mut session).unwrap();
+
+    fs::remove_file(&create_users).unwrap();
+    fs::remove_file(&add_name).unwrap();
+    fs::write(
+      migrations.join("20261018130000_baseline.sql"),
+      "-- spectre:replaces 20261018120000_create_users\n\
+       -- spectre:replaces 20261018130000_add_name\n\
+       CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+    )
+    .unwrap();
+    let migrator = migrator(&dir);
+    assert_eq!(
+      migrator.migrations()[0].replaces,
+      vec!["20261018120000_create_users", "20261018130000_add_name"]
+    );
+
+    // the database already has the tables, the baseline is only recorded
+    let runs = migrator.up(&mut session).unwrap();
+    assert_eq!(runs[0].name, "20261018130000_baseline");
+    assert_eq!(runs[0].batch, BASELINE_BATCH);
+    let applied = migrator.applied(&mut session).unwrap();
+    let names: Vec<&str> = applied.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(names, vec!["20261018130000_baseline"]);
+    assert_eq!(applied[0].batch, BASELINE_BATCH);
+
+    // a new database runs it
+    let mut fresh = other_connection.session().unwrap();
+    migrator.up(&mut fresh).unwrap();
+    fresh.query("SELECT name FROM users").unwrap();
+
+    // one that only ran the first of them cannot be caught up
+    fresh
+      .batch_execute("DELETE FROM spectre_migrations")
+      .unwrap();
+    fresh
+      .batch_execute(&history::insert_sql(
+        Dialect::SQLite,
+        "20261018120000_create_users",
+        "",
+        1,
+        Duration::default(),
+      ))
+      .unwrap();
+    match migrator.up(&mut fresh) {
+      Err(Error::PartialBaseline { missing, .. }) => {
+        assert_eq!(missing, vec!["20261018130000_add_name"])
+      }
+      other => panic!("expected a partial baseline, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn test_baseline_existing_database() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+      migrations.join("20261018120000_create_users.sql"),
+      "CREATE TABLE users (id INTEGER PRIMARY KEY);",
+    )
+    .unwrap();
+    fs::write(
+      migrations.join("20261018130000_add_name.sql"),
+      "ALTER TABLE users ADD COLUMN name TEXT;",
+    )
+    .unwrap();
+    fs::write(
+      migrations.join("20261018140000_create_posts.sql"),
+      "CREATE TABLE posts (id INTEGER PRIMARY KEY);",
+    )
+    .unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+    session
+      .batch_execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)")
+      .unwrap();
+
+    let migrator = migrator(&dir);
+    assert!(matches!(
+      migrator.baseline(&mut session, "20261018150000_missing"),
+      Err(Error::UnknownMigration(_))
+    ));
+    let marked = migrator
+      .baseline(&mut session, "20261018130000_add_name")
+      .unwrap();
+    assert_eq!(marked.len(), 2);
+    assert!(migrator
+      .baseline(&mut session, "20261018130000_add_name")
+      .unwrap()
+      .is_empty());
+
+    let runs = migrator.up(&mut session).unwrap();
+    let names: Vec<&str> = runs.iter().map(|run| run.name.as_str()).collect();
+    assert_eq!(names, vec!["20261018140000_create_posts"]);
+    let batches: Vec<i64> = migrator
+      .applied(&mut session)
+      .unwrap()
+      .iter()
+      .map(|a| a.batch)
+      .collect();
+    assert_eq!(batches, vec![BASELINE_BATCH, BASELINE_BATCH, 1]);
+
+    // the baselined migrations are left alone unless targeted and forced
+    let runs = migrator
+      .rollback(&mut session, RollbackTarget::LastBatch, true)
+      .unwrap();
+    assert_eq!(runs[0].name, "20261018140000_create_posts");
+    for target in [RollbackTarget::LastBatch, RollbackTarget::Steps(2)] {
+      assert!(migrator
+        .rollback(&mut session, target, true)
+        .unwrap()
+        .is_empty());
+    }
+    assert!(migrator.reset(&mut session, true).unwrap().is_empty());
+    assert!(matches!(
+      migrator.rollback(&mut session, RollbackTarget::Batch(BASELINE_BATCH), false),
+      Err(Error::BaselineRollback)
+    ));
+    let runs = migrator
+      .rollback(&mut session, RollbackTarget::Batch(BASELINE_BATCH), true)
+      .unwrap();
+    assert_eq!(runs.len(), 2);
+    assert!(migrator.applied(&mut session).unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_repeatable() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+      migrations.join("20261018120000_create_users.sql"),
+      "-- +up\nCREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);\n-- +down\nDROP TABLE users;\n",
+    )
+    .unwrap();
+    let view = migrations.join("R__user_names.sql");
+    fs::write(
+      &view,
+      "DROP VIEW IF EXISTS user_names; CREATE VIEW user_names AS SELECT name FROM users;",
+    )
+    .unwrap();
+    let connection = connection(&dir);
+    let mut session = connection.session().unwrap();
+
+    let runs = migrator(&dir).up(&mut session).unwrap();
+    let names: Vec<&str> = runs.iter().map(|run| run.name.as_str()).collect();
+    assert_eq!(names, vec!["20261018120000_create_users", "R__user_names"]);
+    assert!(migrator(&dir).up(&mut session).unwrap().is_empty());
+
+    // changing the file runs it again instead of failing the checksum check
+    fs::write(
+      &view,
+      "DROP VIEW IF EXISTS user_names; CREATE VIEW user_names AS SELECT id, name FROM users;",
+    )
+    .unwrap();
+    let migrator = migrator(&dir);
+    assert_eq!(migrator.pending(&mut session).unwrap().len(), 1);
+    let runs = migrator.up(&mut session).unwrap();
+    assert_eq!((runs[0].name.as_str(), runs[0].batch), ("R__user_names", 2));
+    session.query("SELECT id FROM user_names").unwrap();
+    let applied = migrator.applied(&mut session).unwrap();
+    assert_eq!(applied.len(), 2);
+    assert_eq!(applied[1].checksum, migrator.migrations()[1].checksum);
+
+    // the last batch only re-ran the view, there is nothing to roll back
+    assert!(migrator
+      .rollback(&mut session, RollbackTarget::LastBatch, false)
+      .unwrap()
+      .is_empty());
+    assert_eq!(migrator.applied(&mut session).unwrap().len(), 2);
+    let runs = migrator
+      .rollback(&mut session, RollbackTarget::Batch(1), false)
+      .unwrap();
+    assert_eq!(runs[0].name, "20261018120000_create_users");
+    assert_eq!(migrator.up(&mut session).unwrap().len(), 1);
+
+    migrator.reset(&mut session, false).unwrap();
+    assert!(migrator.applied(&mut session).unwrap().is_empty());
+    assert_eq!(migrator.up(&mut session).unwrap().len(), 2);
+  }
+}
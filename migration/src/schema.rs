@@ -0,0 +1,193 @@
+use crate::lock::LOCK_TABLE;
+use crate::{Dialect, Result};
+use connection::Session;
+use std::fmt;
+
+/// A table, view, sequence or type removed by [`drop_all`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DroppedObject {
+  pub kind: &'static str,
+  pub name: String,
+}
+
+impl fmt::Display for DroppedObject {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} {}", self.kind, self.name)
+  }
+}
+
+/// Drop every view, table, sequence and type of the current schema, whether
+/// or not a migration created it
+pub(crate) fn drop_all(session: &mut dyn Session, dialect: Dialect) -> Result<Vec<DroppedObject>> {
+  let objects = list(session, dialect)?;
+  let cascade = if dialect == Dialect::Postgres {
+    " CASCADE"
+  } else {
+    ""
+  };
+  let drops: Vec<String> = objects
+    .iter()
+    .map(|object| {
+      format!(
+        "DROP {} IF EXISTS {}{}",
+        object.kind.to_uppercase(),
+        dialect.quote_ident(&object.name),
+        cascade
+      )
+    })
+    .collect();
+
+  // foreign keys would otherwise dictate the order tables can be dropped in
+  let (before, after) = match dialect {
+    Dialect::Postgres => (None, None),
+    Dialect::MySQL => (
+      Some("SET FOREIGN_KEY_CHECKS = 0"),
+      Some("SET FOREIGN_KEY_CHECKS = 1"),
+    ),
+    Dialect::SQLite => (
+      Some("PRAGMA foreign_keys = OFF"),
+      Some("PRAGMA foreign_keys = ON"),
+    ),
+  };
+  if let Some(before) = before {
+    session.batch_execute(before)?;
+  }
+  let result = drops
+    .iter()
+    .try_for_each(|statement| session.batch_execute(statement));
+  if let Some(after) = after {
+    session.batch_execute(after)?;
+  }
+  result?;
+  Ok(objects)
+}
+
+/// The objects of the current schema, views first so the tables they read
+/// from can be dropped after them
+fn list(session: &mut dyn Session, dialect: Dialect) -> Result<Vec<DroppedObject>> {
+  let queries: Vec<(&'static str, String)> = match dialect {
+    Dialect::Postgres => vec![
+      ("materialized view", relations("'m'")),
+      ("view", relations("'v'")),
+      ("table", relations("'r', 'p'")),
+      ("sequence", relations("'S'")),
+      (
+        "domain",
+        format!(
+          "SELECT t.typname AS name FROM pg_type t \
+           JOIN pg_namespace n ON n.oid = t.typnamespace \
+           WHERE n.nspname = current_schema() AND t.typtype = 'd' AND {}",
+          not_in_extension("pg_type", "t.oid")
+        ),
+      ),
+      (
+        "type",
+        format!(
+          "SELECT t.typname AS name FROM pg_type t \
+           JOIN pg_namespace n ON n.oid = t.typnamespace \
+           LEFT JOIN pg_class c ON c.oid = t.typrelid \
+           WHERE n.nspname = current_schema() \
+           AND (t.typtype = 'e' OR (t.typtype = 'c' AND c.relkind = 'c')) AND {}",
+          not_in_extension("pg_type", "t.oid")
+        ),
+      ),
+    ],
+    Dialect::MySQL => vec![
+      (
+        "view",
+        "SELECT table_name AS name FROM information_schema.tables \
+         WHERE table_schema = DATABASE() AND table_type = 'VIEW'"
+          .into(),
+      ),
+      (
+        "table",
+        "SELECT table_name AS name FROM information_schema.tables \
+         WHERE table_schema = DATABASE() AND table_type = 'BASE TABLE'"
+          .into(),
+      ),
+    ],
+    Dialect::SQLite => vec![
+      (
+        "view",
+        "SELECT name FROM sqlite_master WHERE type = 'view' ORDER BY name".into(),
+      ),
+      (
+        "table",
+        // the lock table is kept, it marks the run dropping everything else
+        format!(
+          "SELECT name FROM sqlite_master WHERE type = 'table' \
+           AND name NOT LIKE 'sqlite_%' AND name <> '{}' ORDER BY name",
+          LOCK_TABLE
+        ),
+      ),
+    ],
+  };
+
+  let mut objects = Vec::new();
+  for (kind, query) in queries.iter() {
+    for row in session.query(query)?.iter() {
+      if let Some(name) = row.get_string("name") {
+        objects.push(DroppedObject { kind, name });
+      }
+    }
+  }
+  Ok(objects)
+}
+
+/// The Postgres relations of the current schema with one of the given `relkind`s
+fn relations(kinds: &str) -> String {
+  format!(
+    "SELECT c.relname AS name FROM pg_class c \
+     JOIN pg_namespace n ON n.oid = c.relnamespace \
+     WHERE n.nspname = current_schema() AND c.relkind IN ({}) AND {}",
+    kinds,
+    not_in_extension("pg_class", "c.oid")
+  )
+}
+
+/// Leaves out objects created by an extension, such as PostGIS's
+/// `spatial_ref_sys`, which only `DROP EXTENSION` removes
+fn not_in_extension(catalog: &str, oid: &str) -> String {
+  format!(
+    "NOT EXISTS (SELECT 1 FROM pg_depend d \
+     WHERE d.classid = '{}'::regclass AND d.objid = {} AND d.deptype = 'e')",
+    catalog, oid
+  )
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod test {
+  use super::*;
+  use connection::{Connection, ConnectionOption, Datasource, Provider};
+
+  #[test]
+  fn test_drop_all() {
+    let mut connection = Connection::new(&ConnectionOption {
+      datasource: Datasource {
+        provider: Provider::SQLite,
+        url: "sqlite://:memory:".into(),
+      },
+      ..ConnectionOption::default()
+    });
+    connection.connect().unwrap();
+    let mut session = connection.session().unwrap();
+    session
+      .batch_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY AUTOINCREMENT); \
+         CREATE TABLE posts (id INTEGER, user_id INTEGER REFERENCES users (id)); \
+         CREATE VIEW user_posts AS SELECT * FROM users JOIN posts ON posts.user_id = users.id;",
+      )
+      .unwrap();
+
+    let dropped: Vec<String> = drop_all(&mut session, Dialect::SQLite)
+      .unwrap()
+      .iter()
+      .map(|object| object.to_string())
+      .collect();
+    assert_eq!(
+      dropped,
+      vec!["view user_posts", "table posts", "table users"]
+    );
+    assert!(list(&mut session, Dialect::SQLite).unwrap().is_empty());
+  }
+}
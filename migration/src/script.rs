@@ -0,0 +1,465 @@
+use crate::Dialect;
+
+/// Turns off the transaction around a script, for statements like `CREATE INDEX CONCURRENTLY`
+pub const NO_TRANSACTION: &str = "-- spectre:no-transaction";
+
+/// The up or down half of a migration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Script {
+  pub sql: String,
+  /// Whether the script runs inside a transaction where the dialect allows
+  pub transaction: bool,
+}
+
+impl Script {
+  pub fn new(sql: &str) -> Self {
+    Script {
+      sql: sql.to_string(),
+      transaction: !has_directive(sql),
+    }
+  }
+
+  /// Split the script into the statements sent to the database one by one.
+  ///
+  /// Delimiters inside quotes, comments and dollar-quoted bodies do not end a
+  /// statement, MySQL scripts may change the delimiter with `DELIMITER`, and
+  /// SQLite trigger bodies are kept whole.
+  pub fn statements(&self, dialect: Dialect) -> Result<Vec<String>, String> {
+    Splitter::new(&self.sql, dialect).split()
+  }
+}
+
+/// Lists a migration squashed into a baseline, one per line, e.g.
+/// `-- spectre:replaces 20261018120000_create_users`
+pub const REPLACES: &str = "-- spectre:replaces";
+
+/// The migrations a baseline script replaces
+pub(crate) fn replaced(sql: &str) -> Vec<String> {
+  sql
+    .lines()
+    .filter_map(|line| line.trim().strip_prefix(REPLACES))
+    .map(|name| name.trim().to_string())
+    .filter(|name| !name.is_empty())
+    .collect()
+}
+
+pub(crate) fn has_directive(sql: &str) -> bool {
+  sql
+    .lines()
+    .any(|line| line.trim().eq_ignore_ascii_case(NO_TRANSACTION))
+}
+
+/// Split a single migration file on its `-- +up` and `-- +down` markers.
+///
+/// A file without markers is all up script. A `-- spectre:no-transaction`
+/// above the first marker applies to both halves, statements above `-- +up`
+/// are an error.
+pub(crate) fn split_markers(source: &str) -> Result<(Script, Option<Script>), String> {
+  let mut header = String::new();
+  let mut up: Option<String> = None;
+  let mut down: Option<String> = None;
+
+  for line in source.split_inclusive('\n') {
+    let marker = line.trim().to_ascii_lowercase();
+    if marker == "-- +up" {
+      if up.is_some() {
+        return Err("the `-- +up` marker is given more than once".to_string());
+      }
+      if down.is_some() {
+        return Err("the `-- +up` marker must come before `-- +down`".to_string());
+      }
+      up = Some(String::new());
+    } else if marker == "-- +down" {
+      if down.is_some() {
+        return Err("the `-- +down` marker is given more than once".to_string());
+      }
+      down = Some(String::new());
+    } else if let Some(ref mut down) = down {
+      down.push_str(line);
+    } else if let Some(ref mut up) = up {
+      up.push_str(line);
+    } else {
+      header.push_str(line);
+    }
+  }
+
+  // with an up marker the lines above it are not run, only directives belong there
+  if up.is_some()
+    && header.lines().any(|line| {
+      let line = line.trim();
+      !line.is_empty() && !line.starts_with("--")
+    })
+  {
+    return Err("only comments and directives may come before the `-- +up` marker".to_string());
+  }
+  let shared = has_directive(&header);
+  let script = |sql: &str| {
+    let mut script = Script::new(sql);
+    script.transaction = script.transaction && !shared;
+    script
+  };
+  match (up, down) {
+    (None, None) => Ok((Script::new(&header), None)),
+    (Some(up), down) => Ok((script(&up), down.as_deref().map(script))),
+    // everything above the down marker is the up script
+    (None, Some(down)) => Ok((Script::new(&header), Some(script(&down)))),
+  }
+}
+
+struct Splitter<'a> {
+  sql: &'a str,
+  bytes: &'a [u8],
+  dialect: Dialect,
+  delimiter: String,
+  statements: Vec<String>,
+  /// Start of the statement being read
+  start: usize,
+  /// Whether the statement being read has anything besides whitespace and comments
+  has_content: bool,
+}
+
+impl<'a> Splitter<'a> {
+  fn new(sql: &'a str, dialect: Dialect) -> Self {
+    Splitter {
+      sql,
+      bytes: sql.as_bytes(),
+      dialect,
+      delimiter: ";".to_string(),
+      statements: Vec::new(),
+      start: 0,
+      has_content: false,
+    }
+  }
+
+  fn split(mut self) -> Result<Vec<String>, String> {
+    let mut i = 0;
+    while i < self.bytes.len() {
+      if self.dialect == Dialect::MySQL && (i == 0 || self.bytes[i - 1] == b'\n') {
+        if let Some(next) = self.delimiter_command(i)? {
+          self.push(i);
+          i = next;
+          self.start = i;
+          continue;
+        }
+      }
+
+      let c = self.bytes[i];
+      i = match c {
+        b'-' if self.peek(i + 1) == Some(b'-') => self.line_end(i),
+        b'#' if self.dialect == Dialect::MySQL => self.line_end(i),
+        b'/' if self.peek(i + 1) == Some(b'*') => match self.sql[i + 2..].find("*/") {
+          Some(end) => i + 2 + end + 2,
+          None => return Err("unterminated block comment".to_string()),
+        },
+        b'\'' | b'"' | b'`' => {
+          self.has_content = true;
+          self.quoted(i, c)?
+        }
+        b'$' if self.dialect == Dialect::Postgres => {
+          self.has_content = true;
+          self.dollar_quoted(i)?
+        }
+        _ if self.bytes[i..].starts_with(self.delimiter.as_bytes()) && !self.in_trigger_body(i) => {
+          self.push(i);
+          self.start = i + self.delimiter.len();
+          self.start
+        }
+        _ => {
+          if !c.is_ascii_whitespace() {
+            self.has_content = true;
+          }
+          i + 1
+        }
+      };
+    }
+    self.push(self.bytes.len());
+    Ok(self.statements)
+  }
+
+  fn peek(&self, i: usize) -> Option<u8> {
+    self.bytes.get(i).copied()
+  }
+
+  fn line_end(&self, i: usize) -> usize {
+    self.sql[i..]
+      .find('\n')
+      .map_or(self.bytes.len(), |end| i + end)
+  }
+
+  /// Finish the statement ending at `end`, skipping it when it holds nothing to run
+  fn push(&mut self, end: usize) {
+    if self.has_content {
+      self
+        .statements
+        .push(self.sql[self.start..end].trim().to_string());
+    }
+    self.has_content = false;
+  }
+
+  /// A MySQL client `DELIMITER` line starting at `i`, returns the end of the line
+  fn delimiter_command(&mut self, i: usize) -> Result<Option<usize>, String> {
+    let end = self.line_end(i);
+    let line = self.sql[i..end].trim();
+    let keyword = "DELIMITER";
+    if line.len() > keyword.len()
+      && line[..keyword.len()].eq_ignore_ascii_case(keyword)
+      && line.as_bytes()[keyword.len()].is_ascii_whitespace()
+    {
+      let delimiter = line[keyword.len()..].trim();
+      if delimiter.is_empty() {
+        return Err("`DELIMITER` needs a delimiter".to_string());
+      }
+      self.delimiter = delimiter.to_string();
+      return Ok(Some(end));
+    }
+    if line.eq_ignore_ascii_case(keyword) {
+      return Err("`DELIMITER` needs a delimiter".to_string());
+    }
+    Ok(None)
+  }
+
+  /// Skip a quoted string or identifier starting at `i`
+  fn quoted(&self, i: usize, quote: u8) -> Result<usize, String> {
+    let backslash_escapes = match self.dialect {
+      Dialect::MySQL => quote != b'`',
+      Dialect::Postgres => quote == b'\'' && self.is_escape_string(i),
+      _ => false,
+    };
+    let mut j = i + 1;
+    while j < self.bytes.len() {
+      match self.bytes[j] {
+        b'\\' if backslash_escapes => j += 2,
+        c if c == quote => return Ok(j + 1),
+        _ => j += 1,
+      }
+    }
+    Err(format!(
+      "unterminated quote {} in `{}`",
+      quote as char,
+      self.excerpt(i)
+    ))
+  }
+
+  /// Whether the quote at `i` opens a Postgres escape string such as `E'it\'s'`
+  fn is_escape_string(&self, i: usize) -> bool {
+    i > 0
+      && self.bytes[i - 1].eq_ignore_ascii_case(&b'e')
+      && (i == 1 || {
+        let c = self.bytes[i - 2];
+        !(c.is_ascii_alphanumeric() || c == b'_' || c == b'$')
+      })
+  }
+
+  /// Skip a Postgres dollar-quoted body starting at `i`, such as `$$ ... $$` or `$body$ ... $body$`
+  fn dollar_quoted(&self, i: usize) -> Result<usize, String> {
+    let follows_identifier = i > 0 && {
+      let c = self.bytes[i - 1];
+      c.is_ascii_alphanumeric() || c == b'_' || c == b'$'
+    };
+    let tag_len = self.bytes[i + 1..]
+      .iter()
+      .take_while(|c| c.is_ascii_alphanumeric() || **c == b'_')
+      .count();
+    let tag_end = i + 1 + tag_len;
+    if follows_identifier
+      || self.peek(tag_end) != Some(b'$')
+      || self.peek(i + 1).is_some_and(|c| c.is_ascii_digit())
+    {
+      // a parameter like `$1` or a `$` inside an identifier
+      return Ok(i + 1);
+    }
+
+    let tag = &self.sql[i..=tag_end];
+    match self.sql[tag_end + 1..].find(tag) {
+      Some(end) => Ok(tag_end + 1 + end + tag.len()),
+      None => Err(format!("unterminated dollar quote {}", tag)),
+    }
+  }
+
+  /// Whether the delimiter at `i` sits inside the body of a SQLite trigger,
+  /// which only ends with the `END` matching its `BEGIN`. `CASE` expressions
+  /// in the body have their own `END`.
+  fn in_trigger_body(&self, i: usize) -> bool {
+    if self.dialect != Dialect::SQLite {
+      return false;
+    }
+    let words = keywords(&self.sql[self.start..i]);
+    let is_trigger = matches!(
+      words.as_slice(),
+      [create, trigger, ..] | [create, _, trigger, ..]
+        if create == "CREATE" && trigger == "TRIGGER"
+    );
+    if !is_trigger {
+      return false;
+    }
+
+    let mut depth = 0;
+    for word in words.iter().skip_while(|word| *word != "BEGIN") {
+      match word.as_str() {
+        "BEGIN" | "CASE" => depth += 1,
+        "END" => depth -= 1,
+        _ => {}
+      }
+    }
+    depth > 0
+  }
+
+  fn excerpt(&self, i: usize) -> String {
+    self.sql[i..].chars().take(30).collect()
+  }
+}
+
+/// The upper-cased words of `sql`, leaving out quoted text and comments
+fn keywords(sql: &str) -> Vec<String> {
+  let bytes = sql.as_bytes();
+  let mut words = Vec::new();
+  let mut i = 0;
+  while i < bytes.len() {
+    let c = bytes[i];
+    let skip_to = |end: Option<usize>, len: usize| end.map_or(bytes.len(), |end| end + len);
+    i = match c {
+      b'-' if bytes.get(i + 1) == Some(&b'-') => skip_to(sql[i..].find('\n').map(|e| i + e), 0),
+      b'/' if bytes.get(i + 1) == Some(&b'*') => {
+        skip_to(sql[i + 2..].find("*/").map(|e| i + 2 + e), 2)
+      }
+      b'\'' | b'"' | b'`' => skip_to(sql[i + 1..].find(c as char).map(|e| i + 1 + e), 1),
+      b'[' => skip_to(sql[i + 1..].find(']').map(|e| i + 1 + e), 1),
+      _ if c.is_ascii_alphanumeric() || c == b'_' => {
+        let len = bytes[i..]
+          .iter()
+          .take_while(|c| c.is_ascii_alphanumeric() || **c == b'_')
+          .count();
+        words.push(sql[i..i + len].to_ascii_uppercase());
+        i + len
+      }
+      _ => i + 1,
+    };
+  }
+  words
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn split(sql: &str, dialect: Dialect) -> Vec<String> {
+    Script::new(sql).statements(dialect).unwrap()
+  }
+
+  #[test]
+  fn test_split_statements() {
+    let statements = split(
+      "-- users\nCREATE TABLE users (id INTEGER, name TEXT DEFAULT 'a;b');\n\
+       /* ; */ INSERT INTO users VALUES (1, \"x;\");\n-- trailing comment\n",
+      Dialect::SQLite,
+    );
+    assert_eq!(statements.len(), 2);
+    assert_eq!(
+      statements[0],
+      "-- users\nCREATE TABLE users (id INTEGER, name TEXT DEFAULT 'a;b')"
+    );
+    assert_eq!(
+      statements[1],
+      "/* ; */ INSERT INTO users VALUES (1, \"x;\")"
+    );
+  }
+
+  #[test]
+  fn test_split_dollar_quoted_bodies() {
+    let sql = "CREATE FUNCTION touch() RETURNS trigger AS $body$\nBEGIN\n  NEW.updated_at = now();\n  RETURN NEW;\nEND;\n$body$ LANGUAGE plpgsql;\n\
+               DO $$ BEGIN PERFORM 1; END $$;\nSELECT $1;";
+    let statements = split(sql, Dialect::Postgres);
+    assert_eq!(statements.len(), 3);
+    assert!(statements[0].ends_with("$body$ LANGUAGE plpgsql"));
+    assert_eq!(statements[1], "DO $$ BEGIN PERFORM 1; END $$");
+  }
+
+  #[test]
+  fn test_split_mysql_delimiter() {
+    let sql = "DROP PROCEDURE IF EXISTS touch;\nDELIMITER //\nCREATE PROCEDURE touch()\nBEGIN\n  UPDATE users SET name = 'it\\'s;';\nEND //\nDELIMITER ;\nSELECT 1;";
+    let statements = split(sql, Dialect::MySQL);
+    assert_eq!(statements.len(), 3);
+    assert!(statements[1].starts_with("CREATE PROCEDURE touch()"));
+    assert!(statements[1].ends_with("END"));
+    assert_eq!(statements[2], "SELECT 1");
+  }
+
+  #[test]
+  fn test_split_sqlite_trigger() {
+    let sql = "CREATE TRIGGER touch AFTER UPDATE ON users BEGIN\n  UPDATE users SET updated_at = 1;\n  SELECT 1;\nEND;\nSELECT 2;";
+    let statements = split(sql, Dialect::SQLite);
+    assert_eq!(statements.len(), 2);
+    assert!(statements[0].ends_with("END"));
+  }
+
+  #[test]
+  fn test_split_sqlite_trigger_with_case() {
+    let sql = "CREATE TRIGGER grade AFTER INSERT ON users BEGIN\n  UPDATE users SET rank = CASE WHEN NEW.score > 10 THEN 'high' ELSE 'end' END;\n  SELECT CASE NEW.id WHEN 1 THEN 1 END;\nEND;\nSELECT 2;";
+    let statements = split(sql, Dialect::SQLite);
+    assert_eq!(statements.len(), 2);
+    assert!(statements[0].starts_with("CREATE TRIGGER grade"));
+    assert!(statements[0].ends_with("END;\nEND"));
+    assert_eq!(statements[1], "SELECT 2");
+  }
+
+  #[test]
+  fn test_split_postgres_escape_strings() {
+    let sql = "INSERT INTO users (name) VALUES (E'it\\'s; fine');\nSELECT 'a\\';\nSELECT 1;";
+    let statements = split(sql, Dialect::Postgres);
+    assert_eq!(statements.len(), 3);
+    assert_eq!(
+      statements[0],
+      "INSERT INTO users (name) VALUES (E'it\\'s; fine')"
+    );
+    assert_eq!(statements[1], "SELECT 'a\\'");
+  }
+
+  #[test]
+  fn test_split_errors() {
+    assert!(Script::new("SELECT 'open")
+      .statements(Dialect::Postgres)
+      .is_err());
+    assert!(Script::new("SELECT $$ open")
+      .statements(Dialect::Postgres)
+      .is_err());
+    assert!(Script::new("/* open").statements(Dialect::SQLite).is_err());
+    assert!(Script::new("DELIMITER\nSELECT 1")
+      .statements(Dialect::MySQL)
+      .is_err());
+  }
+
+  #[test]
+  fn test_split_markers() {
+    let (up, down) =
+      split_markers("-- +up\nCREATE TABLE users (id INTEGER);\n-- +down\nDROP TABLE users;\n")
+        .unwrap();
+    assert_eq!(up.sql, "CREATE TABLE users (id INTEGER);\n");
+    assert!(up.transaction);
+    assert_eq!(down.unwrap().sql, "DROP TABLE users;\n");
+
+    let (up, down) = split_markers("CREATE TABLE users (id INTEGER);").unwrap();
+    assert_eq!(up.sql, "CREATE TABLE users (id INTEGER);");
+    assert!(down.is_none());
+
+    assert!(split_markers("-- +up\n-- +up\n").is_err());
+    assert!(split_markers("-- +down\n-- +up\n").is_err());
+    // statements above the up marker would never run
+    assert!(split_markers("-- users\n\n-- +up\nSELECT 1;\n").is_ok());
+    assert!(split_markers("CREATE TABLE users (id INTEGER);\n-- +up\nSELECT 1;\n").is_err());
+  }
+
+  #[test]
+  fn test_no_transaction_directive() {
+    let (up, down) = split_markers(
+      "-- +up\n-- spectre:no-transaction\nCREATE INDEX CONCURRENTLY idx ON users (name);\n-- +down\nDROP INDEX idx;\n",
+    )
+    .unwrap();
+    assert!(!up.transaction);
+    assert!(down.unwrap().transaction);
+
+    let (up, down) =
+      split_markers("-- spectre:no-transaction\n-- +up\nSELECT 1;\n-- +down\nSELECT 2;\n").unwrap();
+    assert!(!up.transaction);
+    assert!(!down.unwrap().transaction);
+  }
+}
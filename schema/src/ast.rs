@@ -0,0 +1,220 @@
+/// Every model and enum declared in a schema file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+  pub enums: Vec<Enum>,
+  pub models: Vec<Model>,
+}
+
+impl Schema {
+  pub fn model(&self, name: &str) -> Option<&Model> {
+    self.models.iter().find(|model| model.name == name)
+  }
+
+  pub fn find_enum(&self, name: &str) -> Option<&Enum> {
+    self.enums.iter().find(|e| e.name == name)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enum {
+  pub name: String,
+  pub values: Vec<String>,
+}
+
+/// A model maps to a table, its scalar fields to columns
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+  pub name: String,
+  pub fields: Vec<Field>,
+  /// The fields marked `@id`, or listed in `@@id`
+  pub primary_key: Vec<String>,
+  pub indexes: Vec<Index>,
+}
+
+impl Model {
+  pub fn field(&self, name: &str) -> Option<&Field> {
+    self.fields.iter().find(|field| field.name == name)
+  }
+
+  /// The fields stored as columns, leaving out relation fields
+  pub fn columns(&self) -> impl Iterator<Item = &Field> {
+    self.fields.iter().filter(|field| !field.is_relation())
+  }
+
+  /// The foreign keys declared by the `@relation` attributes of the model
+  pub fn foreign_keys(&self) -> Vec<ForeignKey> {
+    self
+      .fields
+      .iter()
+      .filter_map(|field| match (&field.ty, &field.relation) {
+        (FieldType::Model(target), Some(relation)) if !relation.fields.is_empty() => {
+          let optional = relation
+            .fields
+            .iter()
+            .any(|name| self.field(name).is_some_and(|f| f.arity == Arity::Optional));
+          Some(ForeignKey {
+            name: format!("{}_{}_fkey", self.name, relation.fields.join("_")),
+            columns: relation.fields.clone(),
+            referenced_table: target.clone(),
+            referenced_columns: relation.references.clone(),
+            on_delete: relation.on_delete.unwrap_or(if optional {
+              ReferentialAction::SetNull
+            } else {
+              ReferentialAction::Restrict
+            }),
+          })
+        }
+        _ => None,
+      })
+      .collect()
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+  pub name: String,
+  pub ty: FieldType,
+  pub arity: Arity,
+  pub default: Option<DefaultValue>,
+  pub relation: Option<Relation>,
+}
+
+impl Field {
+  /// Whether the field points at another model instead of holding a column
+  pub fn is_relation(&self) -> bool {
+    matches!(self.ty, FieldType::Model(_))
+  }
+
+  pub fn is_autoincrement(&self) -> bool {
+    self.default == Some(DefaultValue::Autoincrement)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+  Int,
+  BigInt,
+  Float,
+  Decimal,
+  String,
+  Boolean,
+  DateTime,
+  Json,
+  Bytes,
+  Enum(String),
+  Model(String),
+}
+
+impl FieldType {
+  /// The name the type is written with in a schema file
+  pub fn name(&self) -> &str {
+    match self {
+      FieldType::Int => "Int",
+      FieldType::BigInt => "BigInt",
+      FieldType::Float => "Float",
+      FieldType::Decimal => "Decimal",
+      FieldType::String => "String",
+      FieldType::Boolean => "Boolean",
+      FieldType::DateTime => "DateTime",
+      FieldType::Json => "Json",
+      FieldType::Bytes => "Bytes",
+      FieldType::Enum(name) | FieldType::Model(name) => name,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+  Required,
+  /// Written `Type?`, the column accepts `NULL`
+  Optional,
+  /// Written `Type[]`, only allowed for relation fields
+  List,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefaultValue {
+  /// `autoincrement()`
+  Autoincrement,
+  /// `now()`
+  Now,
+  Int(i64),
+  /// Kept as written to avoid float rounding in the generated SQL
+  Float(String),
+  String(String),
+  Boolean(bool),
+  EnumValue(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relation {
+  /// The columns of this model holding the key, empty on the side without the foreign key
+  pub fields: Vec<String>,
+  pub references: Vec<String>,
+  pub on_delete: Option<ReferentialAction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReferentialAction {
+  Cascade,
+  Restrict,
+  NoAction,
+  SetNull,
+  SetDefault,
+}
+
+impl ReferentialAction {
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "Cascade" => Some(ReferentialAction::Cascade),
+      "Restrict" => Some(ReferentialAction::Restrict),
+      "NoAction" => Some(ReferentialAction::NoAction),
+      "SetNull" => Some(ReferentialAction::SetNull),
+      "SetDefault" => Some(ReferentialAction::SetDefault),
+      _ => None,
+    }
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      ReferentialAction::Cascade => "Cascade",
+      ReferentialAction::Restrict => "Restrict",
+      ReferentialAction::NoAction => "NoAction",
+      ReferentialAction::SetNull => "SetNull",
+      ReferentialAction::SetDefault => "SetDefault",
+    }
+  }
+
+  pub fn sql(self) -> &'static str {
+    match self {
+      ReferentialAction::Cascade => "CASCADE",
+      ReferentialAction::Restrict => "RESTRICT",
+      ReferentialAction::NoAction => "NO ACTION",
+      ReferentialAction::SetNull => "SET NULL",
+      ReferentialAction::SetDefault => "SET DEFAULT",
+    }
+  }
+}
+
+/// An `@unique` field, or an `@@index` or `@@unique` attribute
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index {
+  pub columns: Vec<String>,
+  pub unique: bool,
+}
+
+impl Index {
+  pub fn name(&self, table: &str) -> String {
+    let suffix = if self.unique { "key" } else { "idx" };
+    format!("{}_{}_{}", table, self.columns.join("_"), suffix)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+  pub name: String,
+  pub columns: Vec<String>,
+  pub referenced_table: String,
+  pub referenced_columns: Vec<String>,
+  pub on_delete: ReferentialAction,
+}
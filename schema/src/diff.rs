@@ -0,0 +1,439 @@
+use crate::ast::*;
+use migration::Dialect;
+use std::fmt;
+
+/// One step taking the database from a schema to the next
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Change {
+  CreateEnum(Enum),
+  DropEnum(Enum),
+  /// Postgres enums are recreated, converting the columns using them
+  AlterEnum {
+    from: Enum,
+    to: Enum,
+    /// The tables and fields of the columns typed with the enum
+    columns: Vec<(String, Field)>,
+  },
+  CreateTable(Model),
+  DropTable(Model),
+  /// SQLite cannot alter columns or constraints, the table is copied into a new one
+  RebuildTable {
+    from: Model,
+    to: Model,
+  },
+  AddColumn {
+    table: String,
+    field: Field,
+  },
+  DropColumn {
+    table: String,
+    field: Field,
+  },
+  AlterColumn {
+    table: String,
+    from: Field,
+    to: Field,
+  },
+  AlterPrimaryKey {
+    table: String,
+    columns: Vec<String>,
+  },
+  CreateIndex {
+    table: String,
+    index: Index,
+  },
+  DropIndex {
+    table: String,
+    index: Index,
+  },
+  AddForeignKey {
+    table: String,
+    key: ForeignKey,
+  },
+  DropForeignKey {
+    table: String,
+    key: ForeignKey,
+  },
+}
+
+impl Change {
+  /// Whether applying the change loses data already stored
+  pub fn is_destructive(&self) -> bool {
+    match self {
+      Change::DropTable(_) | Change::DropColumn { .. } => true,
+      Change::AlterColumn { from, to, .. } => from.ty != to.ty,
+      Change::AlterEnum { from, to, .. } => from.values.iter().any(|v| !to.values.contains(v)),
+      Change::RebuildTable { from, to } => from
+        .columns()
+        .any(|old| to.field(&old.name).is_none_or(|new| new.ty != old.ty)),
+      _ => false,
+    }
+  }
+
+  /// The order changes run in: constraints are dropped before the tables and
+  /// columns they use change, and added once everything they refer to exists
+  fn rank(&self) -> u8 {
+    match self {
+      Change::CreateEnum(_) | Change::AlterEnum { .. } => 0,
+      Change::DropForeignKey { .. } => 1,
+      Change::DropIndex { .. } => 2,
+      Change::CreateTable(_) => 3,
+      Change::RebuildTable { .. }
+      | Change::AddColumn { .. }
+      | Change::DropColumn { .. }
+      | Change::AlterColumn { .. }
+      | Change::AlterPrimaryKey { .. } => 4,
+      Change::DropTable(_) => 5,
+      Change::CreateIndex { .. } => 6,
+      Change::AddForeignKey { .. } => 7,
+      Change::DropEnum(_) => 8,
+    }
+  }
+}
+
+impl fmt::Display for Change {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Change::CreateEnum(e) => write!(f, "create the enum `{}`", e.name),
+      Change::DropEnum(e) => write!(f, "drop the enum `{}`", e.name),
+      Change::AlterEnum { from, to, .. } => {
+        let removed: Vec<&str> = from
+          .values
+          .iter()
+          .filter(|value| !to.values.contains(value))
+          .map(String::as_str)
+          .collect();
+        if removed.is_empty() {
+          write!(f, "alter the enum `{}`", to.name)
+        } else {
+          write!(
+            f,
+            "remove {} from the enum `{}`",
+            removed.join(", "),
+            to.name
+          )
+        }
+      }
+      Change::CreateTable(model) => write!(f, "create the table `{}`", model.name),
+      Change::DropTable(model) => write!(f, "drop the table `{}`", model.name),
+      Change::RebuildTable { to, .. } => write!(f, "rebuild the table `{}`", to.name),
+      Change::AddColumn { table, field } => write!(f, "add the column `{}.{}`", table, field.name),
+      Change::DropColumn { table, field } => {
+        write!(f, "drop the column `{}.{}`", table, field.name)
+      }
+      Change::AlterColumn { table, from, to } if from.ty != to.ty => write!(
+        f,
+        "change the type of `{}.{}` from {} to {}",
+        table,
+        to.name,
+        from.ty.name(),
+        to.ty.name()
+      ),
+      Change::AlterColumn { table, to, .. } => {
+        write!(f, "alter the column `{}.{}`", table, to.name)
+      }
+      Change::AlterPrimaryKey { table, .. } => write!(f, "change the primary key of `{}`", table),
+      Change::CreateIndex { table, index } => write!(f, "create the index `{}`", index.name(table)),
+      Change::DropIndex { table, index } => write!(f, "drop the index `{}`", index.name(table)),
+      Change::AddForeignKey { key, .. } => write!(f, "add the foreign key `{}`", key.name),
+      Change::DropForeignKey { key, .. } => write!(f, "drop the foreign key `{}`", key.name),
+    }
+  }
+}
+
+/// The changes turning the tables of `from` into the tables of `to`
+pub fn diff(from: &Schema, to: &Schema, dialect: Dialect) -> Vec<Change> {
+  let mut changes = Vec::new();
+
+  // only Postgres has enum types, the others inline the values in the column type
+  if dialect == Dialect::Postgres {
+    for e in to.enums.iter() {
+      match from.find_enum(&e.name) {
+        None => changes.push(Change::CreateEnum(e.clone())),
+        Some(old) if old.values != e.values => changes.push(Change::AlterEnum {
+          from: old.clone(),
+          to: e.clone(),
+          columns: enum_columns(from, &e.name),
+        }),
+        Some(_) => {}
+      }
+    }
+    for e in from.enums.iter() {
+      if to.find_enum(&e.name).is_none() {
+        changes.push(Change::DropEnum(e.clone()));
+      }
+    }
+  }
+
+  for model in from.models.iter() {
+    if to.model(&model.name).is_none() {
+      if dialect != Dialect::SQLite {
+        for key in model.foreign_keys() {
+          changes.push(Change::DropForeignKey {
+            table: model.name.clone(),
+            key,
+          });
+        }
+      }
+      changes.push(Change::DropTable(model.clone()));
+    }
+  }
+
+  for model in to.models.iter() {
+    match from.model(&model.name) {
+      None => create_table(&mut changes, model, dialect),
+      Some(old) => alter_table(&mut changes, old, model, from, to, dialect),
+    }
+  }
+
+  changes.sort_by_key(Change::rank);
+  changes
+}
+
+fn enum_columns(schema: &Schema, name: &str) -> Vec<(String, Field)> {
+  schema
+    .models
+    .iter()
+    .flat_map(|model| {
+      model
+        .columns()
+        .filter(|field| field.ty == FieldType::Enum(name.to_string()))
+        .map(move |field| (model.name.clone(), field.clone()))
+    })
+    .collect()
+}
+
+fn create_table(changes: &mut Vec<Change>, model: &Model, dialect: Dialect) {
+  changes.push(Change::CreateTable(model.clone()));
+  for index in model.indexes.iter() {
+    changes.push(Change::CreateIndex {
+      table: model.name.clone(),
+      index: index.clone(),
+    });
+  }
+  // SQLite declares foreign keys along with the table
+  if dialect != Dialect::SQLite {
+    for key in model.foreign_keys() {
+      changes.push(Change::AddForeignKey {
+        table: model.name.clone(),
+        key,
+      });
+    }
+  }
+}
+
+fn alter_table(
+  changes: &mut Vec<Change>,
+  old: &Model,
+  new: &Model,
+  from: &Schema,
+  to: &Schema,
+  dialect: Dialect,
+) {
+  let table = &new.name;
+  let mut columns = Vec::new();
+  for field in old.columns() {
+    match new.field(&field.name).filter(|f| !f.is_relation()) {
+      None => columns.push(Change::DropColumn {
+        table: table.clone(),
+        field: field.clone(),
+      }),
+      Some(next) if column_changed(field, next, from, to, dialect) => {
+        columns.push(Change::AlterColumn {
+          table: table.clone(),
+          from: field.clone(),
+          to: next.clone(),
+        })
+      }
+      Some(_) => {}
+    }
+  }
+  for field in new.columns() {
+    if old
+      .field(&field.name)
+      .filter(|f| !f.is_relation())
+      .is_none()
+    {
+      columns.push(Change::AddColumn {
+        table: table.clone(),
+        field: field.clone(),
+      });
+    }
+  }
+  if old.primary_key != new.primary_key {
+    columns.push(Change::AlterPrimaryKey {
+      table: table.clone(),
+      columns: new.primary_key.clone(),
+    });
+  }
+
+  let old_keys = old.foreign_keys();
+  let new_keys = new.foreign_keys();
+  let dropped_keys: Vec<_> = old_keys.iter().filter(|k| !new_keys.contains(k)).collect();
+  let added_keys: Vec<_> = new_keys.iter().filter(|k| !old_keys.contains(k)).collect();
+
+  if dialect == Dialect::SQLite {
+    let rebuild = !dropped_keys.is_empty()
+      || !added_keys.is_empty()
+      || columns.iter().any(|change| match change {
+        // a column can only be added when existing rows have a value for it
+        Change::AddColumn { field, .. } => {
+          (field.arity == Arity::Required && field.default.is_none())
+            || field.default == Some(DefaultValue::Now)
+        }
+        _ => true,
+      });
+    if rebuild {
+      changes.push(Change::RebuildTable {
+        from: old.clone(),
+        to: new.clone(),
+      });
+      return;
+    }
+  }
+
+  changes.extend(columns);
+  for key in dropped_keys {
+    changes.push(Change::DropForeignKey {
+      table: table.clone(),
+      key: key.clone(),
+    });
+  }
+  for key in added_keys {
+    changes.push(Change::AddForeignKey {
+      table: table.clone(),
+      key: key.clone(),
+    });
+  }
+  for index in old.indexes.iter().filter(|i| !new.indexes.contains(i)) {
+    changes.push(Change::DropIndex {
+      table: table.clone(),
+      index: index.clone(),
+    });
+  }
+  for index in new.indexes.iter().filter(|i| !old.indexes.contains(i)) {
+    changes.push(Change::CreateIndex {
+      table: table.clone(),
+      index: index.clone(),
+    });
+  }
+}
+
+fn column_changed(old: &Field, new: &Field, from: &Schema, to: &Schema, dialect: Dialect) -> bool {
+  old.arity != new.arity
+    || !same_type(old, new, from, to, dialect)
+    || !same_default(old, new, dialect)
+}
+
+/// Whether the columns are stored with the same type. SQLite stores enums as
+/// plain text, and MySQL spells the values out in the column type, so a
+/// database pulled back names its enums after the table and column.
+fn same_type(old: &Field, new: &Field, from: &Schema, to: &Schema, dialect: Dialect) -> bool {
+  match (dialect, &old.ty, &new.ty) {
+    (Dialect::MySQL, FieldType::Enum(a), FieldType::Enum(b)) => {
+      from.find_enum(a).map(|e| &e.values) == to.find_enum(b).map(|e| &e.values)
+    }
+    (Dialect::SQLite, FieldType::Enum(_), FieldType::Enum(_) | FieldType::String)
+    | (Dialect::SQLite, FieldType::String, FieldType::Enum(_)) => true,
+    (_, a, b) => a == b,
+  }
+}
+
+fn same_default(old: &Field, new: &Field, dialect: Dialect) -> bool {
+  match (dialect, &old.default, &new.default) {
+    // a text column pulled back from SQLite has a string default
+    (
+      Dialect::SQLite,
+      Some(DefaultValue::String(a) | DefaultValue::EnumValue(a)),
+      Some(DefaultValue::String(b) | DefaultValue::EnumValue(b)),
+    ) => a == b,
+    (_, a, b) => a == b,
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::parse;
+
+  fn changes(from: &str, to: &str, dialect: Dialect) -> Vec<Change> {
+    diff(&parse(from).unwrap(), &parse(to).unwrap(), dialect)
+  }
+
+  const USER: &str = "model User {\n  id Int @id\n  name String\n}";
+
+  #[test]
+  fn test_diff_identical() {
+    assert!(changes(USER, USER, Dialect::Postgres).is_empty());
+  }
+
+  #[test]
+  fn test_diff_columns() {
+    let next = "model User {\n  id Int @id\n  name String?\n  age Int @default(0)\n}";
+    let postgres = changes(USER, next, Dialect::Postgres);
+    assert!(matches!(
+      postgres.as_slice(),
+      [Change::AlterColumn { .. }, Change::AddColumn { .. }]
+    ));
+    assert!(!postgres.iter().any(Change::is_destructive));
+
+    let sqlite = changes(USER, next, Dialect::SQLite);
+    assert!(matches!(sqlite.as_slice(), [Change::RebuildTable { .. }]));
+
+    let dropped = changes(next, USER, Dialect::MySQL);
+    assert!(dropped.iter().any(Change::is_destructive));
+    assert_eq!(dropped[1].to_string(), "drop the column `User.age`");
+  }
+
+  #[test]
+  fn test_diff_order() {
+    let next = "model User {\n  id Int @id\n  name String\n  posts Post[]\n}\n\
+                model Post {\n  id Int @id\n  authorId Int\n  \
+                author User @relation(fields: [authorId], references: [id])\n  @@index([authorId])\n}";
+    let created = changes(USER, next, Dialect::Postgres);
+    assert!(matches!(
+      created.as_slice(),
+      [
+        Change::CreateTable(_),
+        Change::CreateIndex { .. },
+        Change::AddForeignKey { .. }
+      ]
+    ));
+
+    let dropped = changes(next, "", Dialect::Postgres);
+    assert!(matches!(
+      dropped.as_slice(),
+      [
+        Change::DropForeignKey { .. },
+        Change::DropTable(_),
+        Change::DropTable(_)
+      ]
+    ));
+  }
+
+  #[test]
+  fn test_diff_enums() {
+    let from = "enum Role {\n  USER\n  ADMIN\n}\nmodel User {\n  id Int @id\n  role Role\n}";
+    let to = "enum Role {\n  USER\n}\nmodel User {\n  id Int @id\n  role Role\n}";
+    match changes(from, to, Dialect::Postgres).as_slice() {
+      [change @ Change::AlterEnum { columns, .. }] => {
+        assert_eq!(columns[0].0, "User");
+        assert!(change.is_destructive());
+      }
+      other => panic!("unexpected changes {:?}", other),
+    }
+    assert!(matches!(
+      changes(from, to, Dialect::MySQL).as_slice(),
+      [Change::AlterColumn { .. }]
+    ));
+    assert!(changes(from, to, Dialect::SQLite).is_empty());
+
+    // what `db pull` reads back is in sync with the schema it was pushed from
+    let mysql = "enum UserRole {\n  USER\n}\nmodel User {\n  id Int @id\n  role UserRole\n}";
+    assert!(changes(mysql, to, Dialect::MySQL).is_empty());
+    let sqlite = "model User {\n  id Int @id\n  role String @default(\"USER\")\n}";
+    let to = "enum Role {\n  USER\n}\nmodel User {\n  id Int @id\n  role Role @default(USER)\n}";
+    assert!(changes(sqlite, to, Dialect::SQLite).is_empty());
+  }
+}
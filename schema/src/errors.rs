@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+use std::{error, fmt, io};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+  /// The schema file could not be read.
+  IoError(PathBuf, io::Error),
+  /// A line of the schema could not be parsed.
+  ParseError { line: usize, message: String },
+  /// The schema parsed but refers to something it does not declare.
+  InvalidSchema(String),
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Error::IoError(ref path, ref err) => write!(f, "{}: {}", path.display(), err),
+      Error::ParseError { line, ref message } => {
+        write!(f, "Invalid schema at line {}: {}", line, message)
+      }
+      Error::InvalidSchema(ref message) => write!(f, "Invalid schema: {}", message),
+    }
+  }
+}
+
+impl error::Error for Error {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match self {
+      Error::IoError(_, err) => Some(err),
+      _ => None,
+    }
+  }
+}
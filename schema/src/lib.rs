@@ -0,0 +1,28 @@
+extern crate connection;
+extern crate migration;
+
+mod ast;
+mod diff;
+mod errors;
+mod parser;
+mod printer;
+mod pull;
+mod sql;
+
+pub use self::ast::*;
+pub use self::diff::{diff, Change};
+pub use self::errors::Error;
+pub use self::parser::parse;
+pub use self::pull::{pull, Pulled};
+pub use self::sql::{own_transaction, statements, statements_against};
+
+use std::fs;
+use std::path::Path;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Read and parse a schema file
+pub fn read(path: &Path) -> Result<Schema> {
+  let source = fs::read_to_string(path).map_err(|e| Error::IoError(path.to_path_buf(), e))?;
+  parse(&source)
+}
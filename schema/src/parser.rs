@@ -0,0 +1,648 @@
+use crate::ast::*;
+use crate::{Error, Result};
+
+/// Parse a schema file made of `model` and `enum` blocks.
+///
+/// ```text
+/// enum Role {
+///   USER
+///   ADMIN
+/// }
+///
+/// model User {
+///   id    Int    @id @default(autoincrement())
+///   email String @unique
+///   role  Role   @default(USER)
+///   posts Post[]
+/// }
+/// ```
+pub fn parse(source: &str) -> Result<Schema> {
+  let mut schema = Schema::default();
+  let mut block: Option<Block> = None;
+  let mut last_line = 0;
+
+  for (index, line) in source.lines().enumerate() {
+    let number = index + 1;
+    last_line = number;
+    let tokens = tokenize(line, number)?;
+    if tokens.is_empty() {
+      continue;
+    }
+    let mut cursor = Cursor {
+      tokens: &tokens,
+      position: 0,
+      line: number,
+    };
+
+    block = match block.take() {
+      None => Some(cursor.block_start()?),
+      Some(block) if tokens == [Token::Symbol('}')] => {
+        match block {
+          Block::Enum(e) => schema.enums.push(e),
+          Block::Model(model) => schema.models.push(model),
+        }
+        None
+      }
+      Some(Block::Enum(mut e)) => {
+        e.values.push(cursor.ident()?);
+        cursor.end()?;
+        Some(Block::Enum(e))
+      }
+      Some(Block::Model(mut model)) => {
+        if cursor.peek() == Some(&Token::AtAt) {
+          cursor.block_attribute(&mut model)?;
+        } else {
+          cursor.field(&mut model)?;
+        }
+        Some(Block::Model(model))
+      }
+    };
+  }
+
+  if block.is_some() {
+    return Err(Error::ParseError {
+      line: last_line,
+      message: "the block is missing its closing `}`".into(),
+    });
+  }
+  resolve(schema)
+}
+
+enum Block {
+  Model(Model),
+  Enum(Enum),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Ident(String),
+  Str(String),
+  Number(String),
+  At,
+  AtAt,
+  Symbol(char),
+}
+
+fn tokenize(line: &str, number: usize) -> Result<Vec<Token>> {
+  let mut tokens = Vec::new();
+  let mut chars = line.chars().peekable();
+  while let Some(&c) = chars.peek() {
+    if c.is_whitespace() {
+      chars.next();
+    } else if c == '/' {
+      chars.next();
+      if chars.next() != Some('/') {
+        return Err(unexpected(number, '/'));
+      }
+      break;
+    } else if c == '@' {
+      chars.next();
+      if chars.peek() == Some(&'@') {
+        chars.next();
+        tokens.push(Token::AtAt);
+      } else {
+        tokens.push(Token::At);
+      }
+    } else if c.is_alphabetic() || c == '_' {
+      let mut ident = String::new();
+      while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+        ident.push(c);
+        chars.next();
+      }
+      tokens.push(Token::Ident(ident));
+    } else if c.is_ascii_digit() || c == '-' {
+      let mut value = String::new();
+      value.push(c);
+      chars.next();
+      while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+        value.push(c);
+        chars.next();
+      }
+      tokens.push(Token::Number(value));
+    } else if c == '"' {
+      chars.next();
+      let mut value = String::new();
+      loop {
+        match chars.next() {
+          Some('"') => break,
+          Some('\\') => value.extend(chars.next()),
+          Some(c) => value.push(c),
+          None => {
+            return Err(Error::ParseError {
+              line: number,
+              message: "unterminated string".into(),
+            })
+          }
+        }
+      }
+      tokens.push(Token::Str(value));
+    } else if "{}()[],:?".contains(c) {
+      chars.next();
+      tokens.push(Token::Symbol(c));
+    } else {
+      return Err(unexpected(number, c));
+    }
+  }
+  Ok(tokens)
+}
+
+fn unexpected(line: usize, c: char) -> Error {
+  Error::ParseError {
+    line,
+    message: format!("unexpected character `{}`", c),
+  }
+}
+
+/// An attribute argument
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+  Ident(String),
+  Str(String),
+  Number(String),
+  /// A function without arguments such as `now()`
+  Call(String),
+  List(Vec<Expr>),
+}
+
+struct Cursor<'a> {
+  tokens: &'a [Token],
+  position: usize,
+  line: usize,
+}
+
+impl<'a> Cursor<'a> {
+  fn error<T>(&self, message: impl Into<String>) -> Result<T> {
+    Err(Error::ParseError {
+      line: self.line,
+      message: message.into(),
+    })
+  }
+
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.position)
+  }
+
+  fn next(&mut self) -> Option<&Token> {
+    let token = self.tokens.get(self.position);
+    self.position += 1;
+    token
+  }
+
+  fn eat(&mut self, symbol: char) -> bool {
+    if self.peek() == Some(&Token::Symbol(symbol)) {
+      self.position += 1;
+      true
+    } else {
+      false
+    }
+  }
+
+  fn expect(&mut self, symbol: char) -> Result<()> {
+    if self.eat(symbol) {
+      Ok(())
+    } else {
+      self.error(format!("expected `{}`", symbol))
+    }
+  }
+
+  fn ident(&mut self) -> Result<String> {
+    match self.next() {
+      Some(Token::Ident(ident)) => Ok(ident.clone()),
+      _ => self.error("expected a name"),
+    }
+  }
+
+  fn end(&self) -> Result<()> {
+    match self.peek() {
+      None => Ok(()),
+      Some(_) => self.error("unexpected text at the end of the line"),
+    }
+  }
+
+  /// `model Name {` or `enum Name {`
+  fn block_start(&mut self) -> Result<Block> {
+    let keyword = self.ident()?;
+    let name = self.ident()?;
+    self.expect('{')?;
+    self.end()?;
+    match keyword.as_str() {
+      "model" => Ok(Block::Model(Model {
+        name,
+        fields: Vec::new(),
+        primary_key: Vec::new(),
+        indexes: Vec::new(),
+      })),
+      "enum" => Ok(Block::Enum(Enum {
+        name,
+        values: Vec::new(),
+      })),
+      _ => self.error(format!(
+        "expected a `model` or `enum` block, found `{}`",
+        keyword
+      )),
+    }
+  }
+
+  /// `name Type? @attribute(...)`
+  fn field(&mut self, model: &mut Model) -> Result<()> {
+    let name = self.ident()?;
+    let ty = self.ident()?;
+    let arity = if self.eat('?') {
+      Arity::Optional
+    } else if self.eat('[') {
+      self.expect(']')?;
+      Arity::List
+    } else {
+      Arity::Required
+    };
+    let mut field = Field {
+      ty: builtin(&ty).unwrap_or(FieldType::Model(ty)),
+      name,
+      arity,
+      default: None,
+      relation: None,
+    };
+
+    while self.peek().is_some() {
+      if self.next() != Some(&Token::At) {
+        return self.error("expected an `@` attribute");
+      }
+      let attribute = self.ident()?;
+      let args = self.args()?;
+      match attribute.as_str() {
+        "id" => model.primary_key.push(field.name.clone()),
+        "unique" => model.indexes.push(Index {
+          columns: vec![field.name.clone()],
+          unique: true,
+        }),
+        "default" => field.default = Some(self.default_value(&args)?),
+        "relation" => field.relation = Some(self.relation(&args)?),
+        _ => return self.error(format!("unsupported attribute `@{}`", attribute)),
+      }
+    }
+    model.fields.push(field);
+    Ok(())
+  }
+
+  /// `@@id([a, b])`, `@@unique([a, b])` or `@@index([a, b])`
+  fn block_attribute(&mut self, model: &mut Model) -> Result<()> {
+    self.next();
+    let attribute = self.ident()?;
+    let args = self.args()?;
+    self.end()?;
+    let columns = match args.first() {
+      Some((None, Expr::List(items))) | Some((Some(_), Expr::List(items))) => self.names(items)?,
+      _ => return self.error(format!("`@@{}` expects a list of fields", attribute)),
+    };
+    match attribute.as_str() {
+      "id" => model.primary_key = columns,
+      "unique" => model.indexes.push(Index {
+        columns,
+        unique: true,
+      }),
+      "index" => model.indexes.push(Index {
+        columns,
+        unique: false,
+      }),
+      _ => return self.error(format!("unsupported attribute `@@{}`", attribute)),
+    }
+    Ok(())
+  }
+
+  /// The optional parenthesised arguments of an attribute, each with its name when given
+  fn args(&mut self) -> Result<Vec<(Option<String>, Expr)>> {
+    let mut args = Vec::new();
+    if !self.eat('(') {
+      return Ok(args);
+    }
+    while !self.eat(')') {
+      let named = match (self.peek(), self.tokens.get(self.position + 1)) {
+        (Some(Token::Ident(name)), Some(Token::Symbol(':'))) => Some(name.clone()),
+        _ => None,
+      };
+      if named.is_some() {
+        self.position += 2;
+      }
+      args.push((named, self.expr()?));
+      if !self.eat(',') && self.peek() != Some(&Token::Symbol(')')) {
+        return self.error("expected `,` or `)`");
+      }
+    }
+    Ok(args)
+  }
+
+  fn expr(&mut self) -> Result<Expr> {
+    match self.next().cloned() {
+      Some(Token::Ident(name)) => {
+        if self.eat('(') {
+          self.expect(')')?;
+          Ok(Expr::Call(name))
+        } else {
+          Ok(Expr::Ident(name))
+        }
+      }
+      Some(Token::Str(value)) => Ok(Expr::Str(value)),
+      Some(Token::Number(value)) => Ok(Expr::Number(value)),
+      Some(Token::Symbol('[')) => {
+        let mut items = Vec::new();
+        while !self.eat(']') {
+          items.push(self.expr()?);
+          if !self.eat(',') && self.peek() != Some(&Token::Symbol(']')) {
+            return self.error("expected `,` or `]`");
+          }
+        }
+        Ok(Expr::List(items))
+      }
+      _ => self.error("expected a value"),
+    }
+  }
+
+  fn names(&self, items: &[Expr]) -> Result<Vec<String>> {
+    items
+      .iter()
+      .map(|item| match item {
+        Expr::Ident(name) => Ok(name.clone()),
+        _ => self.error("expected a field name"),
+      })
+      .collect()
+  }
+
+  fn default_value(&self, args: &[(Option<String>, Expr)]) -> Result<DefaultValue> {
+    match args {
+      [(None, value)] => match value {
+        Expr::Call(name) if name == "autoincrement" => Ok(DefaultValue::Autoincrement),
+        Expr::Call(name) if name == "now" => Ok(DefaultValue::Now),
+        Expr::Call(name) => self.error(format!("unsupported default function `{}()`", name)),
+        Expr::Ident(name) if name == "true" || name == "false" => {
+          Ok(DefaultValue::Boolean(name == "true"))
+        }
+        Expr::Ident(name) => Ok(DefaultValue::EnumValue(name.clone())),
+        Expr::Str(value) => Ok(DefaultValue::String(value.clone())),
+        Expr::Number(value) => match value.parse::<i64>() {
+          Ok(value) => Ok(DefaultValue::Int(value)),
+          Err(_) if value.parse::<f64>().is_ok() => Ok(DefaultValue::Float(value.clone())),
+          Err(_) => self.error(format!("invalid number `{}`", value)),
+        },
+        Expr::List(_) => self.error("list defaults are not supported"),
+      },
+      _ => self.error("`@default` expects a single value"),
+    }
+  }
+
+  fn relation(&self, args: &[(Option<String>, Expr)]) -> Result<Relation> {
+    let mut relation = Relation {
+      fields: Vec::new(),
+      references: Vec::new(),
+      on_delete: None,
+    };
+    for (name, value) in args.iter() {
+      match (name.as_deref(), value) {
+        // the relation name only disambiguates, nothing is generated for it
+        (None, Expr::Str(_)) | (Some("name"), Expr::Str(_)) => {}
+        (Some("fields"), Expr::List(items)) => relation.fields = self.names(items)?,
+        (Some("references"), Expr::List(items)) => relation.references = self.names(items)?,
+        (Some("onDelete"), Expr::Ident(action)) => {
+          relation.on_delete = match ReferentialAction::from_name(action) {
+            Some(action) => Some(action),
+            None => return self.error(format!("unknown referential action `{}`", action)),
+          }
+        }
+        _ => return self.error("invalid `@relation` argument"),
+      }
+    }
+    Ok(relation)
+  }
+}
+
+fn builtin(name: &str) -> Option<FieldType> {
+  match name {
+    "Int" => Some(FieldType::Int),
+    "BigInt" => Some(FieldType::BigInt),
+    "Float" => Some(FieldType::Float),
+    "Decimal" => Some(FieldType::Decimal),
+    "String" => Some(FieldType::String),
+    "Boolean" => Some(FieldType::Boolean),
+    "DateTime" => Some(FieldType::DateTime),
+    "Json" => Some(FieldType::Json),
+    "Bytes" => Some(FieldType::Bytes),
+    _ => None,
+  }
+}
+
+/// Tell enum types from model types and check every name the schema refers to
+fn resolve(mut schema: Schema) -> Result<Schema> {
+  let enums = schema.enums.clone();
+  let models = schema.models.clone();
+  let invalid = |message: String| Err(Error::InvalidSchema(message));
+
+  for (i, e) in enums.iter().enumerate() {
+    if enums[..i].iter().any(|other| other.name == e.name) {
+      return invalid(format!("the enum `{}` is declared twice", e.name));
+    }
+    if e.values.is_empty() {
+      return invalid(format!("the enum `{}` has no values", e.name));
+    }
+  }
+
+  for (i, model) in schema.models.iter_mut().enumerate() {
+    if models[..i].iter().any(|other| other.name == model.name)
+      || enums.iter().any(|e| e.name == model.name)
+    {
+      return invalid(format!("`{}` is declared twice", model.name));
+    }
+
+    for field in model.fields.iter_mut() {
+      if let FieldType::Model(ref name) = field.ty {
+        if let Some(e) = enums.iter().find(|e| &e.name == name) {
+          field.ty = FieldType::Enum(e.name.clone());
+        } else if !models.iter().any(|m| &m.name == name) {
+          return invalid(format!(
+            "unknown type `{}` of `{}.{}`",
+            name, model.name, field.name
+          ));
+        }
+      }
+    }
+
+    let scalar = |name: &String| model.field(name).is_some_and(|f| !f.is_relation());
+    for (j, field) in model.fields.iter().enumerate() {
+      let at = format!("{}.{}", model.name, field.name);
+      if model.fields[..j]
+        .iter()
+        .any(|other| other.name == field.name)
+      {
+        return invalid(format!("the field `{}` is declared twice", at));
+      }
+      if field.arity == Arity::List && !field.is_relation() {
+        return invalid(format!("`{}`: lists are only supported for relations", at));
+      }
+      match (&field.default, &field.ty) {
+        (Some(DefaultValue::Autoincrement), FieldType::Int)
+        | (Some(DefaultValue::Autoincrement), FieldType::BigInt)
+        | (Some(DefaultValue::Now), FieldType::DateTime)
+        | (None, _) => {}
+        (Some(DefaultValue::EnumValue(value)), FieldType::Enum(name)) => {
+          if !enums
+            .iter()
+            .any(|e| &e.name == name && e.values.contains(value))
+          {
+            return invalid(format!(
+              "`{}` defaults to `{}`, which `{}` does not declare",
+              at, value, name
+            ));
+          }
+        }
+        (Some(DefaultValue::Autoincrement), _) | (Some(DefaultValue::Now), _) => {
+          return invalid(format!("`{}` has a default its type does not support", at));
+        }
+        (Some(_), _) => {}
+      }
+
+      if let (FieldType::Model(target), Some(relation)) = (&field.ty, &field.relation) {
+        let target = models.iter().find(|m| &m.name == target).unwrap();
+        if relation.fields.len() != relation.references.len() {
+          return invalid(format!(
+            "`{}`: `fields` and `references` must have the same length",
+            at
+          ));
+        }
+        if let Some(name) = relation.fields.iter().find(|name| !scalar(name)) {
+          return invalid(format!("`{}` refers to the unknown field `{}`", at, name));
+        }
+        if let Some(name) = relation
+          .references
+          .iter()
+          .find(|name| target.field(name).is_none_or(Field::is_relation))
+        {
+          return invalid(format!(
+            "`{}` references the unknown field `{}.{}`",
+            at, target.name, name
+          ));
+        }
+      }
+    }
+
+    if model.primary_key.is_empty() {
+      return invalid(format!(
+        "the model `{}` needs an `@id` field or an `@@id` attribute",
+        model.name
+      ));
+    }
+    let columns = model
+      .primary_key
+      .iter()
+      .chain(model.indexes.iter().flat_map(|i| i.columns.iter()));
+    for name in columns {
+      if !scalar(name) {
+        return invalid(format!(
+          "the model `{}` indexes the unknown field `{}`",
+          model.name, name
+        ));
+      }
+    }
+  }
+  Ok(schema)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use indoc::indoc;
+
+  const SCHEMA: &str = indoc!(
+    r#"
+    // the accounts of the application
+    enum Role {
+      USER
+      ADMIN
+    }
+
+    model User {
+      id        Int      @id @default(autoincrement())
+      email     String   @unique
+      name      String?
+      role      Role     @default(USER)
+      createdAt DateTime @default(now())
+      posts     Post[]
+
+      @@index([name])
+    }
+
+    model Post {
+      id       Int    @id @default(autoincrement())
+      title    String @default("untitled")
+      authorId Int
+      author   User   @relation(fields: [authorId], references: [id], onDelete: Cascade)
+
+      @@unique([title, authorId])
+    }
+  "#
+  );
+
+  #[test]
+  fn test_parse() {
+    let schema = parse(SCHEMA).unwrap();
+    assert_eq!(schema.enums[0].values, vec!["USER", "ADMIN"]);
+
+    let user = schema.model("User").unwrap();
+    assert_eq!(user.primary_key, vec!["id"]);
+    assert!(user.field("id").unwrap().is_autoincrement());
+    assert_eq!(user.field("name").unwrap().arity, Arity::Optional);
+    assert_eq!(
+      user.field("role").unwrap().ty,
+      FieldType::Enum("Role".into())
+    );
+    assert_eq!(
+      user.field("role").unwrap().default,
+      Some(DefaultValue::EnumValue("USER".into()))
+    );
+    assert_eq!(user.columns().count(), 5);
+    assert_eq!(
+      user.indexes,
+      vec![
+        Index {
+          columns: vec!["email".into()],
+          unique: true
+        },
+        Index {
+          columns: vec!["name".into()],
+          unique: false
+        }
+      ]
+    );
+
+    let post = schema.model("Post").unwrap();
+    assert_eq!(
+      post.field("title").unwrap().default,
+      Some(DefaultValue::String("untitled".into()))
+    );
+    let keys = post.foreign_keys();
+    assert_eq!(keys[0].name, "Post_authorId_fkey");
+    assert_eq!(keys[0].referenced_table, "User");
+    assert_eq!(keys[0].on_delete, ReferentialAction::Cascade);
+    assert_eq!(post.indexes[0].name("Post"), "Post_title_authorId_key");
+  }
+
+  #[test]
+  fn test_parse_errors() {
+    let error = parse("model User {\n  id Int @id @map(\"x\")\n}").unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "Invalid schema at line 2: unsupported attribute `@map`"
+    );
+    assert!(matches!(
+      parse("model User {\n  id Int @id\n"),
+      Err(Error::ParseError { line: 2, .. })
+    ));
+    assert_eq!(
+      parse("model User {\n  id Int @id\n  team Team\n}")
+        .unwrap_err()
+        .to_string(),
+      "Invalid schema: unknown type `Team` of `User.team`"
+    );
+    assert!(parse("model User {\n  name String\n}")
+      .unwrap_err()
+      .to_string()
+      .contains("needs an `@id` field"));
+  }
+}
@@ -0,0 +1,166 @@
+use crate::ast::*;
+use std::fmt;
+
+/// Print the schema back in the format [`parse`](crate::parse) reads, fields
+/// aligned in columns
+impl fmt::Display for Schema {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let mut blocks = Vec::new();
+    for e in self.enums.iter() {
+      let mut block = format!("enum {} {{\n", e.name);
+      for value in e.values.iter() {
+        block.push_str(&format!("  {}\n", value));
+      }
+      block.push('}');
+      blocks.push(block);
+    }
+    for model in self.models.iter() {
+      blocks.push(model_block(model));
+    }
+    for (i, block) in blocks.iter().enumerate() {
+      if i > 0 {
+        f.write_str("\n")?;
+      }
+      writeln!(f, "{}", block)?;
+    }
+    Ok(())
+  }
+}
+
+fn model_block(model: &Model) -> String {
+  let single_key = model.primary_key.len() == 1;
+  let unique_fields: Vec<&String> = model
+    .indexes
+    .iter()
+    .filter(|index| index.unique && index.columns.len() == 1)
+    .map(|index| &index.columns[0])
+    .collect();
+
+  let rows: Vec<(String, String, String)> = model
+    .fields
+    .iter()
+    .map(|field| {
+      let ty = match field.arity {
+        Arity::Required => field.ty.name().to_string(),
+        Arity::Optional => format!("{}?", field.ty.name()),
+        Arity::List => format!("{}[]", field.ty.name()),
+      };
+      let mut attributes = Vec::new();
+      if single_key && model.primary_key[0] == field.name {
+        attributes.push("@id".to_string());
+      }
+      if unique_fields.contains(&&field.name) {
+        attributes.push("@unique".to_string());
+      }
+      if let Some(ref default) = field.default {
+        attributes.push(format!("@default({})", default_value(default)));
+      }
+      if let Some(ref relation) = field.relation {
+        attributes.push(relation_attribute(relation));
+      }
+      (field.name.clone(), ty, attributes.join(" "))
+    })
+    .collect();
+
+  let name_width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+  let type_width = rows.iter().map(|row| row.1.len()).max().unwrap_or(0);
+  let mut block = format!("model {} {{\n", model.name);
+  for (name, ty, attributes) in rows.iter() {
+    let line = format!(
+      "  {:name_width$} {:type_width$} {}",
+      name,
+      ty,
+      attributes,
+      name_width = name_width,
+      type_width = type_width
+    );
+    block.push_str(line.trim_end());
+    block.push('\n');
+  }
+
+  let mut attributes = Vec::new();
+  if !single_key {
+    attributes.push(format!("@@id([{}])", model.primary_key.join(", ")));
+  }
+  for index in model.indexes.iter() {
+    if index.unique && index.columns.len() == 1 {
+      continue;
+    }
+    let kind = if index.unique { "unique" } else { "index" };
+    attributes.push(format!("@@{}([{}])", kind, index.columns.join(", ")));
+  }
+  if !attributes.is_empty() {
+    block.push('\n');
+    for attribute in attributes {
+      block.push_str(&format!("  {}\n", attribute));
+    }
+  }
+  block.push('}');
+  block
+}
+
+fn default_value(default: &DefaultValue) -> String {
+  match default {
+    DefaultValue::Autoincrement => "autoincrement()".to_string(),
+    DefaultValue::Now => "now()".to_string(),
+    DefaultValue::Int(value) => value.to_string(),
+    DefaultValue::Float(value) => value.clone(),
+    DefaultValue::String(value) => {
+      format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+    DefaultValue::Boolean(value) => value.to_string(),
+    DefaultValue::EnumValue(value) => value.clone(),
+  }
+}
+
+fn relation_attribute(relation: &Relation) -> String {
+  if relation.fields.is_empty() {
+    return "@relation".to_string();
+  }
+  let mut args = vec![
+    format!("fields: [{}]", relation.fields.join(", ")),
+    format!("references: [{}]", relation.references.join(", ")),
+  ];
+  if let Some(action) = relation.on_delete {
+    args.push(format!("onDelete: {}", action.name()));
+  }
+  format!("@relation({})", args.join(", "))
+}
+
+#[cfg(test)]
+mod test {
+  use crate::parse;
+  use indoc::indoc;
+
+  #[test]
+  fn test_print() {
+    let source = indoc!(
+      r#"
+      enum Role {
+        USER
+        ADMIN
+      }
+
+      model User {
+        id    Int     @id @default(autoincrement())
+        email String  @unique
+        name  String? @default("it's \"me\"")
+        role  Role    @default(USER)
+        posts Post[]
+      }
+
+      model Post {
+        slug     String
+        authorId Int
+        author   User   @relation(fields: [authorId], references: [id], onDelete: Cascade)
+
+        @@id([slug, authorId])
+        @@index([authorId])
+      }
+    "#
+    );
+    let schema = parse(source).unwrap();
+    assert_eq!(schema.to_string(), source);
+    assert_eq!(parse(&schema.to_string()).unwrap(), schema);
+  }
+}
@@ -0,0 +1,663 @@
+use crate::ast::*;
+use connection::{Column, DatabaseSchema, Table};
+use migration::{Dialect, HISTORY_TABLE, LOCK_TABLE};
+
+/// A schema described from an existing database
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pulled {
+  pub schema: Schema,
+  /// What the schema could not describe and was left out or approximated
+  pub warnings: Vec<String>,
+}
+
+/// Describe the tables of a database as a schema, inferring relations from
+/// the foreign keys.
+///
+/// Views, triggers, check constraints, partial indexes and columns of types
+/// the schema has no equivalent for are left out with a warning.
+pub fn pull(database: &DatabaseSchema, dialect: Dialect) -> Pulled {
+  let mut pull = Pull {
+    dialect,
+    schema: Schema::default(),
+    string_enums: Vec::new(),
+    warnings: Vec::new(),
+  };
+
+  for e in database.enums.iter() {
+    if is_ident(&e.name) && e.values.iter().all(|value| is_ident(value)) {
+      pull.schema.enums.push(Enum {
+        name: e.name.clone(),
+        values: e.values.clone(),
+      });
+    } else {
+      pull.warn(format!(
+        "the enum `{}` has a name or value that is not a valid identifier, its columns are described as String",
+        e.name
+      ));
+      pull.string_enums.push(e.name.clone());
+    }
+  }
+
+  let tables: Vec<&Table> = database
+    .tables
+    .iter()
+    .filter(|table| table.name != HISTORY_TABLE && table.name != LOCK_TABLE)
+    .collect();
+  for table in tables.iter() {
+    if let Some(model) = pull.model(table) {
+      pull.schema.models.push(model);
+    }
+  }
+  for table in tables.iter() {
+    pull.relations(table);
+  }
+
+  for view in database.views.iter() {
+    pull.warn(format!("the view `{}` was left out", view.name));
+  }
+  for trigger in database.triggers.iter() {
+    pull.warn(format!(
+      "the trigger `{}` on `{}` was left out",
+      trigger.name, trigger.table
+    ));
+  }
+  // sequences behind autoincrement columns are created along with them
+  for sequence in database.sequences.iter() {
+    let owned = tables.iter().flat_map(|t| t.columns.iter()).any(|column| {
+      column
+        .default
+        .as_deref()
+        .is_some_and(|default| default.contains(&format!("'{}'", sequence.name)))
+    });
+    if !owned {
+      pull.warn(format!("the sequence `{}` was left out", sequence.name));
+    }
+  }
+
+  Pulled {
+    schema: pull.schema,
+    warnings: pull.warnings,
+  }
+}
+
+struct Pull {
+  dialect: Dialect,
+  schema: Schema,
+  /// The Postgres enums the schema cannot describe
+  string_enums: Vec<String>,
+  warnings: Vec<String>,
+}
+
+impl Pull {
+  fn warn(&mut self, warning: String) {
+    self.warnings.push(warning);
+  }
+
+  fn model(&mut self, table: &Table) -> Option<Model> {
+    let name = &table.name;
+    if !is_ident(name) {
+      self.warn(format!(
+        "the table `{}` was left out, its name is not a valid identifier",
+        name
+      ));
+      return None;
+    }
+    let primary_key = match table.primary_key {
+      Some(ref key) => key.columns.clone(),
+      None => {
+        self.warn(format!(
+          "the table `{}` was left out, it has no primary key",
+          name
+        ));
+        return None;
+      }
+    };
+
+    let mut fields = Vec::new();
+    for column in table.columns.iter() {
+      match self.field(table, column) {
+        Some(field) => fields.push(field),
+        None if primary_key.contains(&column.name) => {
+          self.warn(format!(
+            "the table `{}` was left out, its primary key column `{}` cannot be described",
+            name, column.name
+          ));
+          return None;
+        }
+        None => {}
+      }
+    }
+    let has = |columns: &[String]| columns.iter().all(|c| fields.iter().any(|f| &f.name == c));
+
+    let mut indexes = Vec::new();
+    for unique in table.unique_constraints.iter() {
+      if has(&unique.columns) {
+        indexes.push(Index {
+          columns: unique.columns.clone(),
+          unique: true,
+        });
+      }
+    }
+    for index in table.indexes.iter() {
+      if index.predicate.is_some() {
+        self.warn(format!(
+          "the partial index `{}` on `{}` was left out",
+          index.name, name
+        ));
+      } else if index.expressions || !has(&index.columns) {
+        self.warn(format!(
+          "the index `{}` on `{}` was left out, it covers expressions or columns left out",
+          index.name, name
+        ));
+      } else {
+        indexes.push(Index {
+          columns: index.columns.clone(),
+          unique: index.unique,
+        });
+      }
+    }
+    for check in table.checks.iter() {
+      self.warn(format!(
+        "the check constraint {}on `{}` was left out: {}",
+        check
+          .name
+          .as_ref()
+          .map(|name| format!("`{}` ", name))
+          .unwrap_or_default(),
+        name,
+        check.expression
+      ));
+    }
+
+    Some(Model {
+      name: name.clone(),
+      fields,
+      primary_key,
+      indexes,
+    })
+  }
+
+  fn field(&mut self, table: &Table, column: &Column) -> Option<Field> {
+    let at = format!("{}.{}", table.name, column.name);
+    if !is_ident(&column.name) {
+      self.warn(format!(
+        "the column `{}` was left out, its name is not a valid identifier",
+        at
+      ));
+      return None;
+    }
+    let ty = match self.field_type(table, column) {
+      Some(ty) => ty,
+      None => {
+        self.warn(format!(
+          "the column `{}` of type `{}` was left out, the schema has no type for it",
+          at, column.data_type
+        ));
+        return None;
+      }
+    };
+
+    let default = if column.auto_increment && matches!(ty, FieldType::Int | FieldType::BigInt) {
+      Some(DefaultValue::Autoincrement)
+    } else {
+      match column.default.as_deref() {
+        None => None,
+        Some(raw) if raw.eq_ignore_ascii_case("NULL") => None,
+        Some(raw) => {
+          let default = default_value(raw, &ty);
+          if default.is_none() {
+            self.warn(format!(
+              "the default `{}` of `{}` was left out, the schema cannot describe it",
+              raw, at
+            ));
+          }
+          default
+        }
+      }
+    };
+
+    Some(Field {
+      name: column.name.clone(),
+      ty,
+      arity: if column.nullable {
+        Arity::Optional
+      } else {
+        Arity::Required
+      },
+      default,
+      relation: None,
+    })
+  }
+
+  fn field_type(&mut self, table: &Table, column: &Column) -> Option<FieldType> {
+    let data_type = column.data_type.to_lowercase();
+    let base = data_type
+      .split('(')
+      .next()
+      .unwrap_or_default()
+      .trim_end_matches(" unsigned")
+      .trim_end_matches(" without time zone")
+      .trim_end_matches(" with time zone")
+      .trim();
+    let approximated = |pull: &mut Pull| {
+      pull.warn(format!(
+        "the column `{}.{}` of type `{}` is described as String",
+        table.name, column.name, column.data_type
+      ));
+      Some(FieldType::String)
+    };
+
+    match self.dialect {
+      Dialect::Postgres => match base {
+        "smallint" | "integer" | "int" | "int2" | "int4" => Some(FieldType::Int),
+        "bigint" | "int8" => Some(FieldType::BigInt),
+        "real" | "double precision" | "float4" | "float8" => Some(FieldType::Float),
+        "numeric" | "decimal" => Some(FieldType::Decimal),
+        "text" | "character varying" | "varchar" | "character" | "char" | "bpchar" => {
+          Some(FieldType::String)
+        }
+        "boolean" | "bool" => Some(FieldType::Boolean),
+        "timestamp" | "timestamptz" | "date" => Some(FieldType::DateTime),
+        "json" | "jsonb" => Some(FieldType::Json),
+        "bytea" => Some(FieldType::Bytes),
+        "uuid" | "citext" | "xml" | "inet" | "cidr" | "macaddr" | "time" | "interval" => {
+          approximated(self)
+        }
+        _ => {
+          let name = column.data_type.trim_matches('"');
+          if let Some(e) = self.schema.find_enum(name) {
+            Some(FieldType::Enum(e.name.clone()))
+          } else if self.string_enums.iter().any(|e| e == name) {
+            Some(FieldType::String)
+          } else {
+            None
+          }
+        }
+      },
+      Dialect::MySQL => match base {
+        "tinyint" if data_type.starts_with("tinyint(1)") => Some(FieldType::Boolean),
+        "bool" | "boolean" => Some(FieldType::Boolean),
+        "tinyint" | "smallint" | "mediumint" | "int" | "integer" => Some(FieldType::Int),
+        "bigint" => Some(FieldType::BigInt),
+        "float" | "double" | "real" => Some(FieldType::Float),
+        "decimal" | "numeric" => Some(FieldType::Decimal),
+        "char" | "varchar" | "tinytext" | "text" | "mediumtext" | "longtext" => {
+          Some(FieldType::String)
+        }
+        "datetime" | "timestamp" | "date" => Some(FieldType::DateTime),
+        "json" => Some(FieldType::Json),
+        "binary" | "varbinary" | "tinyblob" | "blob" | "mediumblob" | "longblob" => {
+          Some(FieldType::Bytes)
+        }
+        "enum" => self.inline_enum(table, column),
+        "time" | "year" => approximated(self),
+        _ => None,
+      },
+      // SQLite goes by the affinity rules of its declared types
+      Dialect::SQLite => {
+        let has = |part: &str| data_type.contains(part);
+        if has("bool") {
+          Some(FieldType::Boolean)
+        } else if has("json") {
+          Some(FieldType::Json)
+        } else if has("date") || has("time") {
+          Some(FieldType::DateTime)
+        } else if has("bigint") {
+          Some(FieldType::BigInt)
+        } else if has("int") {
+          Some(FieldType::Int)
+        } else if has("char") || has("clob") || has("text") {
+          Some(FieldType::String)
+        } else if has("blob") || data_type.is_empty() {
+          Some(FieldType::Bytes)
+        } else if has("real") || has("floa") || has("doub") {
+          Some(FieldType::Float)
+        } else if has("dec") || has("numeric") {
+          Some(FieldType::Decimal)
+        } else {
+          None
+        }
+      }
+    }
+  }
+
+  /// MySQL spells the values out in the column type, `enum('a','b')`. The
+  /// enum is named after the table and column.
+  fn inline_enum(&mut self, table: &Table, column: &Column) -> Option<FieldType> {
+    let start = column.data_type.find('(')?;
+    let end = column.data_type.rfind(')')?;
+    let values: Vec<String> = column.data_type[start + 1..end]
+      .split(',')
+      .map(|value| value.trim().trim_matches('\'').replace("''", "'"))
+      .collect();
+    if !values.iter().all(|value| is_ident(value)) {
+      return None;
+    }
+    let mut name = format!("{}{}", pascal_case(&table.name), pascal_case(&column.name));
+    while self.schema.find_enum(&name).is_some() {
+      name.push('_');
+    }
+    self.schema.enums.push(Enum {
+      name: name.clone(),
+      values,
+    });
+    Some(FieldType::Enum(name))
+  }
+
+  /// Add a relation field for each foreign key of the table, and the field
+  /// pointing back to the table on the referenced model
+  fn relations(&mut self, table: &Table) {
+    for key in table.foreign_keys.iter() {
+      let described = |schema: &Schema, model: &str, columns: &[String]| {
+        schema.model(model).is_some_and(|model| {
+          columns
+            .iter()
+            .all(|c| model.field(c).is_some_and(|f| !f.is_relation()))
+        })
+      };
+      if !described(&self.schema, &table.name, &key.columns)
+        || !described(&self.schema, &key.referenced_table, &key.referenced_columns)
+      {
+        self.warn(format!(
+          "the foreign key {}on `{}` was left out, the columns it links were left out",
+          key
+            .name
+            .as_ref()
+            .map(|name| format!("`{}` ", name))
+            .unwrap_or_default(),
+          table.name
+        ));
+        continue;
+      }
+
+      let model = self.schema.model(&table.name).unwrap();
+      let optional = key
+        .columns
+        .iter()
+        .any(|c| model.field(c).is_some_and(|f| f.arity == Arity::Optional));
+      let implicit = if optional {
+        ReferentialAction::SetNull
+      } else {
+        ReferentialAction::Restrict
+      };
+      let on_delete = action(&key.on_delete).filter(|action| *action != implicit);
+      // a key that is also unique links to at most one row
+      let one_to_one = model.primary_key == key.columns
+        || model
+          .indexes
+          .iter()
+          .any(|i| i.unique && i.columns == key.columns);
+
+      let name = unique_name(model, &relation_name(&key.columns, &key.referenced_table));
+      let field = Field {
+        name,
+        ty: FieldType::Model(key.referenced_table.clone()),
+        arity: if optional {
+          Arity::Optional
+        } else {
+          Arity::Required
+        },
+        default: None,
+        relation: Some(Relation {
+          fields: key.columns.clone(),
+          references: key.referenced_columns.clone(),
+          on_delete,
+        }),
+      };
+      self.model_mut(&table.name).fields.push(field);
+
+      let target = self.schema.model(&key.referenced_table).unwrap();
+      let back = Field {
+        name: unique_name(target, &back_relation_name(&table.name, one_to_one)),
+        ty: FieldType::Model(table.name.clone()),
+        arity: if one_to_one {
+          Arity::Optional
+        } else {
+          Arity::List
+        },
+        default: None,
+        relation: None,
+      };
+      self.model_mut(&key.referenced_table).fields.push(back);
+    }
+  }
+
+  fn model_mut(&mut self, name: &str) -> &mut Model {
+    self
+      .schema
+      .models
+      .iter_mut()
+      .find(|model| model.name == name)
+      .unwrap()
+  }
+}
+
+fn action(sql: &str) -> Option<ReferentialAction> {
+  match sql.to_uppercase().as_str() {
+    "CASCADE" => Some(ReferentialAction::Cascade),
+    "RESTRICT" => Some(ReferentialAction::Restrict),
+    "NO ACTION" => Some(ReferentialAction::NoAction),
+    "SET NULL" => Some(ReferentialAction::SetNull),
+    "SET DEFAULT" => Some(ReferentialAction::SetDefault),
+    _ => None,
+  }
+}
+
+/// Read a column default written in SQL, e.g. `'draft'::text`, `(0)` or `CURRENT_TIMESTAMP`
+fn default_value(raw: &str, ty: &FieldType) -> Option<DefaultValue> {
+  let mut value = raw.trim();
+  while value.starts_with('(') && value.ends_with(')') {
+    value = value[1..value.len() - 1].trim();
+  }
+  // Postgres casts literals to the column type
+  if value.starts_with('\'') {
+    if let Some(end) = value.rfind('\'').filter(|end| *end > 0) {
+      value = &value[..=end];
+    }
+  }
+
+  let upper = value.to_uppercase();
+  if upper.starts_with("CURRENT_TIMESTAMP") || upper == "NOW()" || upper == "LOCALTIMESTAMP" {
+    return match ty {
+      FieldType::DateTime => Some(DefaultValue::Now),
+      _ => None,
+    };
+  }
+
+  let quoted = value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'');
+  let literal = if quoted {
+    value[1..value.len() - 1].replace("''", "'")
+  } else {
+    value.to_string()
+  };
+  match ty {
+    FieldType::Int | FieldType::BigInt => literal.parse().ok().map(DefaultValue::Int),
+    FieldType::Float | FieldType::Decimal => literal
+      .parse::<f64>()
+      .ok()
+      .map(|_| DefaultValue::Float(literal)),
+    FieldType::Boolean => match literal.to_lowercase().as_str() {
+      "true" | "1" | "t" => Some(DefaultValue::Boolean(true)),
+      "false" | "0" | "f" => Some(DefaultValue::Boolean(false)),
+      _ => None,
+    },
+    FieldType::Enum(_) if is_ident(&literal) => Some(DefaultValue::EnumValue(literal)),
+    // MySQL reports string defaults without quotes
+    FieldType::String | FieldType::Json if quoted || !literal.contains('(') => {
+      if literal.contains('\n') {
+        None
+      } else {
+        Some(DefaultValue::String(literal))
+      }
+    }
+    _ => None,
+  }
+}
+
+/// `author_id` or `authorId` becomes `author`, other keys are named after the
+/// referenced model
+fn relation_name(columns: &[String], target: &str) -> String {
+  if let [column] = columns {
+    for suffix in ["_id", "Id", "_ID"].iter() {
+      if column.len() > suffix.len() && column.ends_with(suffix) {
+        return column[..column.len() - suffix.len()].to_string();
+      }
+    }
+  }
+  lower_first(target)
+}
+
+fn back_relation_name(table: &str, one_to_one: bool) -> String {
+  let name = lower_first(table);
+  if one_to_one || name.ends_with('s') {
+    name
+  } else {
+    format!("{}s", name)
+  }
+}
+
+/// Number the name when the model already has a field of that name
+fn unique_name(model: &Model, name: &str) -> String {
+  let mut candidate = name.to_string();
+  let mut n = 2;
+  while model.field(&candidate).is_some() {
+    candidate = format!("{}{}", name, n);
+    n += 1;
+  }
+  candidate
+}
+
+fn lower_first(name: &str) -> String {
+  let mut chars = name.chars();
+  match chars.next() {
+    Some(first) => first.to_lowercase().chain(chars).collect(),
+    None => String::new(),
+  }
+}
+
+fn pascal_case(name: &str) -> String {
+  name
+    .split('_')
+    .map(|part| {
+      let mut chars = part.chars();
+      match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+      }
+    })
+    .collect()
+}
+
+/// Whether the name can be written in a schema file as is
+fn is_ident(name: &str) -> bool {
+  let mut chars = name.chars();
+  chars
+    .next()
+    .is_some_and(|first| first.is_alphabetic() || first == '_')
+    && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::parse;
+  use connection::{Connection, ConnectionOption, Datasource, Provider, Session};
+  use indoc::indoc;
+
+  #[test]
+  fn test_pull_sqlite() {
+    let mut connection = Connection::new(&ConnectionOption {
+      datasource: Datasource {
+        provider: Provider::SQLite,
+        url: "sqlite://:memory:".into(),
+      },
+      ..ConnectionOption::default()
+    });
+    connection.connect().unwrap();
+    let mut session = connection.session().unwrap();
+    session
+      .batch_execute(
+        "CREATE TABLE users ( \
+           id INTEGER PRIMARY KEY AUTOINCREMENT, \
+           email VARCHAR(255) NOT NULL UNIQUE, \
+           active BOOLEAN NOT NULL DEFAULT 1, \
+           created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP, \
+           location GEOMETRY \
+         ); \
+         CREATE TABLE posts ( \
+           id INTEGER NOT NULL PRIMARY KEY, \
+           author_id INTEGER REFERENCES users (id) ON DELETE CASCADE, \
+           title TEXT NOT NULL DEFAULT 'untitled', \
+           score REAL CHECK (score >= 0) \
+         ); \
+         CREATE INDEX posts_title ON posts (title); \
+         CREATE INDEX posts_scored ON posts (score) WHERE score > 0; \
+         CREATE INDEX posts_lower_title ON posts (author_id, lower(title)); \
+         CREATE TABLE logs (message TEXT); \
+         CREATE VIEW authors AS SELECT DISTINCT author_id FROM posts; \
+         CREATE TABLE spectre_migrations (name TEXT PRIMARY KEY);",
+      )
+      .unwrap();
+    let database = connection::introspect(&mut session, &Provider::SQLite).unwrap();
+
+    let pulled = pull(&database, Dialect::SQLite);
+    assert_eq!(
+      pulled.schema.to_string(),
+      indoc!(
+        r#"
+        model posts {
+          id        Int    @id @default(autoincrement())
+          author_id Int?
+          title     String @default("untitled")
+          score     Float?
+          author    users? @relation(fields: [author_id], references: [id], onDelete: Cascade)
+
+          @@index([title])
+        }
+
+        model users {
+          id         Int      @id @default(autoincrement())
+          email      String   @unique
+          active     Boolean  @default(true)
+          created_at DateTime @default(now())
+          posts      posts[]
+        }
+      "#
+      )
+    );
+    assert_eq!(parse(&pulled.schema.to_string()).unwrap(), pulled.schema);
+    assert_eq!(
+      pulled.warnings,
+      vec![
+        "the table `logs` was left out, it has no primary key",
+        "the index `posts_lower_title` on `posts` was left out, it covers expressions or columns left out",
+        "the partial index `posts_scored` on `posts` was left out",
+        "the check constraint on `posts` was left out: score >= 0",
+        "the column `users.location` of type `GEOMETRY` was left out, the schema has no type for it",
+        "the view `authors` was left out",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_default_value() {
+    let string = FieldType::String;
+    assert_eq!(
+      default_value("'it''s'::text", &string),
+      Some(DefaultValue::String("it's".into()))
+    );
+    assert_eq!(
+      default_value("(0)", &FieldType::Int),
+      Some(DefaultValue::Int(0))
+    );
+    assert_eq!(
+      default_value("'USER'::\"Role\"", &FieldType::Enum("Role".into())),
+      Some(DefaultValue::EnumValue("USER".into()))
+    );
+    assert_eq!(
+      default_value("CURRENT_TIMESTAMP(3)", &FieldType::DateTime),
+      Some(DefaultValue::Now)
+    );
+    assert_eq!(default_value("gen_random_uuid()", &string), None);
+  }
+}
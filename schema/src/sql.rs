@@ -0,0 +1,641 @@
+use crate::ast::*;
+use crate::diff::Change;
+use connection::DatabaseSchema;
+use migration::Dialect;
+
+/// Render changes as the statements of a migration script.
+///
+/// `schema` is the schema the changes lead to, column types are looked up in it.
+/// SQLite statements that copy tables commit their own transaction, see
+/// [`own_transaction`].
+pub fn statements(changes: &[Change], schema: &Schema, dialect: Dialect) -> Vec<String> {
+  render(
+    changes,
+    Sql {
+      schema,
+      database: None,
+      dialect,
+    },
+  )
+}
+
+/// Render changes to apply to `database` directly. Indexes, constraints and
+/// foreign keys are dropped by the names they have there, which need not be
+/// the names derived from the schema.
+pub fn statements_against(
+  changes: &[Change],
+  schema: &Schema,
+  database: &DatabaseSchema,
+  dialect: Dialect,
+) -> Vec<String> {
+  render(
+    changes,
+    Sql {
+      schema,
+      database: Some(database),
+      dialect,
+    },
+  )
+}
+
+fn render(changes: &[Change], sql: Sql) -> Vec<String> {
+  let dialect = sql.dialect;
+  let mut statements = Vec::new();
+  let own_transaction = own_transaction(changes, dialect);
+  if own_transaction {
+    // dropping a table with foreign keys enabled would run the `ON DELETE`
+    // actions of the tables referencing it, and the pragma is ignored inside
+    // a transaction
+    statements.push("PRAGMA foreign_keys = OFF".to_string());
+    statements.push("BEGIN".to_string());
+  }
+  for change in changes.iter() {
+    sql.change(change, &mut statements);
+  }
+  if own_transaction {
+    // fail before committing when the copied rows break a foreign key
+    statements.push(format!(
+      "CREATE TEMP TABLE {} (violations INTEGER NOT NULL CHECK (violations = 0))",
+      FOREIGN_KEY_CHECK
+    ));
+    statements.push(format!(
+      "INSERT INTO {} SELECT count(*) FROM pragma_foreign_key_check",
+      FOREIGN_KEY_CHECK
+    ));
+    statements.push(format!("DROP TABLE {}", FOREIGN_KEY_CHECK));
+    statements.push("COMMIT".to_string());
+    statements.push("PRAGMA foreign_keys = ON".to_string());
+  }
+  statements
+}
+
+/// Temporary table whose check fails when `PRAGMA foreign_key_check` reports violations
+const FOREIGN_KEY_CHECK: &str = "spectre_foreign_key_check";
+
+/// Whether the statements of the changes turn off SQLite foreign keys to copy
+/// or drop tables, committing their own transaction. They have to run outside
+/// of one, migrations holding them are marked `-- spectre:no-transaction`.
+pub fn own_transaction(changes: &[Change], dialect: Dialect) -> bool {
+  dialect == Dialect::SQLite
+    && changes
+      .iter()
+      .any(|change| matches!(change, Change::RebuildTable { .. } | Change::DropTable(_)))
+}
+
+struct Sql<'a> {
+  schema: &'a Schema,
+  /// The database the statements run against, when known
+  database: Option<&'a DatabaseSchema>,
+  dialect: Dialect,
+}
+
+/// How an index dropped from the schema exists in the database
+enum ExistingIndex {
+  Index(String),
+  /// A unique constraint, backed by an index that cannot be dropped by itself
+  Constraint(String),
+}
+
+impl<'a> Sql<'a> {
+  fn ident(&self, name: &str) -> String {
+    self.dialect.quote_ident(name)
+  }
+
+  fn idents(&self, names: &[String]) -> String {
+    names
+      .iter()
+      .map(|name| self.ident(name))
+      .collect::<Vec<_>>()
+      .join(", ")
+  }
+
+  fn change(&self, change: &Change, out: &mut Vec<String>) {
+    let dialect = self.dialect;
+    match change {
+      Change::CreateEnum(e) => out.push(self.create_enum(e)),
+      Change::DropEnum(e) => out.push(format!("DROP TYPE {}", self.ident(&e.name))),
+      Change::AlterEnum { from, to, columns } => {
+        let old = format!("{}_old", from.name);
+        out.push(format!(
+          "ALTER TYPE {} RENAME TO {}",
+          self.ident(&from.name),
+          self.ident(&old)
+        ));
+        out.push(self.create_enum(to));
+        for (table, field) in columns.iter() {
+          let alter = format!(
+            "ALTER TABLE {} ALTER COLUMN {}",
+            self.ident(table),
+            self.ident(&field.name)
+          );
+          // the default is typed with the old enum and has to go while converting
+          if field.default.is_some() {
+            out.push(format!("{} DROP DEFAULT", alter));
+          }
+          out.push(format!(
+            "{} TYPE {} USING ({}::text::{})",
+            alter,
+            self.ident(&to.name),
+            self.ident(&field.name),
+            self.ident(&to.name)
+          ));
+          if let Some(default) = self.default(field) {
+            out.push(format!("{} SET DEFAULT {}", alter, default));
+          }
+        }
+        out.push(format!("DROP TYPE {}", self.ident(&old)));
+      }
+      Change::CreateTable(model) => out.push(self.create_table(model, &model.name)),
+      Change::DropTable(model) => out.push(format!("DROP TABLE {}", self.ident(&model.name))),
+      Change::RebuildTable { from, to } => {
+        let temporary = format!("new_{}", to.name);
+        out.push(self.create_table(to, &temporary));
+        let common: Vec<String> = to
+          .columns()
+          .filter(|field| from.field(&field.name).is_some_and(|f| !f.is_relation()))
+          .map(|field| field.name.clone())
+          .collect();
+        if !common.is_empty() {
+          out.push(format!(
+            "INSERT INTO {} ({columns}) SELECT {columns} FROM {}",
+            self.ident(&temporary),
+            self.ident(&from.name),
+            columns = self.idents(&common)
+          ));
+        }
+        out.push(format!("DROP TABLE {}", self.ident(&from.name)));
+        out.push(format!(
+          "ALTER TABLE {} RENAME TO {}",
+          self.ident(&temporary),
+          self.ident(&to.name)
+        ));
+        for index in to.indexes.iter() {
+          out.push(self.create_index(&to.name, index));
+        }
+      }
+      Change::AddColumn { table, field } => out.push(format!(
+        "ALTER TABLE {} ADD COLUMN {}",
+        self.ident(table),
+        self.column(field, &[])
+      )),
+      Change::DropColumn { table, field } => out.push(format!(
+        "ALTER TABLE {} DROP COLUMN {}",
+        self.ident(table),
+        self.ident(&field.name)
+      )),
+      Change::AlterColumn { table, from, to } => match dialect {
+        Dialect::MySQL => out.push(format!(
+          "ALTER TABLE {} MODIFY COLUMN {}",
+          self.ident(table),
+          self.column(to, &[])
+        )),
+        _ => out.push(self.alter_column(table, from, to)),
+      },
+      Change::AlterPrimaryKey { table, columns } => {
+        let drop = match dialect {
+          Dialect::MySQL => "DROP PRIMARY KEY".to_string(),
+          _ => {
+            let name = self
+              .database
+              .and_then(|database| database.table(table)?.primary_key.as_ref()?.name.clone())
+              .unwrap_or_else(|| format!("{}_pkey", table));
+            format!("DROP CONSTRAINT {}", self.ident(&name))
+          }
+        };
+        out.push(format!(
+          "ALTER TABLE {} {}, ADD PRIMARY KEY ({})",
+          self.ident(table),
+          drop,
+          self.idents(columns)
+        ));
+      }
+      Change::CreateIndex { table, index } => out.push(self.create_index(table, index)),
+      Change::DropIndex { table, index } => out.push(match self.existing_index(table, index) {
+        ExistingIndex::Constraint(name) if dialect != Dialect::MySQL => format!(
+          "ALTER TABLE {} DROP CONSTRAINT {}",
+          self.ident(table),
+          self.ident(&name)
+        ),
+        ExistingIndex::Index(name) | ExistingIndex::Constraint(name) => match dialect {
+          Dialect::MySQL => format!("DROP INDEX {} ON {}", self.ident(&name), self.ident(table)),
+          _ => format!("DROP INDEX {}", self.ident(&name)),
+        },
+      }),
+      Change::AddForeignKey { table, key } => out.push(format!(
+        "ALTER TABLE {} ADD {}",
+        self.ident(table),
+        self.foreign_key(key)
+      )),
+      Change::DropForeignKey { table, key } => {
+        let kind = match dialect {
+          Dialect::MySQL => "FOREIGN KEY",
+          _ => "CONSTRAINT",
+        };
+        let name = self
+          .database
+          .and_then(|database| {
+            database
+              .table(table)?
+              .foreign_keys
+              .iter()
+              .find(|existing| {
+                existing.columns == key.columns && existing.referenced_table == key.referenced_table
+              })?
+              .name
+              .clone()
+          })
+          .unwrap_or_else(|| key.name.clone());
+        out.push(format!(
+          "ALTER TABLE {} DROP {} {}",
+          self.ident(table),
+          kind,
+          self.ident(&name)
+        ))
+      }
+    }
+  }
+
+  /// The index or unique constraint of the database covering the columns of
+  /// `index`, or the name derived from the schema when the database is unknown
+  fn existing_index(&self, table: &str, index: &Index) -> ExistingIndex {
+    let existing = self.database.and_then(|database| database.table(table));
+    if let Some(existing) = existing {
+      let found = existing.indexes.iter().find(|other| {
+        other.columns == index.columns
+          && other.unique == index.unique
+          && !other.expressions
+          && other.predicate.is_none()
+      });
+      if let Some(found) = found {
+        return ExistingIndex::Index(found.name.clone());
+      }
+      let constraint = existing
+        .unique_constraints
+        .iter()
+        .filter(|_| index.unique)
+        .find(|other| other.columns == index.columns)
+        .and_then(|other| other.name.clone());
+      if let Some(name) = constraint {
+        return ExistingIndex::Constraint(name);
+      }
+    }
+    ExistingIndex::Index(index.name(table))
+  }
+
+  fn create_enum(&self, e: &Enum) -> String {
+    format!(
+      "CREATE TYPE {} AS ENUM ({})",
+      self.ident(&e.name),
+      self.values(e)
+    )
+  }
+
+  fn values(&self, e: &Enum) -> String {
+    e.values
+      .iter()
+      .map(|value| self.dialect.quote(value))
+      .collect::<Vec<_>>()
+      .join(", ")
+  }
+
+  fn create_table(&self, model: &Model, name: &str) -> String {
+    let mut lines: Vec<String> = model
+      .columns()
+      .map(|field| self.column(field, &model.primary_key))
+      .collect();
+    if !self.inline_primary_key(model) {
+      lines.push(format!(
+        "CONSTRAINT {} PRIMARY KEY ({})",
+        self.ident(&format!("{}_pkey", model.name)),
+        self.idents(&model.primary_key)
+      ));
+    }
+    if self.dialect == Dialect::SQLite {
+      lines.extend(model.foreign_keys().iter().map(|key| self.foreign_key(key)));
+    }
+    format!(
+      "CREATE TABLE {} (\n  {}\n)",
+      self.ident(name),
+      lines.join(",\n  ")
+    )
+  }
+
+  /// SQLite only autoincrements an `INTEGER PRIMARY KEY` declared on the column
+  fn inline_primary_key(&self, model: &Model) -> bool {
+    self.dialect == Dialect::SQLite
+      && model.primary_key.len() == 1
+      && model
+        .field(&model.primary_key[0])
+        .is_some_and(Field::is_autoincrement)
+  }
+
+  /// The definition of a column, `primary_key` being the key of a table created with it
+  fn column(&self, field: &Field, primary_key: &[String]) -> String {
+    let mut column = format!("{} {}", self.ident(&field.name), self.column_type(field));
+    if field.arity == Arity::Required {
+      column.push_str(" NOT NULL");
+    }
+    if field.is_autoincrement() {
+      match self.dialect {
+        Dialect::MySQL => column.push_str(" AUTO_INCREMENT"),
+        Dialect::SQLite if primary_key == [field.name.clone()] => {
+          column.push_str(" PRIMARY KEY AUTOINCREMENT")
+        }
+        _ => {}
+      }
+    }
+    if let Some(default) = self.default(field) {
+      column.push_str(" DEFAULT ");
+      column.push_str(&default);
+    }
+    column
+  }
+
+  fn column_type(&self, field: &Field) -> String {
+    let serial = field.is_autoincrement();
+    let ty = match (self.dialect, &field.ty) {
+      (Dialect::Postgres, FieldType::Int) if serial => "SERIAL",
+      (Dialect::Postgres, FieldType::Int) => "INTEGER",
+      (Dialect::Postgres, FieldType::BigInt) if serial => "BIGSERIAL",
+      (Dialect::Postgres, FieldType::BigInt) => "BIGINT",
+      (Dialect::Postgres, FieldType::Float) => "DOUBLE PRECISION",
+      (Dialect::Postgres, FieldType::String) => "TEXT",
+      (Dialect::Postgres, FieldType::Boolean) => "BOOLEAN",
+      (Dialect::Postgres, FieldType::DateTime) => "TIMESTAMP(3)",
+      (Dialect::Postgres, FieldType::Json) => "JSONB",
+      (Dialect::Postgres, FieldType::Bytes) => "BYTEA",
+      (Dialect::Postgres, FieldType::Enum(name)) => return self.ident(name),
+      (Dialect::MySQL, FieldType::Int) => "INT",
+      (Dialect::MySQL, FieldType::BigInt) => "BIGINT",
+      (Dialect::MySQL, FieldType::Float) => "DOUBLE",
+      // the longest string an index can hold with utf8mb4
+      (Dialect::MySQL, FieldType::String) => "VARCHAR(191)",
+      (Dialect::MySQL, FieldType::Boolean) => "BOOLEAN",
+      (Dialect::MySQL, FieldType::DateTime) => "DATETIME(3)",
+      (Dialect::MySQL, FieldType::Json) => "JSON",
+      (Dialect::MySQL, FieldType::Bytes) => "LONGBLOB",
+      (Dialect::MySQL, FieldType::Enum(name)) => {
+        let values = self.schema.find_enum(name).map(|e| self.values(e));
+        return format!("ENUM({})", values.unwrap_or_default());
+      }
+      (_, FieldType::Decimal) if self.dialect != Dialect::SQLite => "DECIMAL(65,30)",
+      (Dialect::SQLite, FieldType::Int) | (Dialect::SQLite, FieldType::BigInt) => "INTEGER",
+      (Dialect::SQLite, FieldType::Float) => "REAL",
+      (Dialect::SQLite, FieldType::Decimal) => "DECIMAL",
+      (Dialect::SQLite, FieldType::Boolean) => "BOOLEAN",
+      (Dialect::SQLite, FieldType::DateTime) => "DATETIME",
+      (Dialect::SQLite, FieldType::Bytes) => "BLOB",
+      (Dialect::SQLite, _) => "TEXT",
+      (_, FieldType::Decimal) | (_, FieldType::Model(_)) => unreachable!("not a column"),
+    };
+    ty.to_string()
+  }
+
+  fn default(&self, field: &Field) -> Option<String> {
+    let default = match field.default.as_ref()? {
+      DefaultValue::Autoincrement => return None,
+      DefaultValue::Now if self.dialect == Dialect::MySQL => "CURRENT_TIMESTAMP(3)".to_string(),
+      DefaultValue::Now => "CURRENT_TIMESTAMP".to_string(),
+      DefaultValue::Int(value) => value.to_string(),
+      DefaultValue::Float(value) => value.clone(),
+      DefaultValue::Boolean(value) => match self.dialect {
+        Dialect::Postgres => value.to_string(),
+        _ => (*value as u8).to_string(),
+      },
+      DefaultValue::String(value) | DefaultValue::EnumValue(value) => self.dialect.quote(value),
+    };
+    Some(default)
+  }
+
+  /// Postgres alters a column one property at a time
+  fn alter_column(&self, table: &str, from: &Field, to: &Field) -> String {
+    let column = format!("ALTER COLUMN {}", self.ident(&to.name));
+    let mut actions = Vec::new();
+    if from.ty != to.ty {
+      // the serial pseudo types only exist when creating a column
+      let plain = Field {
+        default: None,
+        ..to.clone()
+      };
+      let ty = self.column_type(&plain);
+      actions.push(format!(
+        "{} SET DATA TYPE {} USING {}::{}",
+        column,
+        ty,
+        self.ident(&to.name),
+        ty
+      ));
+    }
+    if from.arity != to.arity {
+      let action = match to.arity {
+        Arity::Required => "SET NOT NULL",
+        _ => "DROP NOT NULL",
+      };
+      actions.push(format!("{} {}", column, action));
+    }
+    if from.default != to.default {
+      match self.default(to) {
+        Some(default) => actions.push(format!("{} SET DEFAULT {}", column, default)),
+        None if to.is_autoincrement() => {}
+        None => actions.push(format!("{} DROP DEFAULT", column)),
+      }
+    }
+    format!("ALTER TABLE {} {}", self.ident(table), actions.join(", "))
+  }
+
+  fn create_index(&self, table: &str, index: &Index) -> String {
+    format!(
+      "CREATE {}INDEX {} ON {}({})",
+      if index.unique { "UNIQUE " } else { "" },
+      self.ident(&index.name(table)),
+      self.ident(table),
+      self.idents(&index.columns)
+    )
+  }
+
+  fn foreign_key(&self, key: &ForeignKey) -> String {
+    format!(
+      "CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {}({}) ON DELETE {} ON UPDATE CASCADE",
+      self.ident(&key.name),
+      self.idents(&key.columns),
+      self.ident(&key.referenced_table),
+      self.idents(&key.referenced_columns),
+      key.on_delete.sql()
+    )
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{diff, parse};
+  use connection::{Connection, ConnectionOption, Datasource, Provider, Session};
+  use indoc::indoc;
+
+  const BLOG: &str = indoc!(
+    r#"
+    enum Role {
+      USER
+      ADMIN
+    }
+
+    model User {
+      id    Int    @id @default(autoincrement())
+      email String @unique
+      role  Role   @default(USER)
+      posts Post[]
+    }
+
+    model Post {
+      id       Int      @id @default(autoincrement())
+      title    String
+      draft    Boolean  @default(true)
+      authorId Int
+      author   User     @relation(fields: [authorId], references: [id], onDelete: Cascade)
+    }
+  "#
+  );
+
+  fn migrate(from: &str, to: &str, dialect: Dialect) -> Vec<String> {
+    let (from, to) = (parse(from).unwrap(), parse(to).unwrap());
+    statements(&diff(&from, &to, dialect), &to, dialect)
+  }
+
+  #[test]
+  fn test_create_postgres() {
+    let statements = migrate("", BLOG, Dialect::Postgres);
+    assert_eq!(
+      statements,
+      vec![
+        r#"CREATE TYPE "Role" AS ENUM ('USER', 'ADMIN')"#,
+        indoc!(
+          r#"
+          CREATE TABLE "User" (
+            "id" SERIAL NOT NULL,
+            "email" TEXT NOT NULL,
+            "role" "Role" NOT NULL DEFAULT 'USER',
+            CONSTRAINT "User_pkey" PRIMARY KEY ("id")
+          )"#
+        ),
+        indoc!(
+          r#"
+          CREATE TABLE "Post" (
+            "id" SERIAL NOT NULL,
+            "title" TEXT NOT NULL,
+            "draft" BOOLEAN NOT NULL DEFAULT true,
+            "authorId" INTEGER NOT NULL,
+            CONSTRAINT "Post_pkey" PRIMARY KEY ("id")
+          )"#
+        ),
+        r#"CREATE UNIQUE INDEX "User_email_key" ON "User"("email")"#,
+        r#"ALTER TABLE "Post" ADD CONSTRAINT "Post_authorId_fkey" FOREIGN KEY ("authorId") REFERENCES "User"("id") ON DELETE CASCADE ON UPDATE CASCADE"#,
+      ]
+    );
+  }
+
+  #[test]
+  fn test_alter_mysql() {
+    let next = BLOG
+      .replace("  ADMIN\n", "  ADMIN\n  GUEST\n")
+      .replace("  title    String\n", "  title    String?\n");
+    assert_eq!(
+      migrate(BLOG, &next, Dialect::MySQL),
+      vec![
+        "ALTER TABLE `User` MODIFY COLUMN `role` ENUM('USER', 'ADMIN', 'GUEST') NOT NULL DEFAULT 'USER'",
+        "ALTER TABLE `Post` MODIFY COLUMN `title` VARCHAR(191)",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_sqlite() {
+    let mut connection = Connection::new(&ConnectionOption {
+      datasource: Datasource {
+        provider: Provider::SQLite,
+        url: "sqlite://:memory:".into(),
+      },
+      ..ConnectionOption::default()
+    });
+    connection.connect().unwrap();
+    let mut session = connection.session().unwrap();
+    let run = |session: &mut dyn Session, statements: Vec<String>| {
+      statements
+        .iter()
+        .for_each(|statement| session.batch_execute(statement).unwrap())
+    };
+
+    run(&mut session, migrate("", BLOG, Dialect::SQLite));
+    session
+      .batch_execute("INSERT INTO \"User\" (email) VALUES ('a@example.com')")
+      .unwrap();
+    session
+      .batch_execute("INSERT INTO \"Post\" (title, \"authorId\") VALUES ('hello', 1)")
+      .unwrap();
+
+    // making the title optional copies the table
+    let next = BLOG.replace("  title    String\n", "  title    String?\n");
+    let statements = migrate(BLOG, &next, Dialect::SQLite);
+    assert_eq!(statements[0], "PRAGMA foreign_keys = OFF");
+    assert_eq!(statements.last().unwrap(), "PRAGMA foreign_keys = ON");
+    run(&mut session, statements);
+    let rows = session.query("SELECT title, draft FROM \"Post\"").unwrap();
+    assert_eq!(rows[0].get_string("title").unwrap(), "hello");
+
+    // and going back drops everything
+    run(&mut session, migrate(&next, "", Dialect::SQLite));
+    assert!(session.query("SELECT * FROM \"User\"").is_err());
+  }
+
+  #[test]
+  fn test_sqlite_rebuild_referenced_table() {
+    let mut connection = Connection::new(&ConnectionOption {
+      datasource: Datasource {
+        provider: Provider::SQLite,
+        url: "sqlite://:memory:".into(),
+      },
+      on_connect: Some(vec!["PRAGMA foreign_keys = ON".into()]),
+      ..ConnectionOption::default()
+    });
+    connection.connect().unwrap();
+    let mut session = connection.session().unwrap();
+    let run = |session: &mut dyn Session, statements: Vec<String>| {
+      statements
+        .iter()
+        .for_each(|statement| session.batch_execute(statement).unwrap())
+    };
+
+    run(&mut session, migrate("", BLOG, Dialect::SQLite));
+    session
+      .batch_execute(
+        "INSERT INTO \"User\" (email) VALUES ('a@example.com'); \
+         INSERT INTO \"Post\" (title, \"authorId\") VALUES ('hello', 1)",
+      )
+      .unwrap();
+
+    // copying `User` drops the table `Post` cascades from
+    let next = BLOG.replace("  email String @unique\n", "  email String? @unique\n");
+    run(&mut session, migrate(BLOG, &next, Dialect::SQLite));
+    let rows = session.query("SELECT title FROM \"Post\"").unwrap();
+    assert_eq!(rows.len(), 1);
+    let rows = session.query("PRAGMA foreign_keys").unwrap();
+    assert_eq!(rows[0].get_i64("foreign_keys"), Some(1));
+
+    // rows breaking a foreign key fail the copy and leave the table as it was
+    session
+      .batch_execute(
+        "PRAGMA foreign_keys = OFF; \
+         INSERT INTO \"Post\" (title, \"authorId\") VALUES ('orphan', 2); \
+         PRAGMA foreign_keys = ON",
+      )
+      .unwrap();
+    let statements = migrate(&next, BLOG, Dialect::SQLite);
+    let failed = statements
+      .iter()
+      .try_for_each(|statement| session.batch_execute(statement));
+    assert!(failed.is_err());
+    session.batch_execute("ROLLBACK").unwrap();
+    session
+      .batch_execute("INSERT INTO \"User\" (email) VALUES (NULL)")
+      .unwrap();
+  }
+}
@@ -1,21 +1,25 @@
 extern crate config;
 extern crate connection;
 extern crate directory;
+extern crate migration;
+extern crate spectre_macros;
 
 mod spectre;
 
 pub use self::spectre::Spectre;
 pub use config::Config;
 pub use connection::*;
+pub use migration::{EmbeddedFile, Migration, RustMigration, Tx};
+pub use spectre_macros::embed_migrations;
 use std::path::Path;
 
 /// Alias to [`Spectre::init()`] Creates a new instance of `Spectre`.
-pub fn init() -> Spectre {
+pub fn init() -> std::result::Result<Spectre, Box<dyn std::error::Error>> {
   Spectre::init()
 }
 
 /// Alias to [`Spectre::from()`] Creates a new instance of `Spectre`.
-pub fn init_from(root: &Path) -> Spectre {
+pub fn init_from(root: &Path) -> std::result::Result<Spectre, Box<dyn std::error::Error>> {
   Spectre::from(root)
 }
 
@@ -1,49 +1,81 @@
 pub use config::{Config, ConfigManager};
+use connection::ShutdownReport;
 pub use connection::{Connection, ConnectionManager, ConnectionOption, ConnectionOptionManager};
 use directory::find_project_root;
+use migration::{discover, embedded, Dialect, EmbeddedFile, Migration, Migrator};
+use std::env;
 use std::error::Error;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long dropping `Spectre` waits for running statements before cancelling them
+const DROP_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Debug)]
 pub struct Spectre {
+  // the project root migrations are read from, the working directory when unset
+  root: Option<PathBuf>,
   config: Config,
   option_manager: ConnectionOptionManager,
   manager: ConnectionManager,
+  // migrations written in Rust, run by `auto_migrate` along with the files
+  migrations: Vec<Migration>,
+  // migration files bundled into the binary, read instead of the migrations directory
+  embedded: Option<&'static [EmbeddedFile]>,
+  is_shutdown: bool,
 }
 
 impl Spectre {
   // create a new instance loading the config from root
-  pub fn from(root: &Path) -> Spectre {
+  pub fn from(root: &Path) -> Result<Spectre, Box<dyn Error>> {
     Spectre::configured(root.into())
   }
 
-  // create a new spectre using a config from `spectre.yaml` or `spectre.yml`.
-  pub fn init() -> Spectre {
-    let root = match find_project_root() {
-      Ok(path) => path,
-      Err(error) => {
-        println!("{}", error);
-        ::std::process::exit(1);
-      }
-    };
-    Spectre::configured(root)
+  // create a new spectre using a config from `spectre.yaml` or `spectre.yml`
+  // found in the project root
+  pub fn init() -> Result<Spectre, Box<dyn Error>> {
+    Spectre::configured(find_project_root()?)
   }
 
   pub fn custom(config: Config) -> Spectre {
     let option_manager = ConnectionOptionManager::new(&config.connections);
     let manager = ConnectionManager::new();
     Spectre {
+      root: None,
       config,
       option_manager,
       manager,
+      migrations: Vec::new(),
+      embedded: None,
+      is_shutdown: false,
     }
   }
 
+  // register a migration written in Rust, applied with the migration files by `auto_migrate`
+  #[inline(always)]
+  pub fn migration(mut self, migration: Migration) -> Self {
+    self.migrations.push(migration);
+    self
+  }
+
+  // run the migrations bundled by `embed_migrations!` in place of the migrations directory
+  #[inline(always)]
+  pub fn embedded_migrations(mut self, files: &'static [EmbeddedFile]) -> Self {
+    self.embedded = Some(files);
+    self
+  }
+
   // Connect to all connections provided
   pub fn connect(&mut self) -> Result<(), Box<dyn Error>> {
-    for _connection in self.manager.connections().values() {
-      unimplemented!()
-      // connection.connect().unwrap();
+    let names: Vec<String> = self
+      .config
+      .connections
+      .iter()
+      .map(|option| option.clone().name.unwrap_or_else(|| "default".into()))
+      .collect();
+
+    for name in names.iter() {
+      self.connect_to(name)?;
     }
     Ok(())
   }
@@ -53,14 +85,74 @@ impl Spectre {
   }
 
   // connect to a particular connection
-  pub fn connect_to(&self, name: &str) -> Result<(), Box<dyn Error>> {
-    match self.manager().get(name) {
-      Some(_connection) => {
-        // connection.connect().unwrap();
-        Ok(())
+  pub fn connect_to(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+    if !self.manager.has(name) {
+      match self.option_manager.get(name) {
+        Some(option) => self.manager.create(option)?,
+        None => return Err(connection::Error::UnknownConnection(name.to_string()).into()),
+      }
+    }
+
+    let connection = self.manager.get_mut(name).unwrap();
+    if !connection.is_connected() {
+      connection.connect()?;
+      if let Err(error) = self.auto_migrate(name) {
+        self.manager.get_mut(name).unwrap().disconnect()?;
+        return Err(error);
+      }
+    }
+    self.is_shutdown = false;
+    Ok(())
+  }
+
+  // apply the pending migrations of a connection that opted in with `auto_migrate`,
+  // logging each one at info level unless the connection turns `logging` off
+  // and the config `logging` does not list migrations
+  fn auto_migrate(&self, name: &str) -> Result<(), Box<dyn Error>> {
+    let option = self.option_manager.get(name);
+    if option.and_then(|o| o.auto_migrate) != Some(true) {
+      return Ok(());
+    }
+
+    let connection = self.manager.get(name).unwrap();
+    let failed = |error: &dyn Error| -> Box<dyn Error> {
+      format!("Auto migration of connection `{}` failed: {}", name, error).into()
+    };
+
+    let migrations = match self.embedded {
+      Some(files) => embedded(files),
+      None => {
+        let root = match self.root {
+          Some(ref root) => root.clone(),
+          None => env::current_dir()?,
+        };
+        discover(&root.join(self.config.migration_dir()))
       }
-      None => panic!("Connection with the provided name"),
     }
+    .map_err(|e| failed(&e))?;
+    let mut migrator = Migrator::new(Dialect::from(connection.provider()), migrations);
+    for migration in self.migrations.iter() {
+      migrator = migrator.migration(migration.clone());
+    }
+    if let Some(timeout) = self.config.migration_lock_timeout() {
+      migrator = migrator.lock_timeout(timeout);
+    }
+    let mut session = connection.session()?;
+    let runs = migrator.up(&mut session).map_err(|e| failed(&e))?;
+
+    let logging = option.and_then(|o| o.logging) != Some(false) || self.config.logs_migrations();
+    if !logging {
+      return Ok(());
+    }
+    for run in runs.iter() {
+      log::info!(
+        "applied migration {} on `{}` ({}ms)",
+        run.name,
+        name,
+        run.duration.as_millis()
+      );
+    }
+    Ok(())
   }
 
   // connect to a particular connection
@@ -78,6 +170,13 @@ impl Spectre {
     self.option_manager.get(name)
   }
 
+  // Stop handing out sessions and close every connection, statements still
+  // running when the timeout passes are cancelled
+  pub fn shutdown(&mut self, timeout: Duration) -> ShutdownReport {
+    self.is_shutdown = true;
+    self.manager.shutdown(timeout)
+  }
+
   #[inline(always)]
   pub fn config(&self) -> &Config {
     &self.config
@@ -94,14 +193,154 @@ impl Spectre {
   }
 
   #[inline]
-  fn configured(root: PathBuf) -> Spectre {
-    let config = ConfigManager::from(root).unwrap().config();
+  fn configured(root: PathBuf) -> Result<Spectre, Box<dyn Error>> {
+    let config = ConfigManager::from(root.clone())?.config();
     let option_manager = ConnectionOptionManager::new(&config.connections);
     let manager = ConnectionManager::new();
-    Spectre {
+    Ok(Spectre {
+      root: Some(root),
       config,
       option_manager,
       manager,
+      migrations: Vec::new(),
+      embedded: None,
+      is_shutdown: false,
+    })
+  }
+}
+
+impl Drop for Spectre {
+  fn drop(&mut self) {
+    if !self.is_shutdown {
+      let report = self.shutdown(DROP_SHUTDOWN_TIMEOUT);
+      if !report.is_clean() {
+        eprintln!("spectre: {}", report);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  extern crate tempfile;
+
+  use super::*;
+  use connection::{Datasource, Provider, Session};
+  use std::fs;
+
+  fn spectre(dir: &tempfile::TempDir) -> Spectre {
+    spectre_with(dir, ConnectionOption::default())
+  }
+
+  fn spectre_with(dir: &tempfile::TempDir, option: ConnectionOption) -> Spectre {
+    let option = ConnectionOption {
+      datasource: Datasource {
+        provider: Provider::SQLite,
+        url: format!("sqlite://{}", dir.path().join("test.db").display()),
+      },
+      auto_migrate: Some(true),
+      ..option
+    };
+    let mut spectre = Spectre::custom(Config::new().connections(&[option]));
+    spectre.root = Some(dir.path().to_path_buf());
+    spectre
+  }
+
+  #[test]
+  fn test_auto_migrate() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("migrations")).unwrap();
+    fs::write(
+      dir
+        .path()
+        .join("migrations/20261018120000_create_users.sql"),
+      "CREATE TABLE users (id INTEGER);",
+    )
+    .unwrap();
+
+    let mut spectre = spectre(&dir);
+    spectre.connect().unwrap();
+    let connection = spectre.get_connection("default").unwrap();
+    connection
+      .session()
+      .unwrap()
+      .query("SELECT * FROM users")
+      .unwrap();
+  }
+
+  /// Keeps the messages logged by the tests
+  struct Recorder(std::sync::Mutex<Vec<String>>);
+
+  impl log::Log for Recorder {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+      true
+    }
+
+    fn log(&self, record: &log::Record) {
+      let message = format!("{} {}", record.level(), record.args());
+      self.0.lock().unwrap().push(message);
     }
+
+    fn flush(&self) {}
+  }
+
+  static RECORDER: Recorder = Recorder(std::sync::Mutex::new(Vec::new()));
+
+  #[test]
+  fn test_auto_migrate_logs_applied_migrations() {
+    let _ = log::set_logger(&RECORDER);
+    log::set_max_level(log::LevelFilter::Info);
+    let logged = |name: &str| {
+      RECORDER
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|message| message.starts_with("INFO applied migration") && message.contains(name))
+    };
+
+    let migrate = |name: &str, logging: Option<bool>| {
+      let dir = tempfile::tempdir().unwrap();
+      fs::create_dir(dir.path().join("migrations")).unwrap();
+      fs::write(
+        dir.path().join("migrations").join(format!("{}.sql", name)),
+        "CREATE TABLE users (id INTEGER);",
+      )
+      .unwrap();
+      let option = ConnectionOption {
+        logging,
+        ..ConnectionOption::default()
+      };
+      spectre_with(&dir, option).connect().unwrap();
+    };
+    migrate("20261018120000_logged_by_default", None);
+    assert!(logged("20261018120000_logged_by_default"));
+    migrate("20261018120000_logging_turned_off", Some(false));
+    assert!(!logged("20261018120000_logging_turned_off"));
+  }
+
+  #[test]
+  fn test_malformed_config() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("spectre.yaml"), "connections: [").unwrap();
+    assert!(Spectre::from(dir.path()).is_err());
+  }
+
+  #[test]
+  fn test_auto_migrate_failure_fails_connect() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("migrations")).unwrap();
+    fs::write(
+      dir.path().join("migrations/20261018120000_broken.sql"),
+      "INSERT INTO missing VALUES (1);",
+    )
+    .unwrap();
+
+    let mut spectre = spectre(&dir);
+    let error = spectre.connect().unwrap_err();
+    assert!(error.to_string().starts_with(
+      "Auto migration of connection `default` failed: Migration `20261018120000_broken` failed"
+    ));
+    assert!(!spectre.get_connection("default").unwrap().is_connected());
   }
 }
@@ -0,0 +1,41 @@
+use spectre::{Config, ConnectionOption, Datasource, EmbeddedFile, Provider, Session, Spectre};
+
+static MIGRATIONS: &[EmbeddedFile] = spectre::embed_migrations!("fixtures/migrations");
+
+#[test]
+fn embedded_migrations() {
+  let paths: Vec<&str> = MIGRATIONS.iter().map(|file| file.path).collect();
+  assert_eq!(
+    paths,
+    vec![
+      "20261018120000_create_users.sql",
+      "20261018130000_create_posts/down.sql",
+      "20261018130000_create_posts/up.sql"
+    ]
+  );
+
+  let dir = tempfile::tempdir().unwrap();
+  let option = ConnectionOption {
+    datasource: Datasource {
+      provider: Provider::SQLite,
+      url: format!("sqlite://{}", dir.path().join("test.db").display()),
+    },
+    auto_migrate: Some(true),
+    ..ConnectionOption::default()
+  };
+  // no migrations directory is read, they come with the binary
+  let mut spectre =
+    Spectre::custom(Config::new().connections(&[option])).embedded_migrations(MIGRATIONS);
+  spectre.connect().unwrap();
+
+  let mut session = spectre
+    .get_connection("default")
+    .unwrap()
+    .session()
+    .unwrap();
+  session.query("SELECT * FROM posts").unwrap();
+  let rows = session
+    .query("SELECT name FROM spectre_migrations ORDER BY name")
+    .unwrap();
+  assert_eq!(rows.len(), 2);
+}